@@ -0,0 +1,412 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use mmo_packman_rust_server::server_protocol::PROTOCOL_VERSION;
+use rand::Rng;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about)]
+struct Cli {
+    #[arg(long, default_value = "ws://127.0.0.1:8080/ws")]
+    url: String,
+    #[arg(long, default_value_t = 20)]
+    clients: usize,
+    #[arg(long, default_value_t = 30)]
+    duration_secs: u64,
+    #[arg(long, default_value_t = 150)]
+    input_interval_ms: u64,
+    #[arg(long, default_value_t = 1_000)]
+    ping_interval_ms: u64,
+    #[arg(long, default_value_t = 10)]
+    connect_stagger_ms: u64,
+    #[arg(long, default_value_t = 0)]
+    ai: i64,
+    #[arg(long)]
+    minutes: Option<i64>,
+    #[arg(long)]
+    difficulty: Option<String>,
+    #[arg(long)]
+    no_start: bool,
+}
+
+#[derive(Debug)]
+enum ClientEvent {
+    Connected { latency_ms: f64 },
+    ConnectFailed { error: String },
+    Welcome,
+    Rtt { ms: f64 },
+    Received { state: bool },
+    DroppedStateFrames { count: u64 },
+    CatchUpSteps { count: u64 },
+    ServerError,
+    Disconnected { code: Option<u16> },
+}
+
+#[derive(Default)]
+struct Stats {
+    connect_latency_ms: Vec<f64>,
+    connect_failed: usize,
+    connect_errors: Vec<String>,
+    welcomed: usize,
+    rtt_ms: Vec<f64>,
+    messages: u64,
+    state_frames: u64,
+    dropped_state_frames: u64,
+    catch_up_steps: u64,
+    server_errors: u64,
+    disconnects: usize,
+    close_codes: Vec<u16>,
+}
+
+#[derive(Serialize)]
+struct Distribution {
+    samples: usize,
+    avg: f64,
+    p50: f64,
+    p95: f64,
+    max: f64,
+}
+
+#[derive(Serialize)]
+struct Report {
+    url: String,
+    clients: usize,
+    connected: usize,
+    #[serde(rename = "connectFailed")]
+    connect_failed: usize,
+    welcomed: usize,
+    #[serde(rename = "durationMs")]
+    duration_ms: u64,
+    #[serde(rename = "connectLatencyMs")]
+    connect_latency_ms: Distribution,
+    #[serde(rename = "rttMs")]
+    rtt_ms: Distribution,
+    messages: u64,
+    #[serde(rename = "messagesPerSec")]
+    messages_per_sec: f64,
+    #[serde(rename = "stateFrames")]
+    state_frames: u64,
+    #[serde(rename = "droppedStateFrames")]
+    dropped_state_frames: u64,
+    // Extra fixed steps the server ran to catch up; not lost frames.
+    #[serde(rename = "catchUpSteps")]
+    catch_up_steps: u64,
+    #[serde(rename = "serverErrors")]
+    server_errors: u64,
+    #[serde(rename = "serverDisconnects")]
+    server_disconnects: usize,
+    #[serde(rename = "closeCodes")]
+    close_codes: Vec<u16>,
+    #[serde(rename = "connectErrors")]
+    connect_errors: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Arc::new(Cli::parse());
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<ClientEvent>();
+    let (start_tx, start_rx) = watch::channel(false);
+    let (stop_tx, stop_rx) = watch::channel(false);
+
+    let mut handles = Vec::new();
+    for index in 0..cli.clients {
+        let client_cli = cli.clone();
+        let event_tx = event_tx.clone();
+        let start_rx = start_rx.clone();
+        let stop_rx = stop_rx.clone();
+        handles.push(tokio::spawn(async move {
+            run_client(index, client_cli, event_tx, start_rx, stop_rx).await;
+        }));
+        if cli.connect_stagger_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(cli.connect_stagger_ms)).await;
+        }
+    }
+    drop(event_tx);
+
+    let mut stats = Stats::default();
+    let settle_deadline = Instant::now() + Duration::from_secs(10);
+    while stats.welcomed + stats.connect_failed < cli.clients && Instant::now() < settle_deadline {
+        match tokio::time::timeout(Duration::from_millis(100), event_rx.recv()).await {
+            Ok(Some(event)) => apply_event(&mut stats, event),
+            Ok(None) => break,
+            Err(_) => {}
+        }
+    }
+    eprintln!(
+        "[loadtest] {} / {} clients welcomed; running for {}s",
+        stats.welcomed, cli.clients, cli.duration_secs
+    );
+
+    let _ = start_tx.send(true);
+    let started_at = Instant::now();
+    let deadline = started_at + Duration::from_secs(cli.duration_secs);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match tokio::time::timeout(remaining, event_rx.recv()).await {
+            Ok(Some(event)) => apply_event(&mut stats, event),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    let elapsed = started_at.elapsed();
+    let disconnects_before_stop = stats.disconnects;
+    let close_codes_before_stop = stats.close_codes.clone();
+
+    let _ = stop_tx.send(true);
+    for handle in handles {
+        let _ = handle.await;
+    }
+
+    let report = Report {
+        url: cli.url.clone(),
+        clients: cli.clients,
+        connected: stats.connect_latency_ms.len(),
+        connect_failed: stats.connect_failed,
+        welcomed: stats.welcomed,
+        duration_ms: elapsed.as_millis() as u64,
+        connect_latency_ms: distribution(&mut stats.connect_latency_ms),
+        rtt_ms: distribution(&mut stats.rtt_ms),
+        messages: stats.messages,
+        messages_per_sec: round2(stats.messages as f64 / elapsed.as_secs_f64().max(0.001)),
+        state_frames: stats.state_frames,
+        dropped_state_frames: stats.dropped_state_frames,
+        catch_up_steps: stats.catch_up_steps,
+        server_errors: stats.server_errors,
+        server_disconnects: disconnects_before_stop,
+        close_codes: close_codes_before_stop,
+        connect_errors: stats.connect_errors,
+    };
+    println!(
+        "{}",
+        serde_json::to_string(&report).expect("load test report should serialize")
+    );
+}
+
+fn apply_event(stats: &mut Stats, event: ClientEvent) {
+    match event {
+        ClientEvent::Connected { latency_ms } => stats.connect_latency_ms.push(latency_ms),
+        ClientEvent::ConnectFailed { error } => {
+            stats.connect_failed += 1;
+            if stats.connect_errors.len() < 8 {
+                stats.connect_errors.push(error);
+            }
+        }
+        ClientEvent::Welcome => stats.welcomed += 1,
+        ClientEvent::Rtt { ms } => stats.rtt_ms.push(ms),
+        ClientEvent::Received { state } => {
+            stats.messages += 1;
+            if state {
+                stats.state_frames += 1;
+            }
+        }
+        ClientEvent::DroppedStateFrames { count } => stats.dropped_state_frames += count,
+        ClientEvent::CatchUpSteps { count } => stats.catch_up_steps += count,
+        ClientEvent::ServerError => stats.server_errors += 1,
+        ClientEvent::Disconnected { code } => {
+            stats.disconnects += 1;
+            if let Some(code) = code {
+                stats.close_codes.push(code);
+            }
+        }
+    }
+}
+
+async fn run_client(
+    index: usize,
+    cli: Arc<Cli>,
+    events: mpsc::UnboundedSender<ClientEvent>,
+    mut start_rx: watch::Receiver<bool>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let connect_started = Instant::now();
+    let socket = match tokio_tungstenite::connect_async(cli.url.as_str()).await {
+        Ok((socket, _)) => socket,
+        Err(error) => {
+            let _ = events.send(ClientEvent::ConnectFailed {
+                error: error.to_string(),
+            });
+            return;
+        }
+    };
+    let _ = events.send(ClientEvent::Connected {
+        latency_ms: connect_started.elapsed().as_secs_f64() * 1000.0,
+    });
+
+    let (mut sender, mut receiver) = socket.split();
+    let hello = json!({
+        "type": "hello",
+        "name": format!("load-{:03}", index + 1),
+        "protocolVersion": PROTOCOL_VERSION,
+    });
+    if sender
+        .send(Message::Text(hello.to_string().into()))
+        .await
+        .is_err()
+    {
+        let _ = events.send(ClientEvent::Disconnected { code: None });
+        return;
+    }
+
+    let clock = Instant::now();
+    let mut player_id: Option<String> = None;
+    let mut is_host = false;
+    let mut started = false;
+    let mut last_frame: Option<(u64, u64)> = None;
+    let mut input_timer =
+        tokio::time::interval(Duration::from_millis(cli.input_interval_ms.max(1)));
+    let mut ping_timer = tokio::time::interval(Duration::from_millis(cli.ping_interval_ms.max(1)));
+
+    loop {
+        tokio::select! {
+            _ = stop_rx.changed() => {
+                let _ = sender.send(Message::Close(None)).await;
+                return;
+            }
+            changed = start_rx.changed(), if !started => {
+                if changed.is_err() {
+                    continue;
+                }
+                started = true;
+                if is_host && !cli.no_start {
+                    let mut start = json!({ "type": "lobby_start", "aiPlayerCount": cli.ai });
+                    if let Some(minutes) = cli.minutes {
+                        start["timeLimitMinutes"] = json!(minutes);
+                    }
+                    if let Some(difficulty) = cli.difficulty.as_deref() {
+                        start["difficulty"] = json!(difficulty);
+                    }
+                    if sender.send(Message::Text(start.to_string().into())).await.is_err() {
+                        let _ = events.send(ClientEvent::Disconnected { code: None });
+                        return;
+                    }
+                }
+            }
+            _ = input_timer.tick(), if started => {
+                let dir = ["up", "down", "left", "right"][rand::rng().random_range(0..4)];
+                let input = json!({ "type": "input", "dir": dir });
+                if sender.send(Message::Text(input.to_string().into())).await.is_err() {
+                    let _ = events.send(ClientEvent::Disconnected { code: None });
+                    return;
+                }
+            }
+            _ = ping_timer.tick(), if started => {
+                let ping = json!({ "type": "ping", "t": clock.elapsed().as_secs_f64() * 1000.0 });
+                if sender.send(Message::Text(ping.to_string().into())).await.is_err() {
+                    let _ = events.send(ClientEvent::Disconnected { code: None });
+                    return;
+                }
+            }
+            received = receiver.next() => {
+                let message = match received {
+                    Some(Ok(message)) => message,
+                    Some(Err(_)) | None => {
+                        let _ = events.send(ClientEvent::Disconnected { code: None });
+                        return;
+                    }
+                };
+                let text = match message {
+                    Message::Text(text) => text.to_string(),
+                    Message::Binary(raw) => String::from_utf8_lossy(&raw).to_string(),
+                    Message::Close(frame) => {
+                        let _ = events.send(ClientEvent::Disconnected {
+                            code: frame.map(|frame| u16::from(frame.code)),
+                        });
+                        return;
+                    }
+                    _ => continue,
+                };
+                let Ok(value) = serde_json::from_str::<Value>(&text) else {
+                    continue;
+                };
+                let message_type = value.get("type").and_then(Value::as_str).unwrap_or("");
+                let _ = events.send(ClientEvent::Received { state: message_type == "state" });
+                match message_type {
+                    "welcome" => {
+                        player_id = value
+                            .get("playerId")
+                            .and_then(Value::as_str)
+                            .map(str::to_string);
+                        is_host = value.get("isHost").and_then(Value::as_bool).unwrap_or(false);
+                        let _ = events.send(ClientEvent::Welcome);
+                    }
+                    "lobby" if player_id.is_some() => {
+                        let host_id = value.get("hostId").and_then(Value::as_str);
+                        is_host = host_id == player_id.as_deref();
+                    }
+                    "pong" => {
+                        if let Some(sent) = value.get("t").and_then(Value::as_f64) {
+                            let ms = clock.elapsed().as_secs_f64() * 1000.0 - sent;
+                            let _ = events.send(ClientEvent::Rtt { ms });
+                        }
+                    }
+                    "state" => {
+                        let snapshot = value.get("snapshot");
+                        let field = |name: &str| {
+                            snapshot
+                                .and_then(|snapshot| snapshot.get(name))
+                                .and_then(Value::as_u64)
+                        };
+                        let (Some(frame), Some(tick)) = (field("frame"), field("tick")) else {
+                            continue;
+                        };
+                        if let Some((previous_frame, previous_tick)) = last_frame {
+                            // `tick` jumps by several steps when the server catches up, so only
+                            // gaps in `frame` are lost frames.
+                            if frame > previous_frame + 1 {
+                                let _ = events.send(ClientEvent::DroppedStateFrames {
+                                    count: frame - previous_frame - 1,
+                                });
+                            } else if tick > previous_tick + 1 {
+                                let _ = events.send(ClientEvent::CatchUpSteps {
+                                    count: tick - previous_tick - 1,
+                                });
+                            }
+                        }
+                        last_frame = Some((frame, tick));
+                    }
+                    "game_over" | "game_init" => last_frame = None,
+                    "error" => {
+                        let _ = events.send(ClientEvent::ServerError);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn distribution(samples: &mut [f64]) -> Distribution {
+    if samples.is_empty() {
+        return Distribution {
+            samples: 0,
+            avg: 0.0,
+            p50: 0.0,
+            p95: 0.0,
+            max: 0.0,
+        };
+    }
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let percentile = |ratio: f64| {
+        let index = ((samples.len() - 1) as f64 * ratio).round() as usize;
+        round2(samples[index])
+    };
+    Distribution {
+        samples: samples.len(),
+        avg: round2(samples.iter().sum::<f64>() / samples.len() as f64),
+        p50: percentile(0.5),
+        p95: percentile(0.95),
+        max: round2(samples[samples.len() - 1]),
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -1,55 +1,196 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-
-use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
-use axum::extract::{Query, State};
-use axum::response::IntoResponse;
-use axum::routing::get;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::body::Bytes;
+use axum::extract::ws::{CloseFrame, Message, Utf8Bytes, WebSocket, WebSocketUpgrade};
+use axum::extract::{OriginalUri, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
-use futures_util::{SinkExt, StreamExt};
+use clap::Parser;
+use futures_util::{Sink, SinkExt, StreamExt};
+use mmo_packman_rust_server::chat_manager::{ChatManager, ChatManagerOptions, PostChatInput};
 use mmo_packman_rust_server::constants::TICK_MS;
-use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions};
-use mmo_packman_rust_server::ping_manager::{PingManager, PingManagerOptions, PlacePingInput};
+use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions, InputAck, SquadPlannerMode};
+use mmo_packman_rust_server::flood_guard::{
+    FloodCounters, FloodGuard, FloodGuardOptions, FloodVerdict, INVALID_MESSAGE_TYPE,
+};
+use mmo_packman_rust_server::match_checkpoint::{
+    remove_checkpoint, CheckpointOptions, MatchCheckpoint,
+};
+use mmo_packman_rust_server::matchmaking::{FormedMatch, MatchmakingQueue, QueuePreferences};
+use mmo_packman_rust_server::metrics::{MetricsGauges, ServerMetrics};
+use mmo_packman_rust_server::ping_manager::{
+    PingManager, PlacePingInput, PING_REJECTED_INVALID_TARGET, PING_REJECTED_SPECTATOR,
+};
 use mmo_packman_rust_server::ranking_store::RankingStore;
-use mmo_packman_rust_server::server_protocol::{parse_client_message, ParsedClientMessage};
+use mmo_packman_rust_server::rules::BalanceRules;
+use mmo_packman_rust_server::server_config::{RoomLimits, ServerConfig};
+use mmo_packman_rust_server::server_log::{self, LogIds, LogOptions};
+use mmo_packman_rust_server::server_protocol::{
+    negotiate_capabilities, parse_client_message, ParsedClientMessage, LEGACY_PROTOCOL_VERSION,
+    MIN_PROTOCOL_VERSION, PROTOCOL_TOO_OLD_CLOSE_CODE, PROTOCOL_VERSION,
+};
 use mmo_packman_rust_server::server_utils::{
-    is_supported_room, normalize_ai_count, normalize_time_limit_ms, parse_ranking_limit,
-    player_order_key, sanitize_name,
+    bearer_token_matches, is_supported_room, normalize_ai_count, normalize_time_limit_ms,
+    parse_ranking_limit, player_order_key, sanitize_name, secret_matches, LobbyLimits,
+};
+use mmo_packman_rust_server::tick_clock::{TickClock, TickClockOptions, TickCounters};
+use mmo_packman_rust_server::types::{
+    AiSkill, BotObservation, Difficulty, GameOverReason, Snapshot, StartPlayer,
 };
-use mmo_packman_rust_server::types::{Difficulty, StartPlayer};
+use mmo_packman_rust_server::wire_codec::{encode_binary, is_binary_message, BINARY_CAPABILITY};
 use rand::distr::Alphanumeric;
 use rand::Rng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite;
 use tower_http::services::{ServeDir, ServeFile};
 
 static NEXT_ID: AtomicU64 = AtomicU64::new(1);
 
+const BOT_OBSERVATION_RADIUS: i32 = 7;
+const KICKED_CLOSE_CODE: u16 = 4003;
+const ADMIN_LOG_TARGET: &str = "admin";
+const SHUTDOWN_CLOSE_CODE: u16 = 1001;
+const SHUTDOWN_SEND_TIMEOUT: Duration = Duration::from_secs(2);
+const QUEUE_STATUS_INTERVAL_MS: u64 = 1_000;
+
 type SharedState = Arc<Mutex<ServerState>>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct LobbyPlayerInternal {
     id: String,
     name: String,
     connected: bool,
     ai: bool,
+    bot: bool,
     spectator: bool,
+    ready: bool,
     reconnect_token: String,
+    // Set while a queued match this member was not matched into is running: the `spectator`
+    // flag to go back to once it ends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spectator_after_match: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LobbySettings {
+    difficulty: Difficulty,
+    ai_player_count: usize,
+    time_limit_minutes: Option<u64>,
+    ai_skill: AiSkill,
+    require_ready: bool,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Normal,
+            ai_player_count: 0,
+            time_limit_minutes: None,
+            ai_skill: AiSkill::default(),
+            require_ready: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct LobbySettingsUpdate {
+    difficulty: Option<Difficulty>,
+    ai_player_count: Option<i64>,
+    // `Some(None)` goes back to the difficulty's default time limit.
+    time_limit_minutes: Option<Option<i64>>,
+    ai_skill: Option<AiSkill>,
+    require_ready: Option<bool>,
+}
+
+impl LobbySettings {
+    fn apply(&mut self, update: LobbySettingsUpdate, limits: &LobbyLimits) {
+        if let Some(difficulty) = update.difficulty {
+            self.difficulty = difficulty;
+        }
+        if update.ai_player_count.is_some() {
+            self.ai_player_count = normalize_ai_count(update.ai_player_count, limits);
+        }
+        match update.time_limit_minutes {
+            None => {}
+            Some(None) => self.time_limit_minutes = None,
+            Some(minutes) => {
+                if let Some(limit_ms) = normalize_time_limit_ms(minutes, limits) {
+                    self.time_limit_minutes = Some(limit_ms / 60_000);
+                }
+            }
+        }
+        if let Some(ai_skill) = update.ai_skill {
+            self.ai_skill = ai_skill;
+        }
+        if let Some(require_ready) = update.require_ready {
+            self.require_ready = require_ready;
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "difficulty": self.difficulty,
+            "aiPlayerCount": self.ai_player_count,
+            "timeLimitMinutes": self.time_limit_minutes,
+            "aiSkill": self.ai_skill,
+            "requireReady": self.require_ready,
+        })
+    }
 }
 
-#[derive(Clone)]
 struct ClientContext {
     tx: mpsc::Sender<OutboundMessage>,
     player_id: Option<String>,
+    decision_window: Option<DecisionWindow>,
+    protocol_version: u32,
+    capabilities: Vec<String>,
+    binary: bool,
+    // Hands the writer task the shutdown notice and close frame so they skip a full send queue.
+    shutdown: Option<oneshot::Sender<Vec<OutboundMessage>>>,
+}
+
+// A bot's window to answer its latest observation.
+#[derive(Clone, Copy, Debug)]
+enum DecisionWindow {
+    // Handed to the tick fan-out; the deadline is set once the send result is known.
+    InFlight,
+    Until(Instant),
+}
+
+#[derive(Clone, Debug)]
+struct BotOptions {
+    api_token: Option<String>,
+    decision_deadline_ms: u64,
+}
+
+impl BotOptions {
+    // The token is a secret and only ever read from `BOT_API_TOKEN`, never from the config.
+    fn new(decision_deadline_ms: u64) -> Self {
+        Self {
+            api_token: std::env::var("BOT_API_TOKEN")
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+            decision_deadline_ms,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
+// Payloads are reference-counted so one encoded frame can be queued to every client.
 enum OutboundMessage {
-    Text(String),
+    Text(Utf8Bytes),
+    Binary(Bytes),
     Close { code: u16, reason: String },
 }
 
@@ -64,23 +205,86 @@ struct ServerState {
     lobby_players: HashMap<String, LobbyPlayerInternal>,
     active_client_by_player_id: HashMap<String, String>,
     host_id: Option<String>,
+    // Reconnect token -> player id of members who left the lobby, so rejoining with the token
+    // keeps the id and with it any mute.
+    departed_players: HashMap<String, String>,
+    kicked_tokens: HashSet<String>,
+    lobby_settings: LobbySettings,
     game: Option<GameEngine>,
+    match_id: Option<String>,
     running_ai_count: usize,
     ranking_store: RankingStore,
     ping_manager: PingManager,
+    chat_manager: ChatManager,
+    matchmaking: MatchmakingQueue,
+    queue_status_sent_at: u64,
+    flood_options: FloodGuardOptions,
+    flood_counters: Arc<FloodCounters>,
+    tick_options: TickClockOptions,
+    tick_counters: Arc<TickCounters>,
+    metrics: Arc<ServerMetrics>,
+    bot_options: BotOptions,
+    lobby_limits: LobbyLimits,
+    room_limits: RoomLimits,
+    client_channel_size: usize,
+    rules: BalanceRules,
+    squad_planner: SquadPlannerMode,
+    shutting_down: bool,
+    checkpoint_tx: Option<mpsc::UnboundedSender<CheckpointCommand>>,
 }
 
 impl ServerState {
-    fn new(ranking_store: RankingStore) -> Self {
+    fn new(
+        config: &ServerConfig,
+        rules: BalanceRules,
+        bot_options: BotOptions,
+        chat_options: ChatManagerOptions,
+    ) -> Self {
         Self {
             clients: HashMap::new(),
             lobby_players: HashMap::new(),
             active_client_by_player_id: HashMap::new(),
             host_id: None,
+            departed_players: HashMap::new(),
+            kicked_tokens: HashSet::new(),
+            lobby_settings: LobbySettings::default(),
             game: None,
+            match_id: None,
             running_ai_count: 0,
-            ranking_store,
-            ping_manager: PingManager::new(PingManagerOptions::default()),
+            ranking_store: RankingStore::new(config.ranking_db_path.clone()),
+            ping_manager: PingManager::new(config.pings),
+            chat_manager: ChatManager::new(chat_options),
+            matchmaking: MatchmakingQueue::new(config.matchmaking),
+            queue_status_sent_at: 0,
+            flood_options: config.flood.clone(),
+            flood_counters: Arc::new(FloodCounters::default()),
+            tick_options: config.tick,
+            tick_counters: Arc::new(TickCounters::default()),
+            metrics: Arc::new(ServerMetrics::default()),
+            bot_options,
+            lobby_limits: config.lobby,
+            room_limits: config.room,
+            client_channel_size: config.client_channel_size,
+            rules,
+            squad_planner: config.squad_planner,
+            shutting_down: false,
+            checkpoint_tx: None,
+        }
+    }
+}
+
+impl ServerState {
+    // The player is looked up from the connection when not given explicitly.
+    fn log_ids<'a>(&'a self, client_id: Option<&'a str>, player_id: Option<&'a str>) -> LogIds<'a> {
+        let player_id = player_id.or_else(|| {
+            client_id
+                .and_then(|client_id| self.clients.get(client_id))
+                .and_then(|client| client.player_id.as_deref())
+        });
+        LogIds {
+            client_id,
+            player_id,
+            match_id: self.match_id.as_deref(),
         }
     }
 }
@@ -90,60 +294,212 @@ struct RankingQuery {
     limit: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Cli {
+    #[arg(long)]
+    config: Option<PathBuf>,
+    #[arg(long)]
+    bind: Option<IpAddr>,
+    #[arg(long)]
+    port: Option<u16>,
+    #[arg(long = "ranking-db")]
+    ranking_db: Option<PathBuf>,
+    #[arg(long = "static-dir")]
+    static_dir: Option<PathBuf>,
+    #[arg(long)]
+    rules: Option<PathBuf>,
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    #[arg(long = "channel-size")]
+    channel_size: Option<usize>,
+    #[arg(long = "max-players")]
+    max_players: Option<usize>,
+    #[arg(long = "max-spectators")]
+    max_spectators: Option<usize>,
+    #[arg(long = "max-ai-players")]
+    max_ai_players: Option<usize>,
+    #[arg(long = "print-config")]
+    print_config: bool,
+}
+
+// defaults < config file < environment < command line.
+fn load_config(cli: &Cli) -> Result<ServerConfig, String> {
+    let mut config = match &cli.config {
+        Some(path) => ServerConfig::load(path)?,
+        None => ServerConfig::default(),
+    };
+    config.apply_env(|name| std::env::var(name).ok())?;
+    if let Some(bind) = cli.bind {
+        config.bind_address = bind;
+    }
+    if let Some(port) = cli.port {
+        config.port = port;
+    }
+    if let Some(path) = &cli.ranking_db {
+        config.ranking_db_path = path.clone();
+    }
+    if let Some(path) = &cli.static_dir {
+        config.static_dir = Some(path.clone());
+    }
+    if let Some(path) = &cli.rules {
+        config.rules_file = Some(path.clone());
+    }
+    if let Some(path) = &cli.checkpoint {
+        config.checkpoint.path = path.clone();
+    }
+    if let Some(size) = cli.channel_size {
+        config.client_channel_size = size;
+    }
+    if let Some(max) = cli.max_players {
+        config.room.max_players = max;
+    }
+    if let Some(max) = cli.max_spectators {
+        config.room.max_spectators = max;
+    }
+    if let Some(max) = cli.max_ai_players {
+        config.lobby.max_ai_players = max;
+    }
+    config.validate()?;
+    Ok(config)
+}
+
 #[tokio::main]
 async fn main() {
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|value| value.parse::<u16>().ok())
-        .unwrap_or(8080);
-
-    let ranking_path = std::env::var("RANKING_DB_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from(".data/ranking.json"));
-
-    let state = Arc::new(Mutex::new(ServerState::new(RankingStore::new(
-        ranking_path,
-    ))));
+    server_log::init(LogOptions::from_env());
+    let cli = Cli::parse();
+    let (config, rules) = match load_config(&cli)
+        .and_then(|config| config.load_rules().map(|rules| (config, rules)))
+    {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            server_log::error(
+                "server",
+                "invalid configuration",
+                LogIds::default(),
+                json!({ "error": error }),
+            );
+            std::process::exit(2);
+        }
+    };
+    if cli.print_config {
+        match config.to_toml() {
+            Ok(text) => print!("{text}"),
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let bot_options = BotOptions::new(config.bot_decision_deadline_ms);
+    if bot_options.api_token.is_some() {
+        server_log::info(
+            "server",
+            "external bot protocol enabled",
+            LogIds::default(),
+            json!({ "decisionDeadlineMs": bot_options.decision_deadline_ms }),
+        );
+    }
+
+    let state = Arc::new(Mutex::new(ServerState::new(
+        &config,
+        rules,
+        bot_options,
+        config.chat.clone(),
+    )));
+    let checkpoint_writer = if config.checkpoint.enabled {
+        let mut guard = state.lock().await;
+        restore_checkpoint(&mut guard, &config.checkpoint);
+        let (tx, writer) = start_checkpoint_writer(config.checkpoint.path.clone());
+        guard.checkpoint_tx = Some(tx);
+        Some(writer)
+    } else {
+        None
+    };
     start_tick_loop(state.clone());
+    if checkpoint_writer.is_some() {
+        start_checkpoint_loop(
+            state.clone(),
+            Duration::from_millis(config.checkpoint.interval_ms),
+        );
+    }
+    let admin_token = config.admin.token.as_deref().map(Arc::<str>::from);
+    if admin_token.is_some() {
+        server_log::info(
+            "server",
+            "admin API enabled",
+            LogIds::default(),
+            Value::Null,
+        );
+    }
+    let shutdown_state = state.clone();
+    let shutdown_grace = Duration::from_millis(config.shutdown_grace_ms);
 
     let app = Router::new()
         .route("/healthz", get(healthz))
+        .route("/metrics", get(metrics_handler))
         .route("/api/ranking", get(ranking_handler))
+        .route("/api/ranking/bots", get(bot_ranking_handler))
+        .route("/api/flood-stats", get(flood_stats_handler))
+        .route("/api/tick-stats", get(tick_stats_handler))
+        .route("/api/status", get(status_handler))
         .route("/ws", get(ws_handler))
+        .nest("/api/admin", admin_router(admin_token))
         .with_state(state);
 
-    let app = if let Some(static_dir) = resolve_static_dir() {
+    let app = if let Some(static_dir) = resolve_static_dir(config.static_dir.as_deref()) {
         let index_file = static_dir.join("index.html");
-        println!(
-            "[server] static file root: {}",
-            static_dir.to_string_lossy()
+        server_log::info(
+            "server",
+            "serving static files",
+            LogIds::default(),
+            json!({ "root": static_dir.to_string_lossy() }),
         );
         app.fallback_service(
             ServeDir::new(static_dir).not_found_service(ServeFile::new(index_file)),
         )
     } else {
-        eprintln!(
-            "[server] static file root not found. run `npm run build` to generate dist/client."
+        server_log::warn(
+            "server",
+            "static file root not found. run `npm run build` to generate dist/client.",
+            LogIds::default(),
+            Value::Null,
         );
         app
     };
 
-    let bind_addr = format!("0.0.0.0:{port}");
-    let listener = tokio::net::TcpListener::bind(&bind_addr)
+    let bind_addr = SocketAddr::new(config.bind_address, config.port);
+    let listener = tokio::net::TcpListener::bind(bind_addr)
         .await
         .expect("failed to bind server socket");
 
-    println!("[server] listening on :{port}");
+    server_log::info(
+        "server",
+        "listening",
+        LogIds::default(),
+        json!({ "addr": bind_addr.to_string() }),
+    );
     axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            let signal = shutdown_signal().await;
+            server_log::info(
+                "server",
+                "shutdown signal received",
+                LogIds::default(),
+                json!({ "signal": signal }),
+            );
+            shut_down(shutdown_state, shutdown_grace, checkpoint_writer).await;
+        })
         .await
         .expect("server runtime failed");
 }
 
-fn resolve_static_dir() -> Option<PathBuf> {
-    if let Ok(raw) = std::env::var("STATIC_DIR") {
-        let path = PathBuf::from(raw);
+fn resolve_static_dir(configured: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = configured {
         if path.join("index.html").is_file() {
-            return Some(path);
+            return Some(path.to_path_buf());
         }
     }
 
@@ -160,6 +516,36 @@ async fn healthz() -> impl IntoResponse {
     Json(json!({ "ok": true }))
 }
 
+async fn metrics_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let (metrics, gauges) = {
+        let guard = state.lock().await;
+        let connected_members = || {
+            guard
+                .lobby_players
+                .values()
+                .filter(|member| member.connected)
+        };
+        let gauges = MetricsGauges {
+            clients: guard.clients.len(),
+            spectators: connected_members()
+                .filter(|member| member.spectator)
+                .count(),
+            running_games: usize::from(guard.game.is_some()),
+            ai_fill_players: guard.running_ai_count,
+            ai_substitute_players: guard
+                .lobby_players
+                .values()
+                .filter(|member| member.ai)
+                .count(),
+        };
+        (guard.metrics.clone(), gauges)
+    };
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(&gauges),
+    )
+}
+
 async fn ranking_handler(
     State(state): State<SharedState>,
     Query(query): Query<RankingQuery>,
@@ -172,126 +558,915 @@ async fn ranking_handler(
     )
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<SharedState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(state, socket))
+async fn bot_ranking_handler(
+    State(state): State<SharedState>,
+    Query(query): Query<RankingQuery>,
+) -> impl IntoResponse {
+    let guard = state.lock().await;
+    Json(
+        guard
+            .ranking_store
+            .build_bot_response(parse_ranking_limit(query.limit.as_deref())),
+    )
+}
+
+async fn flood_stats_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let guard = state.lock().await;
+    Json(guard.flood_counters.snapshot())
 }
 
-async fn handle_socket(state: SharedState, socket: WebSocket) {
-    let client_id = make_id("client");
-    let (tx, mut rx) = mpsc::channel::<OutboundMessage>(256);
+async fn tick_stats_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let counters = state.lock().await.tick_counters.clone();
+    Json(counters.snapshot())
+}
 
-    {
-        let mut guard = state.lock().await;
-        guard.clients.insert(
-            client_id.clone(),
-            ClientContext {
-                tx: tx.clone(),
-                player_id: None,
-            },
+// Public and polled by dashboards, so reconnect tokens and connection internals stay out.
+async fn status_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let guard = state.lock().await;
+    let mut members: Vec<&LobbyPlayerInternal> = guard.lobby_players.values().collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    let members: Vec<Value> = members
+        .into_iter()
+        .map(|member| {
+            json!({
+                "id": member.id,
+                "name": member.name,
+                "connected": member.connected,
+                "ai": member.ai,
+                "bot": member.bot,
+                "spectator": member.spectator,
+                "ready": member.ready,
+                "isHost": guard.host_id.as_deref() == Some(member.id.as_str()),
+            })
+        })
+        .collect();
+    let game = guard.game.as_ref().map(|game| {
+        let players: Vec<Value> = game
+            .score_board()
+            .into_iter()
+            .map(|entry| {
+                let member = guard.lobby_players.get(&entry.player_id);
+                json!({
+                    "playerId": entry.player_id,
+                    "name": entry.name,
+                    "score": entry.score,
+                    "dots": entry.dots,
+                    "ghosts": entry.ghosts,
+                    "rescues": entry.rescues,
+                    "captures": entry.captures,
+                    "bot": entry.bot,
+                    "connected": member.is_some_and(|member| member.connected),
+                    "ai": member.is_none_or(|member| member.ai),
+                })
+            })
+            .collect();
+        json!({
+            "matchId": guard.match_id,
+            "difficulty": game.config.difficulty,
+            "seed": game.seed(),
+            "elapsedMs": game.elapsed_ms(),
+            "remainingMs": game.time_left_ms(),
+            "timeLimitMs": game.config.time_limit_ms,
+            "captureRatio": game.capture_ratio(),
+            "aiPlayerCount": guard.running_ai_count,
+            "players": players,
+        })
+    });
+    Json(json!({
+        "running": guard.game.is_some(),
+        "hostId": guard.host_id,
+        "members": members,
+        "settings": guard.lobby_settings.to_json(),
+        "game": game,
+    }))
+}
+
+fn admin_router(token: Option<Arc<str>>) -> Router<SharedState> {
+    Router::new()
+        .route("/rooms", get(admin_rooms_handler))
+        .route("/clients", get(admin_clients_handler))
+        .route("/match/end", post(admin_end_match_handler))
+        .route("/kick", post(admin_kick_handler))
+        .route("/toast", post(admin_toast_handler))
+        .route(
+            "/ranking/players/{name}/reset",
+            post(admin_reset_player_handler),
+        )
+        .route(
+            "/ranking/players/{name}",
+            delete(admin_delete_player_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(token, require_admin_token))
+}
+
+// The admin API does not exist unless a token is configured.
+async fn require_admin_token(
+    State(token): State<Option<Arc<str>>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !bearer_token_matches(provided, &token) {
+        server_log::warn(
+            ADMIN_LOG_TARGET,
+            "rejected admin request",
+            LogIds::default(),
+            json!({ "path": request
+                .extensions()
+                .get::<OriginalUri>()
+                .map_or_else(|| request.uri().path(), |uri| uri.path()) }),
         );
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(json!({ "error": "invalid admin token" })),
+        )
+            .into_response();
     }
+    next.run(request).await
+}
 
-    let (mut ws_sender, mut ws_receiver) = socket.split();
-    let writer = tokio::spawn(async move {
-        while let Some(outbound) = rx.recv().await {
-            let should_close = matches!(outbound, OutboundMessage::Close { .. });
-            let result = match outbound {
-                OutboundMessage::Text(payload) => {
-                    ws_sender.send(Message::Text(payload.into())).await
-                }
-                OutboundMessage::Close { code, reason } => {
-                    let frame = CloseFrame {
-                        code,
-                        reason: reason.into(),
-                    };
-                    ws_sender.send(Message::Close(Some(frame))).await
-                }
+fn admin_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+// The server hosts a single room; it is listed as `main` so the shape survives more rooms.
+async fn admin_rooms_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let guard = state.lock().await;
+    let mut members: Vec<&LobbyPlayerInternal> = guard.lobby_players.values().collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    let members: Vec<Value> = members
+        .into_iter()
+        .map(|member| {
+            json!({
+                "id": member.id,
+                "name": member.name,
+                "connected": member.connected,
+                "clientId": guard.active_client_by_player_id.get(&member.id),
+                "ai": member.ai,
+                "bot": member.bot,
+                "spectator": member.spectator,
+                "ready": member.ready,
+                "muted": guard.chat_manager.is_muted(&member.id),
+                "isHost": guard.host_id.as_deref() == Some(member.id.as_str()),
+            })
+        })
+        .collect();
+    Json(json!({
+        "rooms": [{
+            "id": "main",
+            "running": guard.game.is_some(),
+            "matchId": guard.match_id,
+            "hostId": guard.host_id,
+            "runningAiCount": guard.running_ai_count,
+            "settings": guard.lobby_settings.to_json(),
+            "members": members,
+        }],
+    }))
+}
+
+async fn admin_clients_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let guard = state.lock().await;
+    let mut clients: Vec<(&String, &ClientContext)> = guard.clients.iter().collect();
+    clients.sort_by(|a, b| a.0.cmp(b.0));
+    let clients: Vec<Value> = clients
+        .into_iter()
+        .map(|(client_id, client)| {
+            json!({
+                "clientId": client_id,
+                "playerId": client.player_id,
+                "protocolVersion": client.protocol_version,
+                "capabilities": client.capabilities,
+                "binary": client.binary,
+                "queueFree": client.tx.capacity(),
+            })
+        })
+        .collect();
+    Json(json!({ "clients": clients }))
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminEndMatchRequest {
+    reason: GameOverReason,
+}
+
+async fn admin_end_match_handler(
+    State(state): State<SharedState>,
+    Json(request): Json<AdminEndMatchRequest>,
+) -> Response {
+    let mut guard = state.lock().await;
+    let Some(game) = guard.game.as_mut().filter(|game| !game.is_ended()) else {
+        return admin_error(StatusCode::CONFLICT, "no match is running");
+    };
+    game.force_end(request.reason, "管理者による終了");
+    server_log::info(
+        ADMIN_LOG_TARGET,
+        "match ended by admin",
+        guard.log_ids(None, None),
+        json!({ "reason": request.reason }),
+    );
+    let match_id = guard.match_id.clone();
+    finish_game_if_ended(&mut guard);
+    Json(json!({ "ok": true, "matchId": match_id, "reason": request.reason })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminKickRequest {
+    client_id: Option<String>,
+    player_id: Option<String>,
+}
+
+// Kicks by player when one is bound; a client that never sent `hello` is just closed.
+async fn admin_kick_handler(
+    State(state): State<SharedState>,
+    Json(request): Json<AdminKickRequest>,
+) -> Response {
+    let mut guard = state.lock().await;
+    let (client_id, player_id) = match (request.client_id, request.player_id) {
+        (Some(client_id), None) => {
+            let Some(client) = guard.clients.get(&client_id) else {
+                return admin_error(StatusCode::NOT_FOUND, "client not found");
             };
-            if result.is_err() || should_close {
-                break;
+            let player_id = client.player_id.clone();
+            (Some(client_id), player_id)
+        }
+        (None, Some(player_id)) => {
+            if !guard.lobby_players.contains_key(&player_id) {
+                return admin_error(StatusCode::NOT_FOUND, "player not found");
             }
+            (None, Some(player_id))
         }
-    });
-
-    while let Some(received) = ws_receiver.next().await {
-        let Ok(message) = received else {
-            break;
-        };
+        _ => {
+            return admin_error(
+                StatusCode::BAD_REQUEST,
+                "exactly one of clientId or playerId is required",
+            )
+        }
+    };
 
-        match message {
-            Message::Text(raw) => {
-                handle_client_message(state.clone(), &client_id, raw.to_string()).await;
-            }
-            Message::Binary(raw) => {
-                if let Ok(text) = String::from_utf8(raw.to_vec()) {
-                    handle_client_message(state.clone(), &client_id, text).await;
-                } else {
-                    send_error_to_client(&state, &client_id, "invalid utf8 message").await;
-                }
+    match player_id.as_deref() {
+        Some(player_id) => {
+            let name = guard
+                .lobby_players
+                .get(player_id)
+                .map(|member| member.name.clone());
+            kick_member(&mut guard, player_id, "kicked by admin");
+            let note = name.map(|name| format!("{name} が管理者によりキックされました"));
+            broadcast_lobby(&mut guard, note);
+        }
+        None => {
+            if let Some(client) = client_id
+                .as_deref()
+                .and_then(|client_id| guard.clients.remove(client_id))
+            {
+                close_connection(&client.tx, KICKED_CLOSE_CODE, "kicked by admin");
             }
-            Message::Close(_) => break,
-            _ => {}
         }
     }
+    server_log::info(
+        ADMIN_LOG_TARGET,
+        "kicked by admin",
+        guard.log_ids(client_id.as_deref(), player_id.as_deref()),
+        Value::Null,
+    );
+    Json(json!({ "ok": true, "clientId": client_id, "playerId": player_id })).into_response()
+}
 
-    handle_disconnect(state, &client_id).await;
-    drop(tx);
-    let _ = writer.await;
+#[derive(Debug, Deserialize)]
+struct AdminToastRequest {
+    message: String,
 }
 
-async fn handle_client_message(state: SharedState, client_id: &str, raw: String) {
-    let Some(message) = parse_client_message(&raw) else {
-        send_error_to_client(&state, client_id, "invalid message").await;
-        return;
+// During a match the toast rides on the next snapshot; otherwise it becomes the lobby note.
+async fn admin_toast_handler(
+    State(state): State<SharedState>,
+    Json(request): Json<AdminToastRequest>,
+) -> Response {
+    let message = request.message.trim();
+    if message.is_empty() {
+        return admin_error(StatusCode::BAD_REQUEST, "message must not be empty");
+    }
+    let mut guard = state.lock().await;
+    let delivery = match guard.game.as_mut() {
+        Some(game) => {
+            game.push_toast(message);
+            "game"
+        }
+        None => {
+            broadcast_lobby(&mut guard, Some(message.to_string()));
+            "lobby"
+        }
     };
+    server_log::info(
+        ADMIN_LOG_TARGET,
+        "toast broadcast by admin",
+        guard.log_ids(None, None),
+        json!({ "message": message, "delivery": delivery }),
+    );
+    Json(json!({ "ok": true, "delivery": delivery })).into_response()
+}
 
-    match message {
-        ParsedClientMessage::Hello {
-            name,
-            reconnect_token,
-            spectator,
-            room_id,
-        } => {
-            handle_hello(state, client_id, name, reconnect_token, spectator, room_id).await;
+#[derive(Debug, Default, Deserialize)]
+struct AdminRankingQuery {
+    #[serde(default)]
+    bot: bool,
+}
+
+async fn admin_reset_player_handler(
+    State(state): State<SharedState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Query(query): Query<AdminRankingQuery>,
+) -> Response {
+    let mut guard = state.lock().await;
+    if !guard.ranking_store.reset_player(&name, query.bot) {
+        return admin_error(StatusCode::NOT_FOUND, "player not found in ranking");
+    }
+    server_log::info(
+        ADMIN_LOG_TARGET,
+        "ranking entry reset by admin",
+        LogIds::default(),
+        json!({ "name": name, "bot": query.bot }),
+    );
+    Json(json!({ "ok": true })).into_response()
+}
+
+async fn admin_delete_player_handler(
+    State(state): State<SharedState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Query(query): Query<AdminRankingQuery>,
+) -> Response {
+    let mut guard = state.lock().await;
+    if !guard.ranking_store.delete_player(&name, query.bot) {
+        return admin_error(StatusCode::NOT_FOUND, "player not found in ranking");
+    }
+    server_log::info(
+        ADMIN_LOG_TARGET,
+        "ranking entry deleted by admin",
+        LogIds::default(),
+        json!({ "name": name, "bot": query.bot }),
+    );
+    Json(json!({ "ok": true })).into_response()
+}
+
+// Oversized frames and messages are rejected by the WebSocket layer before they are buffered.
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<SharedState>) -> Response {
+    let max_frame_bytes = {
+        let guard = state.lock().await;
+        if guard.shutting_down {
+            return (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down").into_response();
         }
-        ParsedClientMessage::Ping { t } => {
-            let mut guard = state.lock().await;
-            send_to_client(
-                &mut guard,
-                client_id,
-                &json!({
-                    "type": "pong",
-                    "t": t,
-                }),
-                QueuePolicy::DisconnectOnFull,
-            );
+        guard.flood_options.max_frame_bytes
+    };
+    ws.max_message_size(max_frame_bytes)
+        .max_frame_size(max_frame_bytes)
+        .on_upgrade(move |socket| handle_socket(state, socket))
+}
+
+async fn shutdown_signal() -> &'static str {
+    let interrupt = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
         }
-        ParsedClientMessage::LobbyStart {
-            difficulty,
-            ai_player_count,
-            time_limit_minutes,
-        } => {
-            let player_id = {
-                let guard = state.lock().await;
-                guard
-                    .clients
-                    .get(client_id)
-                    .and_then(|ctx| ctx.player_id.clone())
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+// The running match is checkpointed so it can resume after the restart; if that is disabled or
+// fails it is ended and recorded like any other. Every client then gets `server_shutdown`
+// followed by a close frame, and the ranking file is flushed. Sockets are given up to `grace` to
+// finish the close handshake before the process exits.
+async fn shut_down(state: SharedState, grace: Duration, checkpoint_writer: Option<JoinHandle<()>>) {
+    // The tick loop stops on `shutting_down`, so the checkpoint is the match's final state.
+    let saving = {
+        let mut guard = state.lock().await;
+        guard.shutting_down = true;
+        capture_checkpoint(&guard).and_then(|checkpoint| {
+            let (done, saved) = oneshot::channel();
+            let command = CheckpointCommand::Save {
+                checkpoint: Box::new(checkpoint),
+                done: Some(done),
             };
-            let Some(player_id) = player_id else {
-                send_error_to_client(&state, client_id, "send hello first").await;
+            request_checkpoint(&guard, command).then_some(saved)
+        })
+    };
+    let resumable = match saving {
+        Some(saved) => matches!(saved.await, Ok(Ok(()))),
+        None => false,
+    };
+
+    {
+        let mut guard = state.lock().await;
+        if !resumable {
+            if let Some(game) = guard.game.as_mut() {
+                game.force_end(GameOverReason::Shutdown, "サーバー停止");
+            }
+            finish_game_if_ended(&mut guard);
+        }
+        let message = if resumable {
+            "サーバーを再起動します。再接続すると試合が再開されます"
+        } else {
+            "サーバーを停止します。しばらくしてから再接続してください"
+        };
+        let notice = json!({
+            "type": "server_shutdown",
+            "message": message,
+            "resumable": resumable,
+        });
+        let mut encoded = EncodedMessage::new(&notice);
+        for client in guard.clients.values_mut() {
+            let frames = vec![
+                encoded.encode(client.binary),
+                OutboundMessage::Close {
+                    code: SHUTDOWN_CLOSE_CODE,
+                    reason: "server shutting down".to_string(),
+                },
+            ];
+            // A closed receiver means the writer already stopped and the socket is going away.
+            if let Some(shutdown) = client.shutdown.take() {
+                let _ = shutdown.send(frames);
+            }
+        }
+        if let Err(error) = guard.ranking_store.flush() {
+            server_log::error(
+                "server",
+                "failed to flush ranking",
+                LogIds::default(),
+                json!({ "error": error }),
+            );
+        }
+        server_log::info(
+            "server",
+            "closing connections",
+            LogIds::default(),
+            json!({
+                "clients": guard.clients.len(),
+                "graceMs": grace.as_millis() as u64,
+                "resumable": resumable,
+            }),
+        );
+        // Dropping the sender lets the writer finish what is queued and stop.
+        guard.checkpoint_tx = None;
+    }
+    if let Some(writer) = checkpoint_writer {
+        let _ = writer.await;
+    }
+
+    let deadline = Instant::now() + grace;
+    let remaining = loop {
+        let remaining = state.lock().await.clients.len();
+        if remaining == 0 || Instant::now() >= deadline {
+            break remaining;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    server_log::info(
+        "server",
+        "shutdown complete",
+        LogIds::default(),
+        json!({ "unclosedClients": remaining }),
+    );
+}
+
+fn outbound_to_ws(outbound: OutboundMessage) -> Message {
+    match outbound {
+        OutboundMessage::Text(payload) => Message::Text(payload),
+        OutboundMessage::Binary(payload) => Message::Binary(payload),
+        OutboundMessage::Close { code, reason } => Message::Close(Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        })),
+    }
+}
+
+// Shutdown frames are written ahead of anything still queued; each gets a bounded wait and
+// failures are counted instead of silently lost.
+async fn write_outbound<S>(
+    mut sink: S,
+    mut rx: mpsc::Receiver<OutboundMessage>,
+    mut shutdown: oneshot::Receiver<Vec<OutboundMessage>>,
+    metrics: Arc<ServerMetrics>,
+) where
+    S: Sink<Message> + Unpin,
+{
+    let mut shutdown_open = true;
+    loop {
+        tokio::select! {
+            biased;
+            frames = &mut shutdown, if shutdown_open => {
+                let Ok(frames) = frames else {
+                    shutdown_open = false;
+                    continue;
+                };
+                for outbound in frames {
+                    let sent =
+                        tokio::time::timeout(SHUTDOWN_SEND_TIMEOUT, sink.send(outbound_to_ws(outbound)))
+                            .await;
+                    if !matches!(sent, Ok(Ok(()))) {
+                        metrics.record_shutdown_send_failure();
+                    }
+                }
+                break;
+            }
+            outbound = rx.recv() => {
+                let Some(outbound) = outbound else {
+                    break;
+                };
+                let should_close = matches!(outbound, OutboundMessage::Close { .. });
+                if sink.send(outbound_to_ws(outbound)).await.is_err() || should_close {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn handle_socket(state: SharedState, socket: WebSocket) {
+    let client_id = make_id("client");
+    let (tx, rx, shutdown_rx, mut flood_guard, metrics) = {
+        let mut guard = state.lock().await;
+        let (tx, rx) = mpsc::channel::<OutboundMessage>(guard.client_channel_size);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        guard.clients.insert(
+            client_id.clone(),
+            ClientContext {
+                tx: tx.clone(),
+                player_id: None,
+                decision_window: None,
+                protocol_version: LEGACY_PROTOCOL_VERSION,
+                capabilities: Vec::new(),
+                binary: false,
+                shutdown: Some(shutdown_tx),
+            },
+        );
+        server_log::debug(
+            "server",
+            "client connected",
+            guard.log_ids(Some(&client_id), None),
+            Value::Null,
+        );
+        (
+            tx,
+            rx,
+            shutdown_rx,
+            FloodGuard::new(guard.flood_options.clone(), guard.flood_counters.clone()),
+            guard.metrics.clone(),
+        )
+    };
+
+    let (ws_sender, mut ws_receiver) = socket.split();
+    let writer = tokio::spawn(write_outbound(ws_sender, rx, shutdown_rx, metrics.clone()));
+
+    while let Some(received) = ws_receiver.next().await {
+        let message = match received {
+            Ok(message) => message,
+            Err(error) => {
+                let message = error.to_string();
+                if is_oversized_message(error) {
+                    if let FloodVerdict::Close { code, reason } =
+                        flood_guard.reject_oversized_frame()
+                    {
+                        log_flood_close(&state, &client_id, code, reason).await;
+                        close_connection(&tx, code, reason);
+                    }
+                } else {
+                    let guard = state.lock().await;
+                    server_log::debug(
+                        "server",
+                        "websocket receive failed",
+                        guard.log_ids(Some(&client_id), None),
+                        json!({ "error": message }),
+                    );
+                }
+                break;
+            }
+        };
+
+        let text = match message {
+            Message::Text(raw) => Some(raw.to_string()),
+            Message::Binary(raw) => String::from_utf8(raw.to_vec()).ok(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let parsed = text.as_deref().and_then(parse_client_message);
+        if parsed.is_none() {
+            metrics.record_invalid_message();
+        }
+        let message_type = parsed
+            .as_ref()
+            .map_or(INVALID_MESSAGE_TYPE, ParsedClientMessage::message_type);
+        match flood_guard.check_message(message_type, now_ms()) {
+            FloodVerdict::Allow => {}
+            FloodVerdict::Drop => continue,
+            FloodVerdict::Error(reason) => {
+                send_error_to_client(&state, &client_id, &reason).await;
+                continue;
+            }
+            FloodVerdict::Close { code, reason } => {
+                log_flood_close(&state, &client_id, code, reason).await;
+                close_connection(&tx, code, reason);
+                break;
+            }
+        }
+
+        match parsed {
+            Some(message) => handle_client_message(state.clone(), &client_id, message).await,
+            None if text.is_none() => {
+                send_error_to_client(&state, &client_id, "invalid utf8 message").await;
+            }
+            None => send_error_to_client(&state, &client_id, "invalid message").await,
+        }
+    }
+
+    handle_disconnect(state, &client_id).await;
+    drop(tx);
+    let _ = writer.await;
+}
+
+fn is_oversized_message(error: axum::Error) -> bool {
+    matches!(
+        error.into_inner().downcast_ref::<tungstenite::Error>(),
+        Some(tungstenite::Error::Capacity(_))
+    )
+}
+
+async fn log_flood_close(state: &SharedState, client_id: &str, code: u16, reason: &str) {
+    let guard = state.lock().await;
+    server_log::warn(
+        "server",
+        "closing connection",
+        guard.log_ids(Some(client_id), None),
+        json!({ "code": code, "reason": reason }),
+    );
+}
+
+fn close_connection(tx: &mpsc::Sender<OutboundMessage>, code: u16, reason: &str) {
+    let _ = tx.try_send(OutboundMessage::Close {
+        code,
+        reason: reason.to_string(),
+    });
+}
+
+async fn handle_client_message(state: SharedState, client_id: &str, message: ParsedClientMessage) {
+    match message {
+        ParsedClientMessage::Hello {
+            name,
+            reconnect_token,
+            spectator,
+            room_id,
+            bot,
+            bot_token,
+            protocol_version,
+            capabilities,
+        } => {
+            handle_hello(
+                state,
+                client_id,
+                HelloRequest {
+                    name,
+                    reconnect_token,
+                    spectator,
+                    room_id,
+                    bot,
+                    bot_token,
+                    protocol_version,
+                    capabilities,
+                },
+            )
+            .await;
+        }
+        ParsedClientMessage::Chat { channel, text } => {
+            let mut guard = state.lock().await;
+            let Some(member) = bound_member(&guard, client_id) else {
+                send_error(&mut guard, client_id, "send hello first");
+                return;
+            };
+            let result = guard.chat_manager.post(PostChatInput {
+                sender_id: member.id,
+                sender_name: member.name,
+                channel,
+                text,
+                now_ms: now_ms(),
+                spectator: member.spectator,
+            });
+            match result {
+                Ok(message) => broadcast_where(
+                    &mut guard,
+                    &json!({
+                        "type": "chat",
+                        "message": message,
+                    }),
+                    QueuePolicy::DropOnFull,
+                    |recipient| message.channel.is_visible_to(recipient.spectator),
+                ),
+                Err(reason) => send_error(&mut guard, client_id, reason),
+            }
+        }
+        ParsedClientMessage::ChatMute { player_id, muted } => {
+            let mut guard = state.lock().await;
+            let Some(target) = host_moderation_target(&mut guard, client_id, &player_id) else {
+                return;
+            };
+            guard.chat_manager.set_muted(&target.id, muted);
+            let note = if muted {
+                format!("{} をミュートしました", target.name)
+            } else {
+                format!("{} のミュートを解除しました", target.name)
+            };
+            broadcast_lobby(&mut guard, Some(note));
+        }
+        ParsedClientMessage::Kick { player_id } => {
+            let mut guard = state.lock().await;
+            let Some(target) = host_moderation_target(&mut guard, client_id, &player_id) else {
+                return;
+            };
+            kick_member(&mut guard, &target.id, "kicked by host");
+            broadcast_lobby(
+                &mut guard,
+                Some(format!("{} をキックしました", target.name)),
+            );
+        }
+        ParsedClientMessage::QueueJoin {
+            difficulty,
+            party_size,
+        } => {
+            let mut guard = state.lock().await;
+            let Some(member) = bound_member(&guard, client_id) else {
+                send_error(&mut guard, client_id, "send hello first");
+                return;
+            };
+            if guard
+                .game
+                .as_ref()
+                .is_some_and(|game| game.has_player(&member.id))
+            {
+                send_error(&mut guard, client_id, "already in game");
+                return;
+            }
+            guard.matchmaking.enqueue(
+                &member.id,
+                QueuePreferences {
+                    difficulty,
+                    party_size: party_size.map(|size| size.clamp(1, 100) as usize),
+                },
+                now_ms(),
+            );
+            send_queue_status(&mut guard, &member.id, now_ms());
+        }
+        ParsedClientMessage::QueueLeave => {
+            let mut guard = state.lock().await;
+            let Some(member) = bound_member(&guard, client_id) else {
+                send_error(&mut guard, client_id, "send hello first");
+                return;
+            };
+            guard.matchmaking.leave(&member.id);
+            send_to_client(
+                &mut guard,
+                client_id,
+                &json!({
+                    "type": "queue_status",
+                    "queued": false,
+                }),
+                QueuePolicy::DisconnectOnFull,
+            );
+        }
+        ParsedClientMessage::Ping { t } => {
+            let mut guard = state.lock().await;
+            send_to_client(
+                &mut guard,
+                client_id,
+                &json!({
+                    "type": "pong",
+                    "t": t,
+                }),
+                QueuePolicy::DisconnectOnFull,
+            );
+        }
+        ParsedClientMessage::LobbyStart {
+            difficulty,
+            ai_player_count,
+            time_limit_minutes,
+            ai_skill,
+            ai_skills,
+        } => {
+            let player_id = {
+                let guard = state.lock().await;
+                guard
+                    .clients
+                    .get(client_id)
+                    .and_then(|ctx| ctx.player_id.clone())
+            };
+            let Some(player_id) = player_id else {
+                send_error_to_client(&state, client_id, "send hello first").await;
                 return;
             };
             handle_lobby_start(
                 state,
                 &player_id,
-                difficulty.unwrap_or(Difficulty::Normal),
-                ai_player_count,
-                time_limit_minutes,
+                LobbySettingsUpdate {
+                    difficulty,
+                    ai_player_count,
+                    time_limit_minutes,
+                    ai_skill,
+                    require_ready: None,
+                },
+                ai_skills.unwrap_or_default(),
             )
             .await;
         }
-        ParsedClientMessage::Input { dir, awaken } => {
+        ParsedClientMessage::LobbySettings {
+            difficulty,
+            ai_player_count,
+            time_limit_minutes,
+            ai_skill,
+            require_ready,
+        } => {
+            let mut guard = state.lock().await;
+            let Some(member) = bound_member(&guard, client_id) else {
+                send_error(&mut guard, client_id, "send hello first");
+                return;
+            };
+            if guard.host_id.as_deref() != Some(member.id.as_str()) {
+                send_error(&mut guard, client_id, "only host can change lobby settings");
+                return;
+            }
+            if guard.game.is_some() {
+                send_error(&mut guard, client_id, "game already running");
+                return;
+            }
+            let limits = guard.lobby_limits;
+            guard.lobby_settings.apply(
+                LobbySettingsUpdate {
+                    difficulty,
+                    ai_player_count,
+                    time_limit_minutes,
+                    ai_skill,
+                    require_ready,
+                },
+                &limits,
+            );
+            broadcast_lobby(&mut guard, Some("ロビー設定を更新しました".to_string()));
+        }
+        ParsedClientMessage::Ready { ready } => {
+            let mut guard = state.lock().await;
+            let Some(member) = bound_member(&guard, client_id) else {
+                send_error(&mut guard, client_id, "send hello first");
+                return;
+            };
+            if member.spectator {
+                send_error(&mut guard, client_id, "spectator cannot ready");
+                return;
+            }
+            if guard.game.is_some() {
+                send_error(&mut guard, client_id, "game already running");
+                return;
+            }
+            if let Some(member) = guard.lobby_players.get_mut(&member.id) {
+                member.ready = ready;
+            }
+            broadcast_lobby(&mut guard, None);
+        }
+        ParsedClientMessage::HostTransfer { player_id } => {
+            let mut guard = state.lock().await;
+            let Some(target) = host_moderation_target(&mut guard, client_id, &player_id) else {
+                return;
+            };
+            if !target.connected {
+                send_error(&mut guard, client_id, "player is not connected");
+                return;
+            }
+            set_host(&mut guard, Some(target.id.clone()), "transferred");
+            broadcast_lobby(
+                &mut guard,
+                Some(format!("ホストを {} に移譲しました", target.name)),
+            );
+        }
+        ParsedClientMessage::Input {
+            dir,
+            awaken,
+            seq,
+            client_time_ms,
+        } => {
             let player_id = {
                 let guard = state.lock().await;
                 guard
@@ -304,11 +1479,51 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
                 return;
             };
             let mut guard = state.lock().await;
+            let is_bot = guard
+                .lobby_players
+                .get(&player_id)
+                .map(|member| member.bot)
+                .unwrap_or(false);
+            if is_bot && guard.game.is_some() {
+                // One decision per observation: the window closes on the first accepted input.
+                let window = guard
+                    .clients
+                    .get_mut(client_id)
+                    .and_then(|ctx| ctx.decision_window.take());
+                let rejection = match window {
+                    None => Some("no pending bot observation"),
+                    Some(DecisionWindow::Until(deadline)) if Instant::now() > deadline => {
+                        Some("decision deadline exceeded")
+                    }
+                    Some(_) => None,
+                };
+                if let Some(message) = rejection {
+                    send_to_client(
+                        &mut guard,
+                        client_id,
+                        &json!({
+                            "type": "error",
+                            "message": message,
+                        }),
+                        QueuePolicy::DisconnectOnFull,
+                    );
+                    return;
+                }
+            }
             if let Some(game) = guard.game.as_mut() {
                 game.receive_input(&player_id, dir, awaken);
+                if let Some(seq) = seq {
+                    game.acknowledge_input(
+                        &player_id,
+                        InputAck {
+                            seq,
+                            client_time_ms,
+                        },
+                    );
+                }
             }
         }
-        ParsedClientMessage::PlacePing { kind } => {
+        ParsedClientMessage::PlacePing { kind, target } => {
             let player_id = {
                 let guard = state.lock().await;
                 guard
@@ -333,7 +1548,7 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
                 );
                 return;
             };
-            let Some(game) = guard.game.as_mut() else {
+            if guard.game.is_none() {
                 send_to_client(
                     &mut guard,
                     client_id,
@@ -344,8 +1559,9 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
                     QueuePolicy::DisconnectOnFull,
                 );
                 return;
-            };
+            }
             if member.spectator {
+                guard.metrics.record_ping(Some(PING_REJECTED_SPECTATOR));
                 send_to_client(
                     &mut guard,
                     client_id,
@@ -358,18 +1574,29 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
                 return;
             }
 
-            let Some(pos) = game.player_position(&player_id) else {
-                send_to_client(
-                    &mut guard,
-                    client_id,
-                    &json!({
-                        "type": "error",
-                        "message": "player is not in current game",
-                    }),
-                    QueuePolicy::DisconnectOnFull,
-                );
+            let max_distance = guard.ping_manager.options().max_target_distance;
+            let Some(game) = guard.game.as_ref() else {
                 return;
             };
+            let (pos, target_id) =
+                match game.resolve_ping_target(&player_id, target.as_ref(), max_distance) {
+                    Ok(resolved) => resolved,
+                    Err(message) => {
+                        guard
+                            .metrics
+                            .record_ping(Some(PING_REJECTED_INVALID_TARGET));
+                        send_to_client(
+                            &mut guard,
+                            client_id,
+                            &json!({
+                                "type": "error",
+                                "message": message,
+                            }),
+                            QueuePolicy::DisconnectOnFull,
+                        );
+                        return;
+                    }
+                };
             let now_ms = game.current_now_ms();
 
             let result = guard.ping_manager.place(PlacePingInput {
@@ -378,9 +1605,11 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
                 x: pos.x,
                 y: pos.y,
                 kind,
+                target_id,
                 now_ms,
                 spectator: member.spectator,
             });
+            guard.metrics.record_ping(result.code);
             if !result.ok {
                 send_to_client(
                     &mut guard,
@@ -396,15 +1625,46 @@ async fn handle_client_message(state: SharedState, client_id: &str, raw: String)
     }
 }
 
-async fn handle_hello(
-    state: SharedState,
-    client_id: &str,
-    requested_name: String,
+struct HelloRequest {
+    name: String,
     reconnect_token: Option<String>,
-    spectator_requested: bool,
-    requested_room_id: Option<String>,
-) {
+    spectator: bool,
+    room_id: Option<String>,
+    bot: bool,
+    bot_token: Option<String>,
+    protocol_version: u32,
+    capabilities: Vec<String>,
+}
+
+async fn handle_hello(state: SharedState, client_id: &str, request: HelloRequest) {
+    let HelloRequest {
+        name: requested_name,
+        reconnect_token,
+        spectator: spectator_requested,
+        room_id: requested_room_id,
+        bot: bot_requested,
+        bot_token,
+        protocol_version,
+        capabilities,
+    } = request;
     let mut guard = state.lock().await;
+    let Some(client) = guard.clients.get_mut(client_id) else {
+        return;
+    };
+    if protocol_version < MIN_PROTOCOL_VERSION {
+        close_connection(
+            &client.tx,
+            PROTOCOL_TOO_OLD_CLOSE_CODE,
+            &format!("protocol version {protocol_version} is too old (min {MIN_PROTOCOL_VERSION})"),
+        );
+        return;
+    }
+    client.protocol_version = protocol_version.min(PROTOCOL_VERSION);
+    client.capabilities = negotiate_capabilities(&capabilities);
+    client.binary = client
+        .capabilities
+        .iter()
+        .any(|capability| capability == BINARY_CAPABILITY);
     if !is_supported_room(requested_room_id.as_deref()) {
         send_to_client(
             &mut guard,
@@ -417,6 +1677,40 @@ async fn handle_hello(
         );
         return;
     }
+    if bot_requested {
+        let token_matches = |expected: &str| {
+            bot_token
+                .as_deref()
+                .is_some_and(|token| secret_matches(token, expected))
+        };
+        let rejection = match guard.bot_options.api_token.as_deref() {
+            None => Some("bot mode is disabled on this server"),
+            Some(expected) if !token_matches(expected) => Some("invalid bot token"),
+            Some(_) if spectator_requested => Some("bot cannot join as spectator"),
+            Some(_) => None,
+        };
+        if let Some(rejection) = rejection {
+            send_to_client(
+                &mut guard,
+                client_id,
+                &json!({
+                    "type": "error",
+                    "message": rejection,
+                }),
+                QueuePolicy::DisconnectOnFull,
+            );
+            return;
+        }
+    }
+    if reconnect_token
+        .as_deref()
+        .is_some_and(|token| guard.kicked_tokens.contains(token))
+    {
+        if let Some(client) = guard.clients.remove(client_id) {
+            close_connection(&client.tx, KICKED_CLOSE_CODE, "kicked from this lobby");
+        }
+        return;
+    }
     let name = sanitize_name(&requested_name);
 
     let current_player_id = guard
@@ -473,6 +1767,12 @@ async fn handle_hello(
                 }
             }
 
+            log_member_event(
+                &guard,
+                client_id,
+                &current_player_id,
+                "player re-sent hello",
+            );
             ensure_host_assigned(&mut guard, Some(current_player_id.clone()));
             send_welcome_and_initial_state(&mut guard, client_id, &current_player_id);
             broadcast_lobby(&mut guard, None);
@@ -535,6 +1835,7 @@ async fn handle_hello(
                 }
             }
 
+            log_member_event(&guard, client_id, &existing_id, "player reconnected");
             ensure_host_assigned(&mut guard, Some(existing_id.clone()));
             send_welcome_and_initial_state(&mut guard, client_id, &existing_id);
             broadcast_lobby(&mut guard, None);
@@ -555,30 +1856,76 @@ async fn handle_hello(
         return;
     }
 
-    let player_id = make_id("player");
-    let token = make_reconnect_token();
+    let room_limits = guard.room_limits;
+    let (capacity, slot) = if spectator_requested {
+        (room_limits.max_spectators, "spectator")
+    } else {
+        (room_limits.max_players, "player")
+    };
+    let occupied = guard
+        .lobby_players
+        .values()
+        .filter(|member| !member.ai && member.spectator == spectator_requested)
+        .count();
+    if occupied >= capacity {
+        send_to_client(
+            &mut guard,
+            client_id,
+            &json!({
+                "type": "error",
+                "message": format!("room is full ({slot} slots: {capacity})"),
+            }),
+            QueuePolicy::DisconnectOnFull,
+        );
+        return;
+    }
+
+    let (player_id, token) =
+        match reconnect_token.and_then(|token| guard.departed_players.remove_entry(&token)) {
+            Some((token, player_id)) => (player_id, token),
+            None => (make_id("player"), make_reconnect_token()),
+        };
     let player = LobbyPlayerInternal {
         id: player_id.clone(),
         name,
         connected: true,
         ai: false,
+        bot: bot_requested,
         spectator: spectator_requested,
+        ready: false,
         reconnect_token: token,
+        spectator_after_match: None,
     };
 
     guard.lobby_players.insert(player_id.clone(), player);
     bind_client_to_player(&mut guard, client_id, &player_id);
+    log_member_event(&guard, client_id, &player_id, "player joined");
     ensure_host_assigned(&mut guard, Some(player_id.clone()));
     send_welcome_and_initial_state(&mut guard, client_id, &player_id);
     broadcast_lobby(&mut guard, None);
 }
 
+fn log_member_event(state: &ServerState, client_id: &str, player_id: &str, message: &str) {
+    let Some(member) = state.lobby_players.get(player_id) else {
+        return;
+    };
+    server_log::info(
+        "server",
+        message,
+        state.log_ids(Some(client_id), Some(player_id)),
+        json!({
+            "name": member.name,
+            "spectator": member.spectator,
+            "bot": member.bot,
+        }),
+    );
+}
+
 async fn handle_lobby_start(
     state: SharedState,
     requested_by: &str,
-    difficulty: Difficulty,
-    ai_player_count: Option<i64>,
-    time_limit_minutes: Option<i64>,
+    overrides: LobbySettingsUpdate,
+    ai_skills: Vec<AiSkill>,
 ) {
     let mut guard = state.lock().await;
     if guard.game.is_some() {
@@ -601,6 +1948,17 @@ async fn handle_lobby_start(
         return;
     }
 
+    // Overrides only shape this start; the lobby's own settings change through `lobby_settings`.
+    let mut settings = guard.lobby_settings.clone();
+    settings.apply(overrides, &guard.lobby_limits);
+    if settings.require_ready && !all_humans_ready(&guard) {
+        if let Some(client_id) = guard.active_client_by_player_id.get(requested_by).cloned() {
+            send_error(&mut guard, &client_id, "not all players are ready");
+        }
+        broadcast_lobby(&mut guard, None);
+        return;
+    }
+
     let mut human_ids: Vec<String> = guard
         .lobby_players
         .values()
@@ -609,9 +1967,23 @@ async fn handle_lobby_start(
         .collect();
     human_ids.sort_by_key(|id| player_order_key(id));
 
+    if let Err(message) = start_match(&mut guard, &human_ids, &settings, &ai_skills) {
+        if let Some(client_id) = guard.active_client_by_player_id.get(requested_by).cloned() {
+            send_error(&mut guard, &client_id, message);
+        }
+    }
+}
+
+// Creates the game for `human_ids` plus the configured AI players and notifies every client.
+fn start_match(
+    state: &mut ServerState,
+    human_ids: &[String],
+    settings: &LobbySettings,
+    ai_skills: &[AiSkill],
+) -> Result<(), &'static str> {
     let mut start_players = Vec::new();
-    for player_id in &human_ids {
-        let Some(player) = guard.lobby_players.get(player_id) else {
+    for player_id in human_ids {
+        let Some(player) = state.lobby_players.get(player_id) else {
             continue;
         };
         start_players.push(StartPlayer {
@@ -619,50 +1991,53 @@ async fn handle_lobby_start(
             name: player.name.clone(),
             reconnect_token: player.reconnect_token.clone(),
             connected: player.connected,
+            bot: player.bot,
         });
     }
 
-    let ai_count = normalize_ai_count(ai_player_count);
+    let ai_count = settings.ai_player_count;
+    let mut skill_by_ai_id = HashMap::new();
     for idx in 0..ai_count {
+        let id = format!("ai_{}", make_id("id"));
+        let skill = ai_skills.get(idx).copied().unwrap_or(settings.ai_skill);
+        skill_by_ai_id.insert(id.clone(), skill);
         start_players.push(StartPlayer {
-            id: format!("ai_{}", make_id("id")),
+            id,
             name: format!("AI-{:02}", idx + 1),
             reconnect_token: make_reconnect_token(),
             connected: false,
+            bot: false,
         });
     }
 
     if start_players.is_empty() {
-        if let Some(client_id) = guard.active_client_by_player_id.get(requested_by).cloned() {
-            send_to_client(
-                &mut guard,
-                &client_id,
-                &json!({
-                    "type": "error",
-                    "message": "no players. set AI players or join as player.",
-                }),
-                QueuePolicy::DisconnectOnFull,
-            );
-        }
-        return;
+        return Err("no players. set AI players or join as player.");
     }
 
-    guard.running_ai_count = ai_count;
-    guard.ping_manager.clear();
-    guard.game = Some(GameEngine::new(
+    state.running_ai_count = ai_count;
+    state.ping_manager.clear();
+    state.match_id = Some(make_id("match"));
+    state.game = Some(GameEngine::new(
         start_players,
-        difficulty,
+        settings.difficulty,
         now_ms() as u32,
         GameEngineOptions {
-            time_limit_ms_override: normalize_time_limit_ms(time_limit_minutes),
+            time_limit_ms_override: settings.time_limit_minutes.map(|minutes| minutes * 60_000),
+            rules: state.rules.clone(),
+            ai_skills: skill_by_ai_id,
+            squad_planner: state.squad_planner,
         },
     ));
 
-    let player_ids: Vec<String> = guard.lobby_players.keys().cloned().collect();
+    for player_id in human_ids {
+        state.matchmaking.leave(player_id);
+    }
+
+    let player_ids: Vec<String> = state.lobby_players.keys().cloned().collect();
     for player_id in player_ids {
-        let game_has_player = guard.game.as_ref().map(|game| game.has_player(&player_id));
+        let game_has_player = state.game.as_ref().map(|game| game.has_player(&player_id));
         let mut remove_player = false;
-        if let Some(player) = guard.lobby_players.get_mut(&player_id) {
+        if let Some(player) = state.lobby_players.get_mut(&player_id) {
             if player.spectator {
                 player.ai = false;
             } else if let Some(game_has_player) = game_has_player {
@@ -675,13 +2050,13 @@ async fn handle_lobby_start(
         }
 
         if remove_player {
-            guard.lobby_players.remove(&player_id);
-            guard.active_client_by_player_id.remove(&player_id);
+            state.lobby_players.remove(&player_id);
+            state.active_client_by_player_id.remove(&player_id);
         }
     }
 
     let (world, config, started_at_ms, seed, start_note) = {
-        let game = guard
+        let game = state
             .game
             .as_ref()
             .expect("game should be initialized before notifying clients");
@@ -699,18 +2074,30 @@ async fn handle_lobby_start(
         )
     };
 
-    broadcast_lobby(&mut guard, Some(start_note));
+    server_log::info(
+        "server",
+        "match started",
+        state.log_ids(None, None),
+        json!({
+            "seed": seed,
+            "difficulty": settings.difficulty,
+            "humans": human_ids.len(),
+            "ai": ai_count,
+            "timeLimitMs": config.time_limit_ms,
+        }),
+    );
+    broadcast_lobby(state, Some(start_note));
 
-    let members: Vec<LobbyPlayerInternal> = guard
+    let members: Vec<LobbyPlayerInternal> = state
         .lobby_players
         .values()
         .filter(|member| member.connected)
         .cloned()
         .collect();
     for member in members {
-        if let Some(client_id) = guard.active_client_by_player_id.get(&member.id).cloned() {
+        if let Some(client_id) = state.active_client_by_player_id.get(&member.id).cloned() {
             send_to_client(
-                &mut guard,
+                state,
                 &client_id,
                 &json!({
                     "type": "game_init",
@@ -725,6 +2112,8 @@ async fn handle_lobby_start(
             );
         }
     }
+
+    Ok(())
 }
 
 async fn handle_disconnect(state: SharedState, client_id: &str) {
@@ -750,6 +2139,7 @@ fn disconnect_client_internal(state: &mut ServerState, client_id: &str, broadcas
     }
 
     state.active_client_by_player_id.remove(&bound_player_id);
+    state.matchmaking.leave(&bound_player_id);
 
     let game_running = state.game.is_some();
     let mut remove_member = false;
@@ -771,13 +2161,26 @@ fn disconnect_client_internal(state: &mut ServerState, client_id: &str, broadcas
         }
     }
 
+    server_log::info(
+        "server",
+        "player disconnected",
+        state.log_ids(Some(client_id), Some(&bound_player_id)),
+        json!({ "removed": remove_member, "aiTakeover": game_running && !remove_member }),
+    );
     if remove_member {
-        state.lobby_players.remove(&bound_player_id);
+        if let Some(member) = state.lobby_players.remove(&bound_player_id) {
+            state
+                .departed_players
+                .insert(member.reconnect_token, member.id);
+        }
         state.active_client_by_player_id.remove(&bound_player_id);
+        state.chat_manager.forget_player(&bound_player_id);
+        reset_moderation_if_lobby_empty(state);
     }
 
     if state.host_id.as_deref() == Some(&bound_player_id) {
-        state.host_id = choose_next_host(state);
+        let next_host = choose_next_host(state);
+        set_host(state, next_host, "host disconnected");
     }
 
     if broadcast_after {
@@ -789,6 +2192,11 @@ fn send_welcome_and_initial_state(state: &mut ServerState, client_id: &str, play
     let Some(member) = state.lobby_players.get(player_id).cloned() else {
         return;
     };
+    let (protocol_version, capabilities) = state
+        .clients
+        .get(client_id)
+        .map(|client| (client.protocol_version, client.capabilities.clone()))
+        .unwrap_or((LEGACY_PROTOCOL_VERSION, Vec::new()));
 
     send_to_client(
         state,
@@ -799,6 +2207,11 @@ fn send_welcome_and_initial_state(state: &mut ServerState, client_id: &str, play
             "reconnectToken": member.reconnect_token,
             "isHost": state.host_id.as_deref() == Some(player_id),
             "isSpectator": member.spectator,
+            "isBot": member.bot,
+            "chatHistory": state.chat_manager.history_for(member.spectator),
+            "protocolVersion": protocol_version,
+            "serverProtocolVersion": PROTOCOL_VERSION,
+            "capabilities": capabilities,
         }),
         QueuePolicy::DisconnectOnFull,
     );
@@ -891,7 +2304,8 @@ fn broadcast_lobby(state: &mut ServerState, note: Option<String>) {
         .as_ref()
         .and_then(|host_id| state.lobby_players.get(host_id))
         .map(|host| host.connected)
-        .unwrap_or(false);
+        .unwrap_or(false)
+        && (!state.lobby_settings.require_ready || all_humans_ready(state));
 
     let composed_note = if state.running_ai_count > 0 && note.is_none() {
         Some(format!("AI稼働中: {}", state.running_ai_count))
@@ -907,7 +2321,10 @@ fn broadcast_lobby(state: &mut ServerState, note: Option<String>) {
                 "name": player.name,
                 "connected": player.connected,
                 "ai": player.ai,
+                "bot": player.bot,
                 "spectator": player.spectator,
+                "muted": state.chat_manager.is_muted(&player.id),
+                "ready": player.ready,
                 "isHost": state.host_id.as_deref() == Some(player.id.as_str()),
             })
         })
@@ -922,114 +2339,673 @@ fn broadcast_lobby(state: &mut ServerState, note: Option<String>) {
             "canStart": can_start,
             "running": state.game.is_some(),
             "spectatorCount": spectator_count,
+            "settings": state.lobby_settings.to_json(),
             "note": composed_note,
         }),
         QueuePolicy::DisconnectOnFull,
     );
 }
 
-fn start_tick_loop(state: SharedState) {
+fn start_tick_loop(state: SharedState) {
+    tokio::spawn(async move {
+        let (options, counters, metrics) = {
+            let guard = state.lock().await;
+            (
+                guard.tick_options,
+                guard.tick_counters.clone(),
+                guard.metrics.clone(),
+            )
+        };
+        let origin = Instant::now();
+        let clock_ms = || origin.elapsed().as_millis() as u64;
+        let mut clock = TickClock::new(options, clock_ms());
+        loop {
+            tokio::time::sleep(Duration::from_millis(clock.until_next_step_ms())).await;
+            let plan = clock.advance(clock_ms());
+            if plan.steps == 0 {
+                continue;
+            }
+
+            let started = Instant::now();
+            // Only the simulation step runs under the lock. Encoding the snapshot and queueing it
+            // to every client happen after it is released, so handlers are not stalled by fan-out.
+            let frame = {
+                let mut guard = state.lock().await;
+                if guard.shutting_down {
+                    break;
+                }
+                tick_game(&mut guard, plan.steps)
+            };
+            let bot_sends = frame.map(TickFrame::deliver).unwrap_or_default();
+            {
+                let mut guard = state.lock().await;
+                open_decision_windows(&mut guard, bot_sends);
+                finish_game_if_ended(&mut guard);
+                if !guard.shutting_down {
+                    update_matchmaking(&mut guard);
+                }
+            }
+
+            let duration_us = started.elapsed().as_micros() as u64;
+            metrics.observe_tick_duration(duration_us as f64 / 1_000_000.0);
+            if let Some(report) = counters.record(&options, plan, duration_us, clock_ms()) {
+                let guard = state.lock().await;
+                server_log::warn(
+                    "server",
+                    "tick overrun",
+                    guard.log_ids(None, None),
+                    json!({
+                        "overruns": report.overruns,
+                        "tickMs": options.tick_ms,
+                        "droppedSteps": report.dropped_steps,
+                        "worstMs": report.worst_duration_ms,
+                    }),
+                );
+            }
+        }
+    });
+}
+
+// Outbound traffic of one tick, captured under the state lock and encoded after it is released.
+struct TickFrame {
+    snapshot: Snapshot,
+    recipients: Vec<Recipient>,
+    bot_observations: Vec<BotDelivery>,
+    bot_deadline_ms: u64,
+    metrics: Arc<ServerMetrics>,
+}
+
+struct BotDelivery {
+    client_id: String,
+    tx: mpsc::Sender<OutboundMessage>,
+    observation: BotObservation,
+}
+
+impl TickFrame {
+    // Every send is DropOnFull, so a full queue never needs the state back. Returns when each
+    // bot observation was queued (`None` if it was dropped) so the caller can open its window.
+    fn deliver(self) -> Vec<(String, Option<Instant>)> {
+        let message = json!({
+            "type": "state",
+            "snapshot": self.snapshot,
+        });
+        let mut encoded = EncodedMessage::new(&message);
+        let (mut json_sent, mut binary_sent) = (0, 0);
+        for recipient in &self.recipients {
+            let outbound = encoded.encode(recipient.binary);
+            let binary = matches!(outbound, OutboundMessage::Binary(_));
+            if recipient.tx.try_send(outbound).is_err() {
+                self.metrics.record_dropped_send();
+            } else if binary {
+                binary_sent += 1;
+            } else {
+                json_sent += 1;
+            }
+        }
+        if let Some(text) = &encoded.text {
+            self.metrics.record_snapshot("json", text.len(), json_sent);
+        }
+        if let Some(binary) = &encoded.binary {
+            self.metrics
+                .record_snapshot("binary", binary.len(), binary_sent);
+        }
+
+        let mut bot_sends = Vec::with_capacity(self.bot_observations.len());
+        for delivery in self.bot_observations {
+            let payload = json!({
+                "type": "bot_observation",
+                "observation": delivery.observation,
+                "deadlineMs": self.bot_deadline_ms,
+            });
+            let sent = delivery
+                .tx
+                .try_send(OutboundMessage::Text(payload.to_string().into()));
+            let sent_at = match sent {
+                Ok(()) => Some(Instant::now()),
+                Err(_) => {
+                    self.metrics.record_dropped_send();
+                    None
+                }
+            };
+            bot_sends.push((delivery.client_id, sent_at));
+        }
+        bot_sends
+    }
+}
+
+// Runs `steps` fixed steps and snapshots once; events accumulate until the snapshot drains them.
+fn tick_game(state: &mut ServerState, steps: u32) -> Option<TickFrame> {
+    let game = state.game.as_mut()?;
+    for _ in 0..steps {
+        game.set_pings(&state.ping_manager.snapshot(game.current_now_ms()));
+        game.step(TICK_MS);
+        state
+            .ping_manager
+            .follow_targets(|entity_id, near| game.ping_entity_position(entity_id, near));
+        let now_ms = game.current_now_ms();
+        for request in game.take_ai_ping_requests() {
+            // AI pings share the per-player rate limit; rejected ones are simply dropped.
+            let result = state.ping_manager.place(PlacePingInput {
+                owner_id: request.player_id,
+                owner_name: request.player_name,
+                x: request.x,
+                y: request.y,
+                kind: request.kind,
+                target_id: request.target_id,
+                now_ms,
+                spectator: false,
+            });
+            state.metrics.record_ping(result.code);
+        }
+    }
+    let mut snapshot = game.build_snapshot(true);
+    snapshot.pings = state.ping_manager.snapshot(snapshot.now_ms);
+
+    Some(TickFrame {
+        snapshot,
+        recipients: broadcast_recipients(state, |member| !member.bot),
+        bot_observations: collect_bot_observations(state),
+        bot_deadline_ms: state.bot_options.decision_deadline_ms,
+        metrics: state.metrics.clone(),
+    })
+}
+
+fn finish_game_if_ended(state: &mut ServerState) {
+    let summary = {
+        let Some(game) = state.game.as_ref() else {
+            return;
+        };
+        if !game.is_ended() {
+            return;
+        }
+        game.build_summary()
+    };
+
+    server_log::info(
+        "server",
+        "match ended",
+        state.log_ids(None, None),
+        json!({
+            "reason": summary.reason,
+            "durationMs": summary.duration_ms,
+            "captureRatio": summary.capture_ratio,
+            "players": summary.ranking.len(),
+            "top": summary.ranking.first().map(|entry| json!({
+                "playerId": entry.player_id,
+                "name": entry.name,
+                "score": entry.score,
+            })),
+        }),
+    );
+    let ids = LogIds {
+        match_id: state.match_id.as_deref(),
+        ..LogIds::default()
+    };
+    state.ranking_store.record_match(&summary, ids);
+    state.metrics.record_match_ended(summary.reason);
+    if let Some(game) = state.game.as_ref() {
+        state.matchmaking.record_match_duration(game.elapsed_ms());
+    }
+    broadcast(
+        state,
+        &json!({
+            "type": "game_over",
+            "summary": summary,
+        }),
+        QueuePolicy::DisconnectOnFull,
+    );
+
+    state.game = None;
+    state.match_id = None;
+    state.running_ai_count = 0;
+    request_checkpoint(state, CheckpointCommand::Remove);
+    state.ping_manager.clear();
+    for player in state.lobby_players.values_mut() {
+        player.ai = false;
+        player.ready = false;
+        if let Some(spectator) = player.spectator_after_match.take() {
+            player.spectator = spectator;
+        }
+    }
+
+    ensure_host_assigned(state, None);
+    broadcast_lobby(state, Some("ゲーム終了。再スタート可能です".to_string()));
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LobbyCheckpoint {
+    players: Vec<LobbyPlayerInternal>,
+    settings: LobbySettings,
+    running_ai_count: usize,
+    // Ids handed out after a restore must not collide with restored ones.
+    next_id: u64,
+}
+
+type ServerCheckpoint = MatchCheckpoint<LobbyCheckpoint>;
+
+enum CheckpointCommand {
+    Save {
+        checkpoint: Box<ServerCheckpoint>,
+        done: Option<oneshot::Sender<Result<(), String>>>,
+    },
+    Remove,
+}
+
+fn capture_checkpoint(state: &ServerState) -> Option<ServerCheckpoint> {
+    let game = state.game.as_ref().filter(|game| !game.is_ended())?;
+    let match_id = state.match_id.clone()?;
+    let lobby = LobbyCheckpoint {
+        players: state
+            .lobby_players
+            .values()
+            .filter(|member| !member.spectator || member.spectator_after_match == Some(false))
+            .cloned()
+            .collect(),
+        settings: state.lobby_settings.clone(),
+        running_ai_count: state.running_ai_count,
+        next_id: NEXT_ID.load(Ordering::Relaxed),
+    };
+    Some(MatchCheckpoint::new(
+        match_id,
+        game.clone(),
+        lobby,
+        now_ms(),
+    ))
+}
+
+fn request_checkpoint(state: &ServerState, command: CheckpointCommand) -> bool {
+    state
+        .checkpoint_tx
+        .as_ref()
+        .is_some_and(|tx| tx.send(command).is_ok())
+}
+
+// File IO runs on a single task so saves and removals land in the order they were requested.
+fn start_checkpoint_writer(
+    path: PathBuf,
+) -> (mpsc::UnboundedSender<CheckpointCommand>, JoinHandle<()>) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<CheckpointCommand>();
+    let writer = tokio::spawn(async move {
+        while let Some(command) = rx.recv().await {
+            let path = path.clone();
+            let (result, done) = match command {
+                CheckpointCommand::Save { checkpoint, done } => (
+                    tokio::task::spawn_blocking(move || checkpoint.save(&path)).await,
+                    done,
+                ),
+                CheckpointCommand::Remove => (
+                    tokio::task::spawn_blocking(move || remove_checkpoint(&path)).await,
+                    None,
+                ),
+            };
+            let result =
+                result.unwrap_or_else(|error| Err(format!("checkpoint task failed: {error}")));
+            if let Err(error) = &result {
+                server_log::error(
+                    "server",
+                    "checkpoint write failed",
+                    LogIds::default(),
+                    json!({ "error": error }),
+                );
+            }
+            if let Some(done) = done {
+                let _ = done.send(result);
+            }
+        }
+    });
+    (tx, writer)
+}
+
+fn start_checkpoint_loop(state: SharedState, interval: Duration) {
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_millis(TICK_MS));
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
         loop {
-            interval.tick().await;
-            let mut guard = state.lock().await;
-            tick_game(&mut guard);
+            ticker.tick().await;
+            let guard = state.lock().await;
+            if guard.shutting_down {
+                break;
+            }
+            if let Some(checkpoint) = capture_checkpoint(&guard) {
+                let command = CheckpointCommand::Save {
+                    checkpoint: Box::new(checkpoint),
+                    done: None,
+                };
+                request_checkpoint(&guard, command);
+            }
         }
     });
 }
 
-fn tick_game(state: &mut ServerState) {
-    let mut snapshot = {
-        let Some(game) = state.game.as_mut() else {
+// Restored players start disconnected with AI in control, exactly as if they had dropped, and
+// reclaim their slot by sending `hello` with the reconnect token they already hold.
+fn restore_checkpoint(state: &mut ServerState, options: &CheckpointOptions) {
+    let checkpoint = match ServerCheckpoint::load(&options.path) {
+        Ok(Some(checkpoint)) => checkpoint,
+        Ok(None) => return,
+        Err(error) => {
+            server_log::warn(
+                "server",
+                "ignoring unreadable checkpoint",
+                LogIds::default(),
+                json!({ "error": error }),
+            );
             return;
-        };
-        game.step(TICK_MS);
-        game.build_snapshot(true)
+        }
     };
-    snapshot.pings = state.ping_manager.snapshot(snapshot.now_ms);
+    let age_ms = checkpoint.age_ms(now_ms());
+    if age_ms > options.max_age_ms {
+        server_log::info(
+            "server",
+            "discarding stale checkpoint",
+            LogIds {
+                match_id: Some(&checkpoint.match_id),
+                ..LogIds::default()
+            },
+            json!({ "ageMs": age_ms, "maxAgeMs": options.max_age_ms }),
+        );
+        let _ = remove_checkpoint(&options.path);
+        return;
+    }
 
-    broadcast(
-        state,
-        &json!({
-            "type": "state",
-            "snapshot": snapshot,
+    let MatchCheckpoint {
+        match_id,
+        mut game,
+        lobby,
+        ..
+    } = checkpoint;
+    NEXT_ID.fetch_max(lobby.next_id, Ordering::Relaxed);
+    for mut member in lobby.players {
+        member.connected = false;
+        member.ready = false;
+        if game.has_player(&member.id) {
+            member.ai = true;
+            game.set_player_connection(&member.id, false);
+        }
+        state.lobby_players.insert(member.id.clone(), member);
+    }
+    state.lobby_settings = lobby.settings;
+    state.running_ai_count = lobby.running_ai_count;
+    state.match_id = Some(match_id);
+    server_log::info(
+        "server",
+        "match restored from checkpoint",
+        state.log_ids(None, None),
+        json!({
+            "ageMs": age_ms,
+            "elapsedMs": game.elapsed_ms(),
+            "members": state.lobby_players.len(),
         }),
-        QueuePolicy::DropOnFull,
     );
+    state.game = Some(game);
+}
 
-    let summary = {
-        let Some(game) = state.game.as_ref() else {
-            return;
-        };
-        if game.is_ended() {
-            Some(game.build_summary())
-        } else {
-            None
+fn update_matchmaking(state: &mut ServerState) {
+    if state.matchmaking.is_empty() {
+        return;
+    }
+    let now = now_ms();
+    if state.game.is_none() && !lobby_is_readying_up(state) {
+        if let Some(formed) = state.matchmaking.try_form_match(now) {
+            start_queued_match(state, formed);
         }
-    };
+    }
+    if now.saturating_sub(state.queue_status_sent_at) < QUEUE_STATUS_INTERVAL_MS {
+        return;
+    }
+    state.queue_status_sent_at = now;
+    let queued: Vec<String> = state
+        .matchmaking
+        .entries()
+        .iter()
+        .map(|entry| entry.player_id.clone())
+        .collect();
+    for player_id in queued {
+        send_queue_status(state, &player_id, now);
+    }
+}
 
-    if let Some(summary) = summary {
-        state.ranking_store.record_match(&summary);
-        broadcast(
-            state,
-            &json!({
-                "type": "game_over",
-                "summary": summary,
-            }),
-            QueuePolicy::DisconnectOnFull,
-        );
+// The queue does not take the room over from members readying up for a host-driven start.
+fn lobby_is_readying_up(state: &ServerState) -> bool {
+    state
+        .lobby_players
+        .values()
+        .any(|member| member.ready && !state.matchmaking.contains(&member.id))
+}
 
-        state.game = None;
-        state.running_ai_count = 0;
-        state.ping_manager.clear();
-        for player in state.lobby_players.values_mut() {
-            player.ai = false;
+// Matched players join the game; everyone else in the room watches until it ends and then gets
+// their previous role back. Queued players keep their place for the next match.
+fn start_queued_match(state: &mut ServerState, formed: FormedMatch) {
+    let mut matched_roles = Vec::new();
+    for member in state.lobby_players.values_mut() {
+        if formed.player_ids.contains(&member.id) {
+            matched_roles.push((member.id.clone(), member.spectator, member.ready));
+            member.spectator = false;
+            member.ready = false;
+        } else if member.spectator_after_match.is_none() {
+            member.spectator_after_match = Some(member.spectator);
+            member.spectator = true;
         }
-
-        ensure_host_assigned(state, None);
-        broadcast_lobby(state, Some("ゲーム終了。再スタート可能です".to_string()));
     }
+    let mut human_ids = formed.player_ids.clone();
+    human_ids.sort_by_key(|id| player_order_key(id));
+    let settings = LobbySettings {
+        difficulty: formed.difficulty,
+        ai_player_count: normalize_ai_count(Some(formed.ai_fill as i64), &state.lobby_limits),
+        ..state.lobby_settings.clone()
+    };
+    if let Err(message) = start_match(state, &human_ids, &settings, &[]) {
+        server_log::error(
+            "server",
+            "failed to start queued match",
+            state.log_ids(None, None),
+            json!({ "error": message }),
+        );
+        for member in state.lobby_players.values_mut() {
+            if let Some(spectator) = member.spectator_after_match.take() {
+                member.spectator = spectator;
+            }
+        }
+        for (player_id, spectator, ready) in matched_roles {
+            if let Some(member) = state.lobby_players.get_mut(&player_id) {
+                member.spectator = spectator;
+                member.ready = ready;
+            }
+        }
+        state.matchmaking.requeue_front(formed.entries);
+    }
+}
+
+fn send_queue_status(state: &mut ServerState, player_id: &str, now: u64) {
+    let busy_for_ms = state.game.as_ref().map_or(0, |game| game.time_left_ms());
+    let Some(status) = state.matchmaking.status(player_id, now, busy_for_ms) else {
+        return;
+    };
+    let Some(entry) = state
+        .matchmaking
+        .entries()
+        .iter()
+        .find(|entry| entry.player_id == player_id)
+        .cloned()
+    else {
+        return;
+    };
+    let Some(client_id) = state.active_client_by_player_id.get(player_id).cloned() else {
+        return;
+    };
+    send_to_client(
+        state,
+        &client_id,
+        &json!({
+            "type": "queue_status",
+            "queued": true,
+            "position": status.position,
+            "queueSize": status.queue_size,
+            "waitedMs": status.waited_ms,
+            "etaMs": status.eta_ms,
+            "difficulty": entry.preferences.difficulty,
+            "partySize": entry.preferences.party_size,
+        }),
+        QueuePolicy::DropOnFull,
+    );
 }
 
 fn send_to_client(state: &mut ServerState, client_id: &str, message: &Value, policy: QueuePolicy) {
     let send_failed = if let Some(client) = state.clients.get(client_id) {
-        client
-            .tx
-            .try_send(OutboundMessage::Text(message.to_string()))
-            .is_err()
+        let outbound = EncodedMessage::new(message).for_client(client);
+        client.tx.try_send(outbound).is_err()
     } else {
         false
     };
-    if send_failed && policy == QueuePolicy::DisconnectOnFull {
-        disconnect_client_internal(state, client_id, false);
+    if !send_failed {
+        return;
+    }
+    match policy {
+        QueuePolicy::DropOnFull => state.metrics.record_dropped_send(),
+        QueuePolicy::DisconnectOnFull => {
+            state.metrics.record_full_queue_disconnect();
+            disconnect_client_internal(state, client_id, false);
+        }
     }
 }
 
-fn broadcast(state: &mut ServerState, message: &Value, policy: QueuePolicy) {
-    let payload = message.to_string();
-    let client_ids: Vec<String> = state.clients.keys().cloned().collect();
-    let mut failed_clients = Vec::new();
-    for client_id in client_ids {
-        let Some(client) = state.clients.get(&client_id) else {
+// Inputs that arrive while the observation is in flight are accepted; the deadline itself is set
+// by `open_decision_windows` from the instant the observation was queued.
+fn collect_bot_observations(state: &mut ServerState) -> Vec<BotDelivery> {
+    let Some(game) = state.game.as_ref() else {
+        return Vec::new();
+    };
+    let mut deliveries = Vec::new();
+    for member in state.lobby_players.values().filter(|member| member.bot) {
+        let Some(client_id) = state.active_client_by_player_id.get(&member.id) else {
+            continue;
+        };
+        let Some(client) = state.clients.get_mut(client_id) else {
+            continue;
+        };
+        let Some(observation) = game.build_bot_observation(&member.id, BOT_OBSERVATION_RADIUS)
+        else {
             continue;
         };
-        if !can_receive_broadcast(state, &client_id, client) {
+        client.decision_window = Some(DecisionWindow::InFlight);
+        deliveries.push(BotDelivery {
+            client_id: client_id.clone(),
+            tx: client.tx.clone(),
+            observation,
+        });
+    }
+    deliveries
+}
+
+fn open_decision_windows(state: &mut ServerState, bot_sends: Vec<(String, Option<Instant>)>) {
+    let budget = Duration::from_millis(state.bot_options.decision_deadline_ms);
+    for (client_id, sent_at) in bot_sends {
+        let Some(client) = state.clients.get_mut(&client_id) else {
             continue;
+        };
+        // Already answered while in flight: leave the window closed.
+        if matches!(client.decision_window, Some(DecisionWindow::InFlight)) {
+            client.decision_window = sent_at.map(|at| DecisionWindow::Until(at + budget));
         }
-        if client
+    }
+}
+
+fn broadcast(state: &mut ServerState, message: &Value, policy: QueuePolicy) {
+    broadcast_where(state, message, policy, |_| true);
+}
+
+fn broadcast_where(
+    state: &mut ServerState,
+    message: &Value,
+    policy: QueuePolicy,
+    include: impl Fn(&LobbyPlayerInternal) -> bool,
+) {
+    let mut encoded = EncodedMessage::new(message);
+    let mut failed_clients = Vec::new();
+    for recipient in broadcast_recipients(state, include) {
+        if recipient
             .tx
-            .try_send(OutboundMessage::Text(payload.clone()))
+            .try_send(encoded.encode(recipient.binary))
             .is_err()
-            && policy == QueuePolicy::DisconnectOnFull
         {
-            failed_clients.push(client_id);
+            failed_clients.push(recipient.client_id);
+        }
+    }
+    for client_id in failed_clients {
+        match policy {
+            QueuePolicy::DropOnFull => state.metrics.record_dropped_send(),
+            QueuePolicy::DisconnectOnFull => {
+                state.metrics.record_full_queue_disconnect();
+                disconnect_client_internal(state, &client_id, false);
+            }
         }
     }
-    if policy == QueuePolicy::DisconnectOnFull {
-        for client_id in failed_clients {
-            disconnect_client_internal(state, &client_id, false);
+}
+
+struct Recipient {
+    client_id: String,
+    tx: mpsc::Sender<OutboundMessage>,
+    binary: bool,
+}
+
+fn broadcast_recipients(
+    state: &ServerState,
+    include: impl Fn(&LobbyPlayerInternal) -> bool,
+) -> Vec<Recipient> {
+    state
+        .clients
+        .iter()
+        .filter(|(client_id, client)| can_receive_broadcast(state, client_id, client))
+        .filter(|(_, client)| {
+            client
+                .player_id
+                .as_ref()
+                .and_then(|player_id| state.lobby_players.get(player_id))
+                .is_some_and(&include)
+        })
+        .map(|(client_id, client)| Recipient {
+            client_id: client_id.clone(),
+            tx: client.tx.clone(),
+            binary: client.binary,
+        })
+        .collect()
+}
+
+// Encodes a message at most once per wire format while fanning out.
+struct EncodedMessage<'a> {
+    message: &'a Value,
+    text: Option<Utf8Bytes>,
+    binary: Option<Bytes>,
+}
+
+impl<'a> EncodedMessage<'a> {
+    fn new(message: &'a Value) -> Self {
+        Self {
+            message,
+            text: None,
+            binary: None,
+        }
+    }
+
+    fn for_client(&mut self, client: &ClientContext) -> OutboundMessage {
+        self.encode(client.binary)
+    }
+
+    fn encode(&mut self, binary: bool) -> OutboundMessage {
+        if binary && is_binary_message(self.message) {
+            if self.binary.is_none() {
+                self.binary = encode_binary(self.message).map(Bytes::from);
+            }
+            if let Some(binary) = &self.binary {
+                return OutboundMessage::Binary(binary.clone());
+            }
         }
+        OutboundMessage::Text(
+            self.text
+                .get_or_insert_with(|| self.message.to_string().into())
+                .clone(),
+        )
     }
 }
 
@@ -1048,6 +3024,78 @@ fn can_receive_broadcast(state: &ServerState, client_id: &str, client: &ClientCo
     state.lobby_players.contains_key(player_id)
 }
 
+fn send_error(state: &mut ServerState, client_id: &str, message: &str) {
+    send_to_client(
+        state,
+        client_id,
+        &json!({
+            "type": "error",
+            "message": message,
+        }),
+        QueuePolicy::DisconnectOnFull,
+    );
+}
+
+fn bound_member(state: &ServerState, client_id: &str) -> Option<LobbyPlayerInternal> {
+    state
+        .clients
+        .get(client_id)
+        .and_then(|ctx| ctx.player_id.as_ref())
+        .and_then(|player_id| state.lobby_players.get(player_id))
+        .cloned()
+}
+
+// Validates a host-only command aimed at another lobby member.
+fn host_moderation_target(
+    state: &mut ServerState,
+    client_id: &str,
+    target_id: &str,
+) -> Option<LobbyPlayerInternal> {
+    let Some(member) = bound_member(state, client_id) else {
+        send_error(state, client_id, "send hello first");
+        return None;
+    };
+    if state.host_id.as_deref() != Some(member.id.as_str()) {
+        send_error(state, client_id, "only host can moderate players");
+        return None;
+    }
+    if member.id == target_id {
+        send_error(state, client_id, "cannot target yourself");
+        return None;
+    }
+    let Some(target) = state.lobby_players.get(target_id).cloned() else {
+        send_error(state, client_id, "player is not in lobby");
+        return None;
+    };
+    Some(target)
+}
+
+fn kick_member(state: &mut ServerState, player_id: &str, reason: &str) {
+    server_log::info(
+        "server",
+        "player kicked",
+        state.log_ids(None, Some(player_id)),
+        Value::Null,
+    );
+    // The connection is forgotten right away so it cannot send `hello` again before the close
+    // lands, and the reconnect token stays blocked for the lobby's lifetime.
+    if let Some(client_id) = state.active_client_by_player_id.remove(player_id) {
+        if let Some(client) = state.clients.remove(&client_id) {
+            close_connection(&client.tx, KICKED_CLOSE_CODE, reason);
+        }
+    }
+    if let Some(member) = state.lobby_players.remove(player_id) {
+        state.kicked_tokens.insert(member.reconnect_token);
+    }
+    state.chat_manager.forget_player(player_id);
+    state.matchmaking.leave(player_id);
+    if let Some(game) = state.game.as_mut() {
+        if game.has_player(player_id) {
+            game.set_player_connection(player_id, false);
+        }
+    }
+}
+
 async fn send_error_to_client(state: &SharedState, client_id: &str, message: &str) {
     let mut guard = state.lock().await;
     send_to_client(
@@ -1061,6 +3109,16 @@ async fn send_error_to_client(state: &SharedState, client_id: &str, message: &st
     );
 }
 
+// Mutes, kick blocks and departed ids last as long as the lobby; an empty lobby starts over.
+fn reset_moderation_if_lobby_empty(state: &mut ServerState) {
+    if !state.lobby_players.is_empty() || state.game.is_some() {
+        return;
+    }
+    state.departed_players.clear();
+    state.kicked_tokens.clear();
+    state.chat_manager.clear_mutes();
+}
+
 fn ensure_host_assigned(state: &mut ServerState, preferred_player_id: Option<String>) {
     if state
         .host_id
@@ -1079,12 +3137,37 @@ fn ensure_host_assigned(state: &mut ServerState, preferred_player_id: Option<Str
             .map(|player| player.connected)
             .unwrap_or(false)
         {
-            state.host_id = Some(preferred_player_id);
+            set_host(state, Some(preferred_player_id), "assigned");
             return;
         }
     }
 
-    state.host_id = choose_next_host(state);
+    let next_host = choose_next_host(state);
+    set_host(state, next_host, "assigned");
+}
+
+fn set_host(state: &mut ServerState, host_id: Option<String>, reason: &str) {
+    if state.host_id == host_id {
+        return;
+    }
+    server_log::info(
+        "server",
+        "host changed",
+        state.log_ids(None, host_id.as_deref()),
+        json!({ "previous": state.host_id, "reason": reason }),
+    );
+    state.host_id = host_id;
+}
+
+// The host starts the match, so only the other connected human players need to be ready.
+fn all_humans_ready(state: &ServerState) -> bool {
+    state.lobby_players.values().all(|player| {
+        !player.connected
+            || player.spectator
+            || player.bot
+            || player.ready
+            || state.host_id.as_deref() == Some(player.id.as_str())
+    })
 }
 
 fn choose_next_host(state: &ServerState) -> Option<String> {
@@ -1124,3 +3207,285 @@ fn now_ms() -> u64 {
         .unwrap_or_default()
         .as_millis() as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use mmo_packman_rust_server::matchmaking::QueueEntry;
+
+    use super::*;
+
+    fn ranking_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "mmo-packman-server-{}-{name}.json",
+            std::process::id()
+        ))
+    }
+
+    fn test_state(name: &str) -> ServerState {
+        let config = ServerConfig {
+            ranking_db_path: ranking_path(name),
+            ..ServerConfig::default()
+        };
+        ServerState::new(
+            &config,
+            BalanceRules::default(),
+            BotOptions::new(config.bot_decision_deadline_ms),
+            ChatManagerOptions::default(),
+        )
+    }
+
+    fn add_member(state: &mut ServerState, id: &str, spectator: bool) {
+        state.lobby_players.insert(
+            id.to_string(),
+            LobbyPlayerInternal {
+                id: id.to_string(),
+                name: id.to_uppercase(),
+                connected: true,
+                ai: false,
+                bot: false,
+                spectator,
+                ready: false,
+                reconnect_token: format!("token_{id}"),
+                spectator_after_match: None,
+            },
+        );
+    }
+
+    fn add_client(state: &mut ServerState, client_id: &str) -> mpsc::Receiver<OutboundMessage> {
+        let (tx, rx) = mpsc::channel(8);
+        state.clients.insert(
+            client_id.to_string(),
+            ClientContext {
+                tx,
+                player_id: None,
+                decision_window: None,
+                protocol_version: LEGACY_PROTOCOL_VERSION,
+                capabilities: Vec::new(),
+                binary: false,
+                shutdown: None,
+            },
+        );
+        rx
+    }
+
+    fn solo_queue(state: &mut ServerState, player_id: &str) {
+        state.matchmaking.enqueue(
+            player_id,
+            QueuePreferences {
+                difficulty: None,
+                party_size: Some(1),
+            },
+            now_ms(),
+        );
+    }
+
+    #[test]
+    fn decision_window_opens_only_for_delivered_observations() {
+        let mut state = test_state("decision-window");
+        for client_id in ["bot_sent", "bot_dropped", "bot_answered"] {
+            let _ = add_client(&mut state, client_id);
+        }
+        let window =
+            |state: &ServerState, client_id: &str| state.clients[client_id].decision_window;
+        for client_id in ["bot_sent", "bot_dropped"] {
+            if let Some(client) = state.clients.get_mut(client_id) {
+                client.decision_window = Some(DecisionWindow::InFlight);
+            }
+        }
+
+        let sent_at = Instant::now();
+        open_decision_windows(
+            &mut state,
+            vec![
+                ("bot_sent".to_string(), Some(sent_at)),
+                ("bot_dropped".to_string(), None),
+                ("bot_answered".to_string(), Some(sent_at)),
+            ],
+        );
+        let budget = Duration::from_millis(state.bot_options.decision_deadline_ms);
+        assert!(matches!(
+            window(&state, "bot_sent"),
+            Some(DecisionWindow::Until(deadline)) if deadline == sent_at + budget
+        ));
+        assert!(window(&state, "bot_dropped").is_none());
+        assert!(window(&state, "bot_answered").is_none());
+    }
+
+    #[tokio::test]
+    async fn legacy_hello_is_closed_as_too_old() {
+        let state: SharedState = Arc::new(Mutex::new(test_state("legacy-hello")));
+        let mut rx = add_client(&mut *state.lock().await, "client_1");
+        let hello = parse_client_message(r#"{"type":"hello","name":"Old"}"#).expect("valid hello");
+
+        handle_client_message(state.clone(), "client_1", hello).await;
+        match rx.try_recv() {
+            Ok(OutboundMessage::Close { code, .. }) => {
+                assert_eq!(code, PROTOCOL_TOO_OLD_CLOSE_CODE)
+            }
+            _ => panic!("legacy hello should be closed with {PROTOCOL_TOO_OLD_CLOSE_CODE}"),
+        }
+        assert!(state.lock().await.lobby_players.is_empty());
+    }
+
+    #[test]
+    fn queued_match_benches_other_members_until_it_ends() {
+        let mut state = test_state("queued-match");
+        add_member(&mut state, "player_1", false);
+        add_member(&mut state, "player_2", false);
+        add_member(&mut state, "player_3", true);
+        state.host_id = Some("player_2".to_string());
+        solo_queue(&mut state, "player_1");
+
+        update_matchmaking(&mut state);
+        let game = state.game.as_ref().expect("queued match should start");
+        assert!(game.has_player("player_1"));
+        assert!(!game.has_player("player_2"));
+        let spectator = |state: &ServerState, id: &str| state.lobby_players[id].spectator;
+        assert!(!spectator(&state, "player_1"));
+        assert!(spectator(&state, "player_2"));
+        assert!(spectator(&state, "player_3"));
+
+        let checkpoint = capture_checkpoint(&state).expect("running match is checkpointed");
+        let mut saved: Vec<&str> = checkpoint
+            .lobby
+            .players
+            .iter()
+            .map(|member| member.id.as_str())
+            .collect();
+        saved.sort_unstable();
+        assert_eq!(saved, vec!["player_1", "player_2"]);
+
+        if let Some(game) = state.game.as_mut() {
+            game.force_end(GameOverReason::Timeout, "test");
+        }
+        finish_game_if_ended(&mut state);
+        let _ = std::fs::remove_file(ranking_path("queued-match"));
+        assert!(state.game.is_none());
+        assert!(!spectator(&state, "player_1"));
+        assert!(!spectator(&state, "player_2"));
+        assert!(spectator(&state, "player_3"));
+        assert!(state
+            .lobby_players
+            .values()
+            .all(|member| member.spectator_after_match.is_none()));
+    }
+
+    #[test]
+    fn queue_waits_while_the_lobby_is_readying_up() {
+        let mut state = test_state("readying-up");
+        add_member(&mut state, "player_1", false);
+        add_member(&mut state, "player_2", false);
+        solo_queue(&mut state, "player_1");
+        if let Some(member) = state.lobby_players.get_mut("player_2") {
+            member.ready = true;
+        }
+
+        update_matchmaking(&mut state);
+        assert!(state.game.is_none());
+        assert!(state.matchmaking.contains("player_1"));
+
+        if let Some(member) = state.lobby_players.get_mut("player_2") {
+            member.ready = false;
+        }
+        update_matchmaking(&mut state);
+        assert!(state.game.is_some());
+    }
+
+    #[test]
+    fn queued_match_ai_fill_respects_the_lobby_limit() {
+        let mut state = test_state("queued-ai-limit");
+        state.lobby_limits.max_ai_players = 2;
+        add_member(&mut state, "player_1", false);
+        state.matchmaking.enqueue(
+            "player_1",
+            QueuePreferences {
+                difficulty: None,
+                party_size: Some(8),
+            },
+            0,
+        );
+
+        update_matchmaking(&mut state);
+        let game = state.game.as_ref().expect("waited past the threshold");
+        assert_eq!(game.score_board().len(), 3);
+    }
+
+    #[test]
+    fn failed_queued_match_restores_roles_and_requeues_players() {
+        let mut state = test_state("queued-failure");
+        add_member(&mut state, "player_1", false);
+        let entry = QueueEntry {
+            player_id: "player_gone".to_string(),
+            preferences: QueuePreferences::default(),
+            joined_at_ms: 0,
+        };
+        state
+            .matchmaking
+            .enqueue("player_1", QueuePreferences::default(), now_ms());
+
+        start_queued_match(
+            &mut state,
+            FormedMatch {
+                player_ids: vec![entry.player_id.clone()],
+                entries: vec![entry],
+                difficulty: Difficulty::Normal,
+                party_size: 1,
+                ai_fill: 0,
+            },
+        );
+        assert!(state.game.is_none());
+        assert!(!state.lobby_players["player_1"].spectator);
+        assert_eq!(state.lobby_players["player_1"].spectator_after_match, None);
+        assert_eq!(state.matchmaking.entries()[0].player_id, "player_gone");
+        assert!(state.matchmaking.contains("player_1"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_frames_skip_a_full_send_queue() {
+        let (tx, rx) = mpsc::channel(2);
+        for _ in 0..2 {
+            tx.try_send(OutboundMessage::Text("queued".into()))
+                .expect("queue has room");
+        }
+        assert!(tx
+            .try_send(OutboundMessage::Text("overflow".into()))
+            .is_err());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        shutdown_tx
+            .send(vec![
+                OutboundMessage::Text("shutdown".into()),
+                OutboundMessage::Close {
+                    code: SHUTDOWN_CLOSE_CODE,
+                    reason: "server shutting down".to_string(),
+                },
+            ])
+            .expect("writer not started yet");
+
+        let written = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = futures_util::sink::unfold(written.clone(), |written, message: Message| async {
+            written.lock().expect("sink lock").push(message);
+            Ok::<_, std::convert::Infallible>(written)
+        });
+        let metrics = Arc::new(ServerMetrics::default());
+        write_outbound(Box::pin(sink), rx, shutdown_rx, metrics.clone()).await;
+
+        let written = std::mem::take(&mut *written.lock().expect("sink lock"));
+        assert_eq!(written.len(), 2);
+        assert!(matches!(&written[0], Message::Text(text) if text.as_str() == "shutdown"));
+        assert!(matches!(
+            &written[1],
+            Message::Close(Some(frame)) if frame.code == SHUTDOWN_CLOSE_CODE
+        ));
+
+        let (_tx, rx) = mpsc::channel(1);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let _ = shutdown_tx.send(vec![OutboundMessage::Text("shutdown".into())]);
+        let broken = futures_util::sink::unfold((), |_, _: Message| async {
+            Err::<(), _>(axum::Error::new("socket closed"))
+        });
+        write_outbound(Box::pin(broken), rx, shutdown_rx, metrics.clone()).await;
+        assert!(metrics
+            .render(&MetricsGauges::default())
+            .contains("mmo_packman_shutdown_send_failures_total 1\n"));
+    }
+}
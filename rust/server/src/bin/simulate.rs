@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use clap::Parser;
 use mmo_packman_rust_server::constants::TICK_MS;
-use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions};
-use mmo_packman_rust_server::types::{Difficulty, RuntimeEvent, Snapshot, StartPlayer};
+use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions, SquadPlannerMode};
+use mmo_packman_rust_server::types::{AiSkill, Difficulty, RuntimeEvent, Snapshot, StartPlayer};
 use serde::Serialize;
 
 #[derive(Parser, Debug)]
@@ -17,6 +19,13 @@ struct Cli {
     difficulty: Option<String>,
     #[arg(long)]
     seed: Option<u64>,
+    #[arg(long = "ai-skill")]
+    ai_skill: Option<String>,
+    #[arg(long = "skill-sweep")]
+    skill_sweep: bool,
+    // `auto` (default, as on the live server), `on` or `off`.
+    #[arg(long)]
+    squad: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -27,6 +36,10 @@ struct Scenario {
     minutes: i32,
     difficulty: Difficulty,
     seed: u32,
+    #[serde(rename = "aiSkill")]
+    ai_skill: AiSkill,
+    #[serde(rename = "squadPlanner")]
+    squad_planner: SquadPlannerMode,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -37,11 +50,19 @@ struct ScenarioResultLine {
     ai_players: usize,
     minutes: i32,
     difficulty: Difficulty,
+    #[serde(rename = "aiSkill")]
+    ai_skill: AiSkill,
     reason: mmo_packman_rust_server::types::GameOverReason,
     #[serde(rename = "maxCapture")]
     max_capture: f32,
     #[serde(rename = "minCaptureAfter70")]
     min_capture_after70: f32,
+    #[serde(rename = "squadPlanner")]
+    squad_planner: bool,
+    #[serde(rename = "timeToCapture50Ms")]
+    time_to_capture50_ms: Option<u64>,
+    #[serde(rename = "timeToCapture70Ms")]
+    time_to_capture70_ms: Option<u64>,
     #[serde(rename = "dotEaten")]
     dot_eaten: i32,
     #[serde(rename = "dotRespawned")]
@@ -80,6 +101,12 @@ fn main() {
     }
 }
 
+fn scenario_ai_skills(scenario: &Scenario) -> HashMap<String, AiSkill> {
+    (0..scenario.ai_players)
+        .map(|idx| (format!("ai_{}", idx + 1), scenario.ai_skill))
+        .collect()
+}
+
 fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
     let mut start_players = Vec::new();
     for idx in 0..scenario.ai_players {
@@ -88,6 +115,7 @@ fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
             name: format!("AI-{:02}", idx + 1),
             reconnect_token: format!("sim_{}_{}", scenario.seed, idx + 1),
             connected: false,
+            bot: false,
         });
     }
 
@@ -97,12 +125,17 @@ fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
         scenario.seed,
         GameEngineOptions {
             time_limit_ms_override: Some((scenario.minutes as u64) * 60_000),
+            ai_skills: scenario_ai_skills(scenario),
+            squad_planner: scenario.squad_planner,
+            ..GameEngineOptions::default()
         },
     );
 
     let mut max_capture = 0.0f32;
     let mut min_capture_after_70 = 1.0f32;
     let mut crossed_70 = false;
+    let mut time_to_capture50_ms = None;
+    let mut time_to_capture70_ms = None;
     let mut dot_eaten = 0;
     let mut dot_respawned = 0;
     let mut downs = 0;
@@ -125,6 +158,13 @@ fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
         }
 
         max_capture = max_capture.max(snapshot.capture_ratio);
+        let elapsed_ms = tick_safety as u64 * TICK_MS;
+        if snapshot.capture_ratio >= 0.5 && time_to_capture50_ms.is_none() {
+            time_to_capture50_ms = Some(elapsed_ms);
+        }
+        if snapshot.capture_ratio >= 0.7 && time_to_capture70_ms.is_none() {
+            time_to_capture70_ms = Some(elapsed_ms);
+        }
         if snapshot.capture_ratio >= 0.7 {
             crossed_70 = true;
         }
@@ -161,6 +201,7 @@ fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
         ai_players: scenario.ai_players,
         minutes: scenario.minutes,
         difficulty: scenario.difficulty,
+        ai_skill: scenario.ai_skill,
         reason: summary.reason,
         max_capture: (max_capture * 1000.0).round() / 10.0,
         min_capture_after70: (if crossed_70 {
@@ -170,6 +211,9 @@ fn run_scenario(scenario: &Scenario) -> ScenarioResultLine {
         } * 1000.0)
             .round()
             / 10.0,
+        squad_planner: engine.squad_planner_enabled(),
+        time_to_capture50_ms,
+        time_to_capture70_ms,
         dot_eaten,
         dot_respawned,
         downs,
@@ -216,6 +260,22 @@ fn validate_snapshot(snapshot: &Snapshot, anomalies: &mut Vec<String>) {
 }
 
 fn resolve_scenarios(cli: &Cli) -> Vec<Scenario> {
+    let base = resolve_base_scenarios(cli);
+    if !cli.skill_sweep {
+        return base;
+    }
+    base.into_iter()
+        .flat_map(|scenario| {
+            AiSkill::ALL.into_iter().map(move |ai_skill| Scenario {
+                name: format!("{}-{}", scenario.name, ai_skill.as_str()),
+                ai_skill,
+                ..scenario.clone()
+            })
+        })
+        .collect()
+}
+
+fn resolve_base_scenarios(cli: &Cli) -> Vec<Scenario> {
     let seed = normalize_seed(cli.seed.unwrap_or_else(|| {
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -227,6 +287,16 @@ fn resolve_scenarios(cli: &Cli) -> Vec<Scenario> {
         .as_deref()
         .and_then(Difficulty::parse)
         .unwrap_or(Difficulty::Normal);
+    let ai_skill = cli
+        .ai_skill
+        .as_deref()
+        .and_then(AiSkill::parse)
+        .unwrap_or_default();
+    let squad_planner = cli
+        .squad
+        .as_deref()
+        .and_then(SquadPlannerMode::parse)
+        .unwrap_or_default();
 
     if cli.single || cli.ai.is_some() || cli.minutes.is_some() {
         return vec![Scenario {
@@ -235,6 +305,8 @@ fn resolve_scenarios(cli: &Cli) -> Vec<Scenario> {
             minutes: clamp_i32(cli.minutes.unwrap_or(3), 1, 10),
             difficulty,
             seed,
+            ai_skill,
+            squad_planner,
         }];
     }
 
@@ -245,6 +317,8 @@ fn resolve_scenarios(cli: &Cli) -> Vec<Scenario> {
             minutes: 2,
             difficulty: Difficulty::Normal,
             seed,
+            ai_skill,
+            squad_planner,
         },
         Scenario {
             name: "balance-check-ai5".to_string(),
@@ -252,6 +326,8 @@ fn resolve_scenarios(cli: &Cli) -> Vec<Scenario> {
             minutes: 5,
             difficulty: Difficulty::Normal,
             seed: normalize_seed(seed as u64 + 1),
+            ai_skill,
+            squad_planner,
         },
     ]
 }
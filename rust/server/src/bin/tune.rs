@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::Parser;
+use mmo_packman_rust_server::constants::TICK_MS;
+use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions};
+use mmo_packman_rust_server::rng::Rng;
+use mmo_packman_rust_server::rules::BalanceRules;
+use mmo_packman_rust_server::types::{Difficulty, GameOverReason, StartPlayer};
+use serde::Serialize;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Cli {
+    #[arg(long, default_value_t = 5)]
+    ai: i32,
+    #[arg(long)]
+    minutes: Option<i32>,
+    #[arg(long)]
+    difficulty: Option<String>,
+    #[arg(long, default_value_t = 0.55)]
+    target_min: f64,
+    #[arg(long, default_value_t = 0.70)]
+    target_max: f64,
+    #[arg(long = "knob", value_name = "NAME=MIN:MAX")]
+    knobs: Vec<String>,
+    #[arg(long, default_value_t = 10)]
+    candidates: usize,
+    #[arg(long, default_value_t = 3)]
+    rounds: usize,
+    #[arg(long, default_value_t = 12)]
+    matches: usize,
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long)]
+    rules: Option<PathBuf>,
+    #[arg(long, default_value = "tuned_rules.json")]
+    out: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Knob {
+    RegenScale,
+    GraceScale,
+    GhostSpeed,
+    Maintenance,
+    Casual5Speed,
+    Casual5RegenRelief,
+    Casual10Speed,
+    Casual10RegenRelief,
+}
+
+impl Knob {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regen-scale" => Some(Self::RegenScale),
+            "grace-scale" => Some(Self::GraceScale),
+            "ghost-speed" => Some(Self::GhostSpeed),
+            "maintenance" => Some(Self::Maintenance),
+            "casual5-speed" => Some(Self::Casual5Speed),
+            "casual5-regen-relief" => Some(Self::Casual5RegenRelief),
+            "casual10-speed" => Some(Self::Casual10Speed),
+            "casual10-regen-relief" => Some(Self::Casual10RegenRelief),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::RegenScale => "regen-scale",
+            Self::GraceScale => "grace-scale",
+            Self::GhostSpeed => "ghost-speed",
+            Self::Maintenance => "maintenance",
+            Self::Casual5Speed => "casual5-speed",
+            Self::Casual5RegenRelief => "casual5-regen-relief",
+            Self::Casual10Speed => "casual10-speed",
+            Self::Casual10RegenRelief => "casual10-regen-relief",
+        }
+    }
+
+    fn baseline(self, base: &BalanceRules, difficulty: Difficulty) -> f32 {
+        match self {
+            Self::RegenScale | Self::GraceScale => 1.0,
+            Self::GhostSpeed => base.difficulty_multipliers.get(difficulty).ghost_speed,
+            Self::Maintenance => base.difficulty_multipliers.get(difficulty).maintenance,
+            Self::Casual5Speed => base.casual_bands.five_player_speed_multiplier,
+            Self::Casual5RegenRelief => base.casual_bands.five_player_regen_relief,
+            Self::Casual10Speed => base.casual_bands.ten_player_speed_multiplier,
+            Self::Casual10RegenRelief => base.casual_bands.ten_player_regen_relief,
+        }
+    }
+
+    fn apply(self, rules: &mut BalanceRules, difficulty: Difficulty, value: f32) {
+        match self {
+            Self::RegenScale => {
+                for band in &mut rules.capture_pressure {
+                    band.regen_multiplier *= value;
+                }
+                rules.capture_pressure_default.regen_multiplier *= value;
+            }
+            Self::GraceScale => {
+                for band in &mut rules.capture_pressure {
+                    band.grace_ms = (band.grace_ms as f32 * value).round() as u64;
+                }
+                let fallback = &mut rules.capture_pressure_default;
+                fallback.grace_ms = (fallback.grace_ms as f32 * value).round() as u64;
+            }
+            Self::GhostSpeed => {
+                rules.difficulty_multipliers.get_mut(difficulty).ghost_speed = value;
+            }
+            Self::Maintenance => {
+                rules.difficulty_multipliers.get_mut(difficulty).maintenance = value;
+            }
+            Self::Casual5Speed => rules.casual_bands.five_player_speed_multiplier = value,
+            Self::Casual5RegenRelief => rules.casual_bands.five_player_regen_relief = value,
+            Self::Casual10Speed => rules.casual_bands.ten_player_speed_multiplier = value,
+            Self::Casual10RegenRelief => rules.casual_bands.ten_player_regen_relief = value,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct KnobRange {
+    knob: Knob,
+    min: f32,
+    max: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+struct MatchOutcome {
+    reason: Option<GameOverReason>,
+    duration_ms: u64,
+    max_capture: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct EvidenceLine {
+    round: usize,
+    candidate: usize,
+    knobs: BTreeMap<&'static str, f32>,
+    matches: usize,
+    victories: usize,
+    timeouts: usize,
+    #[serde(rename = "allDown")]
+    all_down: usize,
+    collapses: usize,
+    #[serde(rename = "victoryRate")]
+    victory_rate: f64,
+    #[serde(rename = "avgDurationMs")]
+    avg_duration_ms: u64,
+    #[serde(rename = "avgMaxCapture")]
+    avg_max_capture: f32,
+    #[serde(rename = "withinTarget")]
+    within_target: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Recommendation {
+    recommended: EvidenceLine,
+    #[serde(rename = "rulesPath")]
+    rules_path: String,
+    #[serde(rename = "targetMin")]
+    target_min: f64,
+    #[serde(rename = "targetMax")]
+    target_max: f64,
+    #[serde(rename = "aiPlayers")]
+    ai_players: usize,
+    difficulty: Difficulty,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(&cli) {
+        eprintln!("[tune] {error}");
+        std::process::exit(2);
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), String> {
+    let base_rules = match &cli.rules {
+        Some(path) => BalanceRules::load(path)?,
+        None => BalanceRules::default(),
+    };
+    let difficulty = match cli.difficulty.as_deref() {
+        Some(raw) => Difficulty::parse(raw).ok_or_else(|| format!("unknown difficulty: {raw}"))?,
+        None => Difficulty::Normal,
+    };
+    if !(0.0..=1.0).contains(&cli.target_min) || cli.target_min > cli.target_max {
+        return Err("target range must satisfy 0 <= target-min <= target-max <= 1".to_string());
+    }
+    let ranges = parse_knob_ranges(&cli.knobs)?;
+    let ai_players = cli.ai.clamp(1, 100) as usize;
+    let time_limit_ms = cli
+        .minutes
+        .map(|minutes| minutes.clamp(1, 30) as u64 * 60_000);
+    let matches = cli.matches.max(1);
+    let seed = cli.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }) as u32;
+    let match_seeds: Vec<u32> = (0..matches as u32)
+        .map(|idx| seed.wrapping_add(idx))
+        .collect();
+
+    let mut rng = Rng::new(seed);
+    let mut current = ranges.clone();
+    let mut best: Option<(f64, EvidenceLine, BalanceRules)> = None;
+
+    for round in 0..cli.rounds.max(1) {
+        for candidate in 0..cli.candidates.max(1) {
+            let values: Vec<(Knob, f32)> = current
+                .iter()
+                .map(|range| {
+                    let value = if round == 0 && candidate == 0 {
+                        range
+                            .knob
+                            .baseline(&base_rules, difficulty)
+                            .clamp(range.min, range.max)
+                    } else {
+                        range.min + rng.next_f32() * (range.max - range.min)
+                    };
+                    (range.knob, round_knob(value))
+                })
+                .collect();
+
+            let mut rules = base_rules.clone();
+            for (knob, value) in &values {
+                knob.apply(&mut rules, difficulty, *value);
+            }
+            rules.validate()?;
+
+            let outcomes = run_matches(&rules, ai_players, difficulty, time_limit_ms, &match_seeds);
+            let line = summarize(round, candidate, &values, &outcomes, cli);
+            println!(
+                "{}",
+                serde_json::to_string(&line).expect("evidence line should serialize")
+            );
+
+            let score = candidate_score(&line, cli, &values, &base_rules, difficulty);
+            let improved = best
+                .as_ref()
+                .map(|(best_score, _, _)| score < *best_score)
+                .unwrap_or(true);
+            if improved {
+                best = Some((score, line, rules));
+            }
+        }
+
+        if let Some((_, line, _)) = &best {
+            current = narrow_ranges(&ranges, &current, &line.knobs);
+        }
+    }
+
+    let (_, line, rules) = best.ok_or_else(|| "no candidate evaluated".to_string())?;
+    let text = serde_json::to_string_pretty(&rules)
+        .map_err(|error| format!("failed to serialize rules: {error}"))?;
+    std::fs::write(&cli.out, text + "\n")
+        .map_err(|error| format!("failed to write {}: {error}", cli.out.display()))?;
+
+    println!(
+        "{}",
+        serde_json::to_string(&Recommendation {
+            recommended: line,
+            rules_path: cli.out.to_string_lossy().to_string(),
+            target_min: cli.target_min,
+            target_max: cli.target_max,
+            ai_players,
+            difficulty,
+        })
+        .expect("recommendation should serialize")
+    );
+    Ok(())
+}
+
+fn parse_knob_ranges(raw: &[String]) -> Result<Vec<KnobRange>, String> {
+    let defaults = ["regen-scale=0.6:1.4", "grace-scale=0.6:1.4"];
+    let specs: Vec<&str> = if raw.is_empty() {
+        defaults.to_vec()
+    } else {
+        raw.iter().map(|spec| spec.as_str()).collect()
+    };
+
+    let mut ranges: Vec<KnobRange> = Vec::new();
+    for spec in specs {
+        let (name, range) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("knob must be NAME=MIN:MAX: {spec}"))?;
+        let knob = Knob::parse(name.trim()).ok_or_else(|| format!("unknown knob: {name}"))?;
+        let (min, max) = range
+            .split_once(':')
+            .ok_or_else(|| format!("knob must be NAME=MIN:MAX: {spec}"))?;
+        let min: f32 = min
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid knob min: {spec}"))?;
+        let max: f32 = max
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid knob max: {spec}"))?;
+        if !min.is_finite() || !max.is_finite() || min <= 0.0 || min > max {
+            return Err(format!("knob range must satisfy 0 < MIN <= MAX: {spec}"));
+        }
+        if ranges.iter().any(|range| range.knob == knob) {
+            return Err(format!("duplicate knob: {name}"));
+        }
+        ranges.push(KnobRange { knob, min, max });
+    }
+    ranges.sort_by_key(|range| range.knob);
+    Ok(ranges)
+}
+
+fn run_matches(
+    rules: &BalanceRules,
+    ai_players: usize,
+    difficulty: Difficulty,
+    time_limit_ms: Option<u64>,
+    seeds: &[u32],
+) -> Vec<MatchOutcome> {
+    let workers = std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(seeds.len())
+        .max(1);
+    let chunk_size = seeds.len().div_ceil(workers);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = seeds
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|seed| {
+                            run_match(rules.clone(), ai_players, difficulty, time_limit_ms, *seed)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("match worker should not panic"))
+            .collect()
+    })
+}
+
+fn run_match(
+    rules: BalanceRules,
+    ai_players: usize,
+    difficulty: Difficulty,
+    time_limit_ms: Option<u64>,
+    seed: u32,
+) -> MatchOutcome {
+    let start_players = (0..ai_players)
+        .map(|idx| StartPlayer {
+            id: format!("ai_{}", idx + 1),
+            name: format!("AI-{:02}", idx + 1),
+            reconnect_token: format!("tune_{}_{}", seed, idx + 1),
+            connected: false,
+            bot: false,
+        })
+        .collect();
+    let mut engine = GameEngine::new(
+        start_players,
+        difficulty,
+        seed,
+        GameEngineOptions {
+            time_limit_ms_override: time_limit_ms,
+            rules,
+            ..GameEngineOptions::default()
+        },
+    );
+
+    let mut outcome = MatchOutcome::default();
+    while !engine.is_ended() {
+        engine.step(TICK_MS);
+        let snapshot = engine.build_snapshot(true);
+        outcome.max_capture = outcome.max_capture.max(snapshot.capture_ratio);
+    }
+    let summary = engine.build_summary();
+    outcome.reason = Some(summary.reason);
+    outcome.duration_ms = summary.duration_ms;
+    outcome
+}
+
+fn summarize(
+    round: usize,
+    candidate: usize,
+    values: &[(Knob, f32)],
+    outcomes: &[MatchOutcome],
+    cli: &Cli,
+) -> EvidenceLine {
+    let count_reason = |reason: GameOverReason| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.reason == Some(reason))
+            .count()
+    };
+    let matches = outcomes.len().max(1);
+    let victories = count_reason(GameOverReason::Victory);
+    let victory_rate = victories as f64 / matches as f64;
+    let total_duration: u64 = outcomes.iter().map(|outcome| outcome.duration_ms).sum();
+    let total_capture: f32 = outcomes.iter().map(|outcome| outcome.max_capture).sum();
+
+    EvidenceLine {
+        round,
+        candidate,
+        knobs: values
+            .iter()
+            .map(|(knob, value)| (knob.name(), *value))
+            .collect(),
+        matches: outcomes.len(),
+        victories,
+        timeouts: count_reason(GameOverReason::Timeout),
+        all_down: count_reason(GameOverReason::AllDown),
+        collapses: count_reason(GameOverReason::Collapse),
+        victory_rate,
+        avg_duration_ms: total_duration / matches as u64,
+        avg_max_capture: ((total_capture / matches as f32) * 1000.0).round() / 10.0,
+        within_target: victory_rate >= cli.target_min && victory_rate <= cli.target_max,
+    }
+}
+
+fn candidate_score(
+    line: &EvidenceLine,
+    cli: &Cli,
+    values: &[(Knob, f32)],
+    base: &BalanceRules,
+    difficulty: Difficulty,
+) -> f64 {
+    let outside = if line.victory_rate < cli.target_min {
+        cli.target_min - line.victory_rate
+    } else if line.victory_rate > cli.target_max {
+        line.victory_rate - cli.target_max
+    } else {
+        0.0
+    };
+    let center = (cli.target_min + cli.target_max) / 2.0;
+    let drift: f64 = values
+        .iter()
+        .map(|(knob, value)| {
+            let baseline = knob.baseline(base, difficulty).max(0.001);
+            ((*value - baseline) / baseline).abs() as f64
+        })
+        .sum();
+
+    outside * 100.0 + (line.victory_rate - center).abs() + drift * 0.01
+}
+
+fn narrow_ranges(
+    original: &[KnobRange],
+    current: &[KnobRange],
+    best: &BTreeMap<&'static str, f32>,
+) -> Vec<KnobRange> {
+    original
+        .iter()
+        .zip(current)
+        .map(|(original, current)| {
+            let center = best[original.knob.name()];
+            let half = (current.max - current.min) / 4.0;
+            KnobRange {
+                knob: original.knob,
+                min: (center - half).max(original.min),
+                max: (center + half).min(original.max),
+            }
+        })
+        .collect()
+}
+
+fn round_knob(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
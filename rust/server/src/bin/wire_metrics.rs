@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use clap::Parser;
+use mmo_packman_rust_server::constants::TICK_MS;
+use mmo_packman_rust_server::engine::{GameEngine, GameEngineOptions};
+use mmo_packman_rust_server::types::{Difficulty, StartPlayer};
+use mmo_packman_rust_server::wire_codec::encode_binary;
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Cli {
+    #[arg(long, default_value_t = 100)]
+    players: usize,
+    #[arg(long, default_value_t = 1_200)]
+    ticks: usize,
+    #[arg(long, default_value_t = 4_242)]
+    seed: u32,
+    #[arg(long)]
+    difficulty: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SizeStats {
+    #[serde(rename = "jsonBytes")]
+    json_bytes: f64,
+    #[serde(rename = "binaryBytes")]
+    binary_bytes: f64,
+    #[serde(rename = "reductionRatio")]
+    reduction_ratio: f64,
+}
+
+impl SizeStats {
+    fn new(json_bytes: f64, binary_bytes: f64) -> Self {
+        Self {
+            json_bytes,
+            binary_bytes,
+            reduction_ratio: if json_bytes > 0.0 {
+                1.0 - binary_bytes / json_bytes
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    players: usize,
+    ticks: usize,
+    seed: u32,
+    difficulty: Difficulty,
+    #[serde(rename = "gameInit")]
+    game_init: SizeStats,
+    #[serde(rename = "stateAvg")]
+    state_avg: SizeStats,
+    #[serde(rename = "stateMax")]
+    state_max: SizeStats,
+    #[serde(rename = "jsonEncodeUsPerTick")]
+    json_encode_us_per_tick: f64,
+    #[serde(rename = "binaryEncodeUsPerTick")]
+    binary_encode_us_per_tick: f64,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let difficulty = cli
+        .difficulty
+        .as_deref()
+        .and_then(Difficulty::parse)
+        .unwrap_or(Difficulty::Normal);
+    let start_players = (0..cli.players)
+        .map(|idx| StartPlayer {
+            id: format!("ai_{}", idx + 1),
+            name: format!("AI-{:02}", idx + 1),
+            reconnect_token: format!("wire_{}_{}", cli.seed, idx + 1),
+            connected: false,
+            bot: false,
+        })
+        .collect();
+    let mut engine = GameEngine::new(
+        start_players,
+        difficulty,
+        cli.seed,
+        GameEngineOptions::default(),
+    );
+
+    let game_init = json!({
+        "type": "game_init",
+        "meId": "ai_1",
+        "world": engine.get_world_init(),
+        "config": engine.config,
+        "startedAtMs": engine.started_at_ms,
+        "seed": engine.seed(),
+        "isSpectator": false,
+    });
+    let game_init = SizeStats::new(
+        game_init.to_string().len() as f64,
+        encoded_len(&game_init) as f64,
+    );
+
+    let mut ticks = 0usize;
+    let (mut json_total, mut binary_total) = (0usize, 0usize);
+    let (mut json_max, mut binary_max) = (0usize, 0usize);
+    let (mut json_us, mut binary_us) = (0u128, 0u128);
+    while ticks < cli.ticks && !engine.is_ended() {
+        engine.step(TICK_MS);
+        let message = json!({
+            "type": "state",
+            "snapshot": engine.build_snapshot(true),
+        });
+
+        let started = Instant::now();
+        let json_len = message.to_string().len();
+        json_us += started.elapsed().as_micros();
+        let started = Instant::now();
+        let binary_len = encoded_len(&message);
+        binary_us += started.elapsed().as_micros();
+
+        json_total += json_len;
+        binary_total += binary_len;
+        json_max = json_max.max(json_len);
+        binary_max = binary_max.max(binary_len);
+        ticks += 1;
+    }
+
+    let per_tick = |total: usize| total as f64 / ticks.max(1) as f64;
+    let report = Report {
+        players: cli.players,
+        ticks,
+        seed: cli.seed,
+        difficulty,
+        game_init,
+        state_avg: SizeStats::new(per_tick(json_total), per_tick(binary_total)),
+        state_max: SizeStats::new(json_max as f64, binary_max as f64),
+        json_encode_us_per_tick: json_us as f64 / ticks.max(1) as f64,
+        binary_encode_us_per_tick: binary_us as f64 / ticks.max(1) as f64,
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("report should serialize")
+    );
+}
+
+fn encoded_len(message: &serde_json::Value) -> usize {
+    encode_binary(message).map_or(0, |binary| binary.len())
+}
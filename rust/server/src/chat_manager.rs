@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::server_utils::sanitize_chat_text;
+use crate::types::{ChatChannel, ChatMessageView};
+
+static NEXT_CHAT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatManagerOptions {
+    pub max_length: usize,
+    pub rate_window_ms: u64,
+    pub max_per_window: usize,
+    pub history_size: usize,
+    pub banned_words: Vec<String>,
+}
+
+impl Default for ChatManagerOptions {
+    fn default() -> Self {
+        Self {
+            max_length: 120,
+            rate_window_ms: 5_000,
+            max_per_window: 5,
+            history_size: 50,
+            banned_words: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PostChatInput {
+    pub sender_id: String,
+    pub sender_name: String,
+    pub channel: ChatChannel,
+    pub text: String,
+    pub now_ms: u64,
+    pub spectator: bool,
+}
+
+pub struct ChatManager {
+    options: ChatManagerOptions,
+    history: VecDeque<ChatMessageView>,
+    history_by_sender: HashMap<String, Vec<u64>>,
+    muted: HashSet<String>,
+}
+
+impl ChatManager {
+    pub fn new(mut options: ChatManagerOptions) -> Self {
+        options.banned_words = options
+            .banned_words
+            .iter()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        Self {
+            options,
+            history: VecDeque::new(),
+            history_by_sender: HashMap::new(),
+            muted: HashSet::new(),
+        }
+    }
+
+    pub fn post(&mut self, input: PostChatInput) -> Result<ChatMessageView, &'static str> {
+        if self.muted.contains(&input.sender_id) {
+            return Err("you are muted");
+        }
+        match input.channel {
+            ChatChannel::Team if input.spectator => {
+                return Err("spectator cannot use team chat");
+            }
+            ChatChannel::Spectator if !input.spectator => {
+                return Err("only spectators can use spectator chat");
+            }
+            _ => {}
+        }
+        let text = sanitize_chat_text(&input.text, self.options.max_length)
+            .ok_or("chat message is empty")?;
+
+        let history = self
+            .history_by_sender
+            .entry(input.sender_id.clone())
+            .or_default();
+        history.retain(|at| input.now_ms.saturating_sub(*at) <= self.options.rate_window_ms);
+        if history.len() >= self.options.max_per_window {
+            return Err("chat rate limit exceeded");
+        }
+        history.push(input.now_ms);
+
+        let message = ChatMessageView {
+            id: format!("chat_{}", NEXT_CHAT_ID.fetch_add(1, Ordering::Relaxed)),
+            sender_id: input.sender_id,
+            sender_name: input.sender_name,
+            channel: input.channel,
+            text: self.filter_words(&text),
+            sent_at_ms: input.now_ms,
+        };
+        self.history.push_back(message.clone());
+        while self.history.len() > self.options.history_size {
+            self.history.pop_front();
+        }
+        Ok(message)
+    }
+
+    pub fn history_for(&self, spectator: bool) -> Vec<ChatMessageView> {
+        self.history
+            .iter()
+            .filter(|message| message.channel.is_visible_to(spectator))
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_muted(&mut self, player_id: &str, muted: bool) {
+        if muted {
+            self.muted.insert(player_id.to_string());
+        } else {
+            self.muted.remove(player_id);
+        }
+    }
+
+    pub fn is_muted(&self, player_id: &str) -> bool {
+        self.muted.contains(player_id)
+    }
+
+    // Drops rate-limit history only; a mute outlives disconnects until `clear_mutes`.
+    pub fn forget_player(&mut self, player_id: &str) {
+        self.history_by_sender.remove(player_id);
+    }
+
+    pub fn clear_mutes(&mut self) {
+        self.muted.clear();
+    }
+
+    // Case-insensitive; each banned word is masked with `*` of the same length.
+    fn filter_words(&self, text: &str) -> String {
+        if self.options.banned_words.is_empty() {
+            return text.to_string();
+        }
+        let mut chars: Vec<char> = text.chars().collect();
+        let lowered: Vec<char> = chars
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect();
+        for word in &self.options.banned_words {
+            let word: Vec<char> = word.chars().collect();
+            if word.len() > lowered.len() {
+                continue;
+            }
+            for start in 0..=lowered.len() - word.len() {
+                if lowered[start..start + word.len()] == word[..] {
+                    chars[start..start + word.len()].fill('*');
+                }
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(
+        manager: &mut ChatManager,
+        sender_id: &str,
+        channel: ChatChannel,
+        text: &str,
+        now_ms: u64,
+        spectator: bool,
+    ) -> Result<ChatMessageView, &'static str> {
+        manager.post(PostChatInput {
+            sender_id: sender_id.to_string(),
+            sender_name: sender_id.to_uppercase(),
+            channel,
+            text: text.to_string(),
+            now_ms,
+            spectator,
+        })
+    }
+
+    #[test]
+    fn channels_are_restricted_by_role() {
+        let mut manager = ChatManager::new(ChatManagerOptions::default());
+        assert_eq!(
+            post(&mut manager, "s1", ChatChannel::Team, "hi", 0, true).unwrap_err(),
+            "spectator cannot use team chat"
+        );
+        assert_eq!(
+            post(&mut manager, "p1", ChatChannel::Spectator, "hi", 0, false).unwrap_err(),
+            "only spectators can use spectator chat"
+        );
+        assert!(post(&mut manager, "p1", ChatChannel::Team, "go left", 0, false).is_ok());
+        assert!(post(&mut manager, "s1", ChatChannel::Spectator, "nice", 0, true).is_ok());
+        assert!(post(&mut manager, "s1", ChatChannel::Lobby, "gg", 0, true).is_ok());
+
+        let channels = |spectator| {
+            manager
+                .history_for(spectator)
+                .iter()
+                .map(|message| message.channel)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(channels(false), vec![ChatChannel::Team, ChatChannel::Lobby]);
+        assert_eq!(
+            channels(true),
+            vec![ChatChannel::Spectator, ChatChannel::Lobby]
+        );
+    }
+
+    #[test]
+    fn rate_limit_and_mute_reject_messages() {
+        let mut manager = ChatManager::new(ChatManagerOptions {
+            rate_window_ms: 1_000,
+            max_per_window: 2,
+            ..ChatManagerOptions::default()
+        });
+        assert!(post(&mut manager, "p1", ChatChannel::Lobby, "a", 0, false).is_ok());
+        assert!(post(&mut manager, "p1", ChatChannel::Lobby, "b", 100, false).is_ok());
+        assert_eq!(
+            post(&mut manager, "p1", ChatChannel::Lobby, "c", 200, false).unwrap_err(),
+            "chat rate limit exceeded"
+        );
+        assert!(post(&mut manager, "p1", ChatChannel::Lobby, "d", 1_101, false).is_ok());
+
+        manager.set_muted("p1", true);
+        assert_eq!(
+            post(&mut manager, "p1", ChatChannel::Lobby, "e", 5_000, false).unwrap_err(),
+            "you are muted"
+        );
+        manager.forget_player("p1");
+        assert!(manager.is_muted("p1"));
+        manager.set_muted("p1", false);
+        assert!(post(&mut manager, "p1", ChatChannel::Lobby, "f", 5_000, false).is_ok());
+
+        manager.set_muted("p2", true);
+        manager.clear_mutes();
+        assert!(!manager.is_muted("p2"));
+    }
+
+    #[test]
+    fn text_is_trimmed_limited_and_filtered() {
+        let mut manager = ChatManager::new(ChatManagerOptions {
+            max_length: 13,
+            banned_words: vec![" Darn ".to_string(), "".to_string()],
+            ..ChatManagerOptions::default()
+        });
+        assert_eq!(
+            post(&mut manager, "p1", ChatChannel::Lobby, "   ", 0, false).unwrap_err(),
+            "chat message is empty"
+        );
+        let message = post(
+            &mut manager,
+            "p1",
+            ChatChannel::Lobby,
+            "  DARN it, darn!!",
+            0,
+            false,
+        )
+        .expect("message is accepted");
+        assert_eq!(message.text, "**** it, ****");
+        assert_eq!(message.sender_name, "P1");
+    }
+
+    #[test]
+    fn history_keeps_latest_messages() {
+        let mut manager = ChatManager::new(ChatManagerOptions {
+            history_size: 3,
+            max_per_window: 10,
+            ..ChatManagerOptions::default()
+        });
+        for idx in 0..5 {
+            post(
+                &mut manager,
+                "p1",
+                ChatChannel::Lobby,
+                &format!("m{idx}"),
+                idx,
+                false,
+            )
+            .expect("message is accepted");
+        }
+        let texts: Vec<String> = manager
+            .history_for(false)
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(texts, vec!["m2", "m3", "m4"]);
+    }
+}
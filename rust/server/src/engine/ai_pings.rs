@@ -0,0 +1,318 @@
+use super::*;
+
+const AI_DANGER_PING_INTERVAL_MS: u64 = 4_000;
+const AI_FOCUS_PING_INTERVAL_MS: u64 = 6_000;
+const AI_BOSS_SIGHT_DISTANCE: i32 = 6;
+const AI_GHOST_CLUSTER_DISTANCE: i32 = 5;
+const AI_GHOST_CLUSTER_SIZE: usize = 3;
+const AI_DUPLICATE_PING_RADIUS: i32 = 6;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AiPingRequest {
+    pub player_id: String,
+    pub player_name: String,
+    pub x: i32,
+    pub y: i32,
+    pub kind: PingType,
+    pub target_id: Option<String>,
+}
+
+impl GameEngine {
+    // Requests generated by the last `step`; callers route them through `PingManager`.
+    pub fn take_ai_ping_requests(&mut self) -> Vec<AiPingRequest> {
+        std::mem::take(&mut self.ai_ping_requests)
+    }
+
+    pub(super) fn update_ai_pings(&mut self, now_ms: u64) {
+        self.ai_ping_requests.clear();
+
+        for idx in 0..self.players.len() {
+            if !self.players[idx].view.ai {
+                continue;
+            }
+            let (x, y) = (self.players[idx].view.x, self.players[idx].view.y);
+            if self.players[idx].view.state == PlayerState::Down {
+                let down_since = self.players[idx].view.down_since;
+                if down_since.is_some() && self.players[idx].ai_help_pinged_at != down_since {
+                    let player_id = self.players[idx].view.id.clone();
+                    // Requests can be rate-limited away, so only a placed ping counts as sent.
+                    if self.has_help_ping_for(&player_id) {
+                        self.players[idx].ai_help_pinged_at = down_since;
+                    } else {
+                        self.request_ai_ping(idx, Vec2 { x, y }, PingType::Help, Some(player_id));
+                    }
+                }
+                continue;
+            }
+
+            if now_ms >= self.ai_danger_ping_at {
+                if let Some((kind, spot, target_id)) = self.spot_danger(x, y, now_ms) {
+                    if !self.has_team_ping_near(kind, spot) {
+                        self.ai_danger_ping_at = now_ms + AI_DANGER_PING_INTERVAL_MS;
+                        self.request_ai_ping(idx, spot, kind, target_id);
+                        continue;
+                    }
+                }
+            }
+
+            let chosen = self.ai_chosen_sector(idx);
+            if chosen == self.players[idx].ai_focus_sector {
+                continue;
+            }
+            let Some(sector_id) = chosen else {
+                self.players[idx].ai_focus_sector = None;
+                continue;
+            };
+            if now_ms < self.ai_focus_ping_at || self.world.sectors[sector_id].view.captured {
+                continue;
+            }
+            self.players[idx].ai_focus_sector = chosen;
+            let (cx, cy) = self.sector_center(sector_id);
+            let Some(spot) = self.nearest_walkable_in_sector(sector_id, cx, cy) else {
+                continue;
+            };
+            if self.has_team_ping_near(PingType::Focus, spot) {
+                continue;
+            }
+            self.ai_focus_ping_at = now_ms + AI_FOCUS_PING_INTERVAL_MS;
+            self.request_ai_ping(idx, spot, PingType::Focus, None);
+        }
+    }
+
+    fn request_ai_ping(
+        &mut self,
+        player_idx: usize,
+        spot: Vec2,
+        kind: PingType,
+        target_id: Option<String>,
+    ) {
+        let view = &self.players[player_idx].view;
+        self.ai_ping_requests.push(AiPingRequest {
+            player_id: view.id.clone(),
+            player_name: view.name.clone(),
+            x: spot.x,
+            y: spot.y,
+            kind,
+            target_id,
+        });
+    }
+
+    // A boss in sight (tracked by id), or the ghost closest to the centroid of a nearby cluster.
+    fn spot_danger(&self, x: i32, y: i32, now_ms: u64) -> Option<(PingType, Vec2, Option<String>)> {
+        let nearby: Vec<&GhostView> = self
+            .ghosts
+            .iter()
+            .map(|ghost| &ghost.view)
+            .filter(|ghost| ghost.stunned_until <= now_ms)
+            .filter(|ghost| manhattan(x, y, ghost.x, ghost.y) <= AI_BOSS_SIGHT_DISTANCE)
+            .collect();
+        if let Some(boss) = nearby
+            .iter()
+            .filter(|ghost| ghost.ghost_type == GhostType::Boss)
+            .min_by_key(|ghost| manhattan(x, y, ghost.x, ghost.y))
+        {
+            return Some((
+                PingType::Boss,
+                Vec2 {
+                    x: boss.x,
+                    y: boss.y,
+                },
+                Some(boss.id.clone()),
+            ));
+        }
+
+        let cluster: Vec<&&GhostView> = nearby
+            .iter()
+            .filter(|ghost| manhattan(x, y, ghost.x, ghost.y) <= AI_GHOST_CLUSTER_DISTANCE)
+            .collect();
+        if cluster.len() < AI_GHOST_CLUSTER_SIZE {
+            return None;
+        }
+        let count = cluster.len() as i32;
+        let cx = cluster.iter().map(|ghost| ghost.x).sum::<i32>() / count;
+        let cy = cluster.iter().map(|ghost| ghost.y).sum::<i32>() / count;
+        cluster
+            .iter()
+            .min_by_key(|ghost| (manhattan(cx, cy, ghost.x, ghost.y), ghost.id.as_str()))
+            .map(|ghost| {
+                (
+                    PingType::Danger,
+                    Vec2 {
+                        x: ghost.x,
+                        y: ghost.y,
+                    },
+                    None,
+                )
+            })
+    }
+
+    fn ai_chosen_sector(&self, player_idx: usize) -> Option<usize> {
+        match self.squad_task(player_idx) {
+            Some(SquadTask::Sector(sector_id)) => Some(sector_id),
+            Some(SquadTask::Rescue(_)) => None,
+            None => self.players[player_idx]
+                .ai_dot_target
+                .and_then(|target| self.get_sector_id(target.x, target.y)),
+        }
+    }
+
+    fn has_help_ping_for(&self, player_id: &str) -> bool {
+        self.team_pings
+            .iter()
+            .any(|ping| ping.kind == PingType::Help && ping.target_id.as_deref() == Some(player_id))
+    }
+
+    fn has_team_ping_near(&self, kind: PingType, spot: Vec2) -> bool {
+        let mut known = self
+            .team_pings
+            .iter()
+            .map(|ping| (ping.kind, ping.x, ping.y))
+            .chain(
+                self.ai_ping_requests
+                    .iter()
+                    .map(|request| (request.kind, request.x, request.y)),
+            );
+        known.any(|(ping_kind, x, y)| {
+            ping_kind == kind && manhattan(x, y, spot.x, spot.y) <= AI_DUPLICATE_PING_RADIUS
+        })
+    }
+
+    fn nearest_walkable_in_sector(&self, sector_id: usize, x: i32, y: i32) -> Option<Vec2> {
+        let view = &self.world.sectors[sector_id].view;
+        let mut best: Option<(i32, Vec2)> = None;
+        for cy in view.y..view.y + view.size {
+            for cx in view.x..view.x + view.size {
+                if !is_walkable(&self.world, cx, cy) {
+                    continue;
+                }
+                let dist = manhattan(x, y, cx, cy);
+                if best.is_none_or(|(best_dist, _)| dist < best_dist) {
+                    best = Some((dist, Vec2 { x: cx, y: cy }));
+                }
+            }
+        }
+        best.map(|(_, cell)| cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::engine_without_ghosts;
+
+    fn place_ghost(engine: &mut GameEngine, ghost_type: GhostType, x: i32, y: i32) {
+        let mut ghost = engine
+            .ghosts
+            .first()
+            .cloned()
+            .unwrap_or_else(|| GhostInternal {
+                view: GhostView {
+                    id: String::new(),
+                    x,
+                    y,
+                    dir: Direction::None,
+                    ghost_type,
+                    hp: 1,
+                    stunned_until: 0,
+                },
+                move_buffer: 0.0,
+            });
+        ghost.view.id = format!("ghost_test_{}", engine.ghosts.len());
+        ghost.view.x = x;
+        ghost.view.y = y;
+        ghost.view.ghost_type = ghost_type;
+        engine.ghosts.push(ghost);
+    }
+
+    #[test]
+    fn downed_ai_requests_help_until_the_ping_is_placed() {
+        let mut engine = engine_without_ghosts(7_401, 1);
+        let now_ms = engine.current_now_ms();
+        engine.players[0].view.state = PlayerState::Down;
+        engine.players[0].view.down_since = Some(now_ms);
+
+        engine.update_ai_pings(now_ms);
+        let requests = engine.take_ai_ping_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, PingType::Help);
+        assert_eq!(requests[0].target_id.as_deref(), Some("ai_1"));
+        assert_eq!(
+            (requests[0].x, requests[0].y),
+            (engine.players[0].view.x, engine.players[0].view.y)
+        );
+
+        // Not placed yet (e.g. rate-limited): ask again.
+        engine.update_ai_pings(now_ms + 100);
+        assert_eq!(engine.take_ai_ping_requests().len(), 1);
+
+        let me = engine.players[0].view.clone();
+        engine.set_pings(&[PingView {
+            id: "ping_help".to_string(),
+            owner_id: me.id.clone(),
+            owner_name: me.name.clone(),
+            x: me.x,
+            y: me.y,
+            kind: PingType::Help,
+            target_id: Some(me.id.clone()),
+            created_at_ms: now_ms,
+            expires_at_ms: now_ms + 8_000,
+        }]);
+        engine.update_ai_pings(now_ms + 200);
+        assert!(engine.take_ai_ping_requests().is_empty());
+        engine.set_pings(&[]);
+        engine.update_ai_pings(now_ms + 300);
+        assert!(engine.take_ai_ping_requests().is_empty());
+    }
+
+    #[test]
+    fn ai_warns_about_boss_and_ghost_cluster() {
+        let mut engine = engine_without_ghosts(7_402, 1);
+        let now_ms = engine.current_now_ms();
+        engine.players[0].ai_focus_sector = engine.ai_chosen_sector(0);
+        let me = engine.players[0].view.clone();
+        place_ghost(&mut engine, GhostType::Boss, me.x + 4, me.y);
+
+        engine.update_ai_pings(now_ms);
+        let requests = engine.take_ai_ping_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, PingType::Boss);
+        assert_eq!((requests[0].x, requests[0].y), (me.x + 4, me.y));
+        assert_eq!(requests[0].target_id.as_deref(), Some("ghost_test_0"));
+
+        engine.update_ai_pings(now_ms + 100);
+        assert!(engine.take_ai_ping_requests().is_empty());
+
+        engine.ghosts.clear();
+        for offset in 1..=3 {
+            place_ghost(&mut engine, GhostType::Chaser, me.x, me.y + offset);
+        }
+        engine.update_ai_pings(now_ms + AI_DANGER_PING_INTERVAL_MS);
+        let requests = engine.take_ai_ping_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, PingType::Danger);
+        assert_eq!((requests[0].x, requests[0].y), (me.x, me.y + 2));
+    }
+
+    #[test]
+    fn ai_focus_pings_are_throttled_team_wide() {
+        let mut engine = engine_without_ghosts(7_403, 6);
+        engine.squad_planner_enabled = true;
+        let now_ms = engine.current_now_ms();
+        engine.update_squad_plan(now_ms);
+
+        engine.update_ai_pings(now_ms);
+        let requests = engine.take_ai_ping_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, PingType::Focus);
+        let sector_id = engine
+            .get_sector_id(requests[0].x, requests[0].y)
+            .expect("focus ping inside a sector");
+        assert!(!engine.world.sectors[sector_id].view.captured);
+        assert!(is_walkable(&engine.world, requests[0].x, requests[0].y));
+
+        engine.update_ai_pings(now_ms + 100);
+        assert!(engine.take_ai_ping_requests().is_empty());
+        engine.update_ai_pings(now_ms + AI_FOCUS_PING_INTERVAL_MS);
+        assert_eq!(engine.take_ai_ping_requests().len(), 1);
+    }
+}
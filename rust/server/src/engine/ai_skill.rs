@@ -0,0 +1,125 @@
+use crate::types::AiSkill;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct AiSkillProfile {
+    pub think_interval_ms: (i32, i32),
+    pub danger_distance: i32,
+    pub rescue_threat_distance: i32,
+    pub cautious_dot_distance: i32,
+    pub awaken_chance: f32,
+    pub rescue_range: i32,
+    pub path_depth_scale: f32,
+    pub wander_chance: f32,
+}
+
+impl AiSkillProfile {
+    pub fn for_skill(skill: AiSkill) -> Self {
+        match skill {
+            AiSkill::Beginner => Self {
+                think_interval_ms: (280, 460),
+                danger_distance: 1,
+                rescue_threat_distance: 1,
+                cautious_dot_distance: 2,
+                awaken_chance: 0.2,
+                rescue_range: 16,
+                path_depth_scale: 0.4,
+                wander_chance: 0.3,
+            },
+            AiSkill::Intermediate => Self {
+                think_interval_ms: (160, 280),
+                danger_distance: 4,
+                rescue_threat_distance: 2,
+                cautious_dot_distance: 7,
+                awaken_chance: 0.6,
+                rescue_range: 48,
+                path_depth_scale: 0.75,
+                wander_chance: 0.1,
+            },
+            AiSkill::Advanced => Self {
+                think_interval_ms: (90, 190),
+                danger_distance: 4,
+                rescue_threat_distance: 3,
+                cautious_dot_distance: 7,
+                awaken_chance: 1.0,
+                rescue_range: i32::MAX,
+                path_depth_scale: 1.0,
+                wander_chance: 0.0,
+            },
+            AiSkill::Expert => Self {
+                think_interval_ms: (60, 120),
+                danger_distance: 4,
+                rescue_threat_distance: 4,
+                cautious_dot_distance: 7,
+                awaken_chance: 1.0,
+                rescue_range: i32::MAX,
+                path_depth_scale: 1.25,
+                wander_chance: 0.0,
+            },
+        }
+    }
+
+    pub fn path_depth(&self, base: i32) -> i32 {
+        ((base as f32) * self.path_depth_scale).round().max(1.0) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TICK_MS;
+    use crate::engine::test_support::make_ai_players;
+    use crate::engine::{GameEngine, GameEngineOptions};
+    use crate::types::Difficulty;
+
+    #[test]
+    fn tiers_are_monotonic() {
+        let profiles: Vec<AiSkillProfile> = AiSkill::ALL
+            .iter()
+            .map(|skill| AiSkillProfile::for_skill(*skill))
+            .collect();
+        for pair in profiles.windows(2) {
+            assert!(pair[0].think_interval_ms.0 > pair[1].think_interval_ms.0);
+            assert!(pair[0].danger_distance <= pair[1].danger_distance);
+            assert!(pair[0].awaken_chance <= pair[1].awaken_chance);
+            assert!(pair[0].rescue_range <= pair[1].rescue_range);
+            assert!(pair[0].path_depth_scale < pair[1].path_depth_scale);
+            assert!(pair[0].wander_chance >= pair[1].wander_chance);
+        }
+        assert_eq!(
+            AiSkillProfile::for_skill(AiSkill::Advanced).path_depth(48),
+            48
+        );
+    }
+
+    #[test]
+    fn engine_applies_ai_skill_per_player() {
+        let players = make_ai_players(3);
+        let options = GameEngineOptions {
+            ai_skills: [
+                ("ai_1".to_string(), AiSkill::Beginner),
+                ("ai_2".to_string(), AiSkill::Expert),
+            ]
+            .into_iter()
+            .collect(),
+            ..GameEngineOptions::default()
+        };
+        let mut engine = GameEngine::new(players, Difficulty::Normal, 31, options);
+        let skills: Vec<AiSkill> = engine.players.iter().map(|p| p.ai_skill).collect();
+        assert_eq!(
+            skills,
+            vec![AiSkill::Beginner, AiSkill::Expert, AiSkill::Advanced]
+        );
+
+        engine.step(TICK_MS);
+        let now_ms = engine.current_now_ms();
+        for player in &engine.players {
+            let (min, max) = AiSkillProfile::for_skill(player.ai_skill).think_interval_ms;
+            let wait = player.ai_think_at.saturating_sub(now_ms);
+            assert!(
+                (min as u64..=max as u64).contains(&wait),
+                "{:?} waited {wait}ms",
+                player.ai_skill
+            );
+        }
+    }
+}
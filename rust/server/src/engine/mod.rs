@@ -1,26 +1,39 @@
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
 
 use crate::constants::{
-    get_capture_pressure, get_difficulty_multiplier, get_initial_ghost_count, get_time_limit_ms,
-    AWAKEN_DURATION_MS, DOTS_FOR_AWAKEN, GHOST_BASE_SPEED, MAX_AWAKEN_STOCK, PLAYER_BASE_SPEED,
-    PLAYER_CAPTURED_SPEED_MULTIPLIER, POWER_DURATION_MS, POWER_PELLET_RESPAWN_MS,
-    RESCUE_TIMEOUT_MS, TICK_RATE,
+    get_initial_ghost_count, get_time_limit_ms, AWAKEN_DURATION_MS, DOTS_FOR_AWAKEN,
+    GHOST_BASE_SPEED, MAX_AWAKEN_STOCK, PLAYER_BASE_SPEED, PLAYER_CAPTURED_SPEED_MULTIPLIER,
+    POWER_DURATION_MS, POWER_PELLET_RESPAWN_MS, RESCUE_TIMEOUT_MS, TICK_RATE,
 };
 use crate::rng::Rng;
+use crate::rules::BalanceRules;
 use crate::types::{
-    AwardEntry, AwardId, AwardWinner, Difficulty, Direction, FruitView, GameConfig, GameOverReason,
-    GameSummary, GhostType, GhostView, PlayerState, PlayerView, RuntimeEvent, ScoreEntry, Snapshot,
-    StartPlayer, TimelineEvent, Vec2,
+    AiSkill, AwardEntry, AwardId, AwardWinner, BotObservation, Difficulty, Direction, FruitView,
+    GameConfig, GameOverReason, GameSummary, GhostType, GhostView, PingTarget, PingType, PingView,
+    PlayerState, PlayerView, RuntimeEvent, ScoreEntry, Snapshot, StartPlayer, TimelineEvent, Vec2,
 };
 use crate::world::{
     generate_world, is_gate_cell_or_switch, is_walkable, key_of, to_world_init, GeneratedWorld,
 };
 
+mod ai_pings;
+mod ai_skill;
+mod observation;
+mod ping_response;
 mod sector_system;
 mod spawn_system;
+mod squad_planner;
+#[cfg(test)]
+mod test_support;
 mod utils;
 
+pub use self::ai_pings::AiPingRequest;
+use self::ai_skill::AiSkillProfile;
+pub use self::squad_planner::{SquadPlanStats, SquadPlannerMode, SQUAD_PLANNER_AUTO_MIN_PLAYERS};
+use self::squad_planner::{SquadTask, SQUAD_RESCUE_ASSIST_RANGE};
 use self::utils::{
     manhattan, now_ms, offset, pick_ghost_type, random_direction, sector_id_from_coords,
 };
@@ -109,7 +122,7 @@ fn metric_captures(entry: &ScoreEntry) -> i32 {
     entry.captures
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 struct PlayerStats {
     dots: i32,
     ghosts: i32,
@@ -117,7 +130,7 @@ struct PlayerStats {
     captures: i32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct PlayerInternal {
     view: PlayerView,
     desired_dir: Direction,
@@ -125,27 +138,40 @@ struct PlayerInternal {
     spawn: Vec2,
     reconnect_token: String,
     awaken_requested: bool,
+    pending_input_ack: Option<InputAck>,
     remote_revive_grace_until: u64,
+    ai_skill: AiSkill,
     ai_think_at: u64,
     ai_dot_target: Option<Vec2>,
     ai_last_position: Vec2,
     ai_last_progress_at: u64,
+    ai_help_pinged_at: Option<u64>,
+    ai_focus_sector: Option<usize>,
     hold_until_ms: u64,
     stats: PlayerStats,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputAck {
+    pub seq: u64,
+    pub client_time_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct GhostInternal {
     view: GhostView,
     move_buffer: f32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct GameEngineOptions {
     pub time_limit_ms_override: Option<u64>,
+    pub rules: BalanceRules,
+    pub ai_skills: HashMap<String, AiSkill>,
+    pub squad_planner: SquadPlannerMode,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameEngine {
     seed: u32,
     pub started_at_ms: u64,
@@ -153,6 +179,7 @@ pub struct GameEngine {
     pub world: GeneratedWorld,
 
     rng: Rng,
+    rules: BalanceRules,
     players: Vec<PlayerInternal>,
     ghosts: Vec<GhostInternal>,
     fruits: Vec<FruitView>,
@@ -166,10 +193,18 @@ pub struct GameEngine {
     ended: bool,
     end_reason: Option<GameOverReason>,
     tick_counter: u64,
+    snapshot_frame: u64,
     max_capture_ratio: f32,
     milestone_emitted: HashSet<i32>,
     ai_sector_dot_memory: Vec<Vec<Vec2>>,
     ai_dot_memory_updated_at: u64,
+    squad_planner_enabled: bool,
+    ai_squad_tasks: Vec<Option<SquadTask>>,
+    ai_squad_planned_at: u64,
+    team_pings: Vec<PingView>,
+    ai_ping_requests: Vec<AiPingRequest>,
+    ai_danger_ping_at: u64,
+    ai_focus_ping_at: u64,
     next_id_counter: u64,
 }
 
@@ -186,7 +221,7 @@ impl GameEngine {
         let world = generate_world(player_count, seed);
         let sector_count = world.sectors.len();
         let max_ghosts = get_initial_ghost_count(player_count);
-        let difficulty_multiplier = get_difficulty_multiplier(difficulty);
+        let difficulty_multiplier = options.rules.difficulty_multiplier(difficulty);
 
         let config = GameConfig {
             tick_rate: TICK_RATE,
@@ -223,20 +258,31 @@ impl GameEngine {
                     score: 0,
                     connected: start.connected,
                     ai: !start.connected,
+                    bot: start.bot,
                     speed_buff_until: 0,
                     power_until: 0,
                     down_since: None,
+                    last_input_seq: None,
+                    last_input_client_ms: None,
                 },
                 desired_dir: Direction::None,
                 move_buffer: 0.0,
                 spawn,
                 reconnect_token: start.reconnect_token.clone(),
                 awaken_requested: false,
+                pending_input_ack: None,
                 remote_revive_grace_until: started_at_ms + INITIAL_SPAWN_GRACE_MS,
+                ai_skill: options
+                    .ai_skills
+                    .get(&start.id)
+                    .copied()
+                    .unwrap_or_default(),
                 ai_think_at: rng.int(50, 180) as u64,
                 ai_dot_target: None,
                 ai_last_position: spawn,
                 ai_last_progress_at: started_at_ms,
+                ai_help_pinged_at: None,
+                ai_focus_sector: None,
                 hold_until_ms: 0,
                 stats: PlayerStats::default(),
             });
@@ -248,6 +294,7 @@ impl GameEngine {
             config,
             world,
             rng,
+            rules: options.rules,
             players,
             ghosts: Vec::new(),
             fruits: Vec::new(),
@@ -263,10 +310,18 @@ impl GameEngine {
             ended: false,
             end_reason: None,
             tick_counter: 0,
+            snapshot_frame: 0,
             max_capture_ratio: 0.0,
             milestone_emitted: HashSet::new(),
             ai_sector_dot_memory: vec![Vec::new(); sector_count],
             ai_dot_memory_updated_at: 0,
+            squad_planner_enabled: options.squad_planner.enabled_for(player_count),
+            ai_squad_tasks: vec![None; player_count],
+            ai_squad_planned_at: 0,
+            team_pings: Vec::new(),
+            ai_ping_requests: Vec::new(),
+            ai_danger_ping_at: 0,
+            ai_focus_ping_at: 0,
             next_id_counter: 1,
         };
         engine.update_discovered_sectors_by_players();
@@ -279,6 +334,27 @@ impl GameEngine {
         self.ended
     }
 
+    // Queues a toast for every client; it goes out with the next snapshot's events.
+    pub fn push_toast(&mut self, message: &str) {
+        self.events.push(RuntimeEvent::Toast {
+            message: message.to_string(),
+        });
+    }
+
+    // Ends the match from outside the simulation (server shutdown, operator action). No-op
+    // once the match has already ended on its own.
+    pub fn force_end(&mut self, reason: GameOverReason, label: &str) {
+        if self.ended {
+            return;
+        }
+        self.ended = true;
+        self.end_reason = Some(reason);
+        self.timeline.push(TimelineEvent {
+            at_ms: self.elapsed_ms,
+            label: label.to_string(),
+        });
+    }
+
     pub fn get_world_init(&self) -> crate::types::WorldInit {
         to_world_init(&self.world)
     }
@@ -329,6 +405,17 @@ impl GameEngine {
         }
     }
 
+    // Echoed through `PlayerView` once the next `step` has applied the input.
+    pub fn acknowledge_input(&mut self, player_id: &str, ack: InputAck) {
+        if let Some(player) = self
+            .players
+            .iter_mut()
+            .find(|player| player.view.id == player_id && !player.view.ai)
+        {
+            player.pending_input_ack = Some(ack);
+        }
+    }
+
     pub fn step(&mut self, dt_ms: u64) {
         if self.ended {
             return;
@@ -337,6 +424,12 @@ impl GameEngine {
         self.elapsed_ms = self.elapsed_ms.saturating_add(dt_ms);
         let now_ms = self.started_at_ms.saturating_add(self.elapsed_ms);
 
+        for player in &mut self.players {
+            if let Some(ack) = player.pending_input_ack.take() {
+                player.view.last_input_seq = Some(ack.seq);
+                player.view.last_input_client_ms = ack.client_time_ms;
+            }
+        }
         self.update_gates();
         self.update_power_pellets(now_ms);
         if now_ms >= self.ai_dot_memory_updated_at.saturating_add(2_000) {
@@ -352,6 +445,7 @@ impl GameEngine {
             .iter()
             .map(|ghost| (ghost.view.id.clone(), (ghost.view.x, ghost.view.y)))
             .collect();
+        self.update_squad_plan(now_ms);
         self.update_players(dt_ms, now_ms);
         self.update_discovered_sectors_by_players();
         self.update_ghosts(dt_ms, now_ms);
@@ -361,6 +455,7 @@ impl GameEngine {
             &ghost_positions_before_move,
         );
         self.update_sector_control(dt_ms, now_ms);
+        self.update_ai_pings(now_ms);
         if self.tick_counter.is_multiple_of(TICK_RATE as u64) {
             self.adjust_ghost_population(now_ms);
             self.emit_progress_milestones();
@@ -383,15 +478,16 @@ impl GameEngine {
         }
     }
 
+    // Only event-draining snapshots are broadcast frames and advance `frame`.
     pub fn build_snapshot(&mut self, include_events: bool) -> Snapshot {
-        let time_left_ms = self
-            .config
-            .time_limit_ms
-            .saturating_sub(self.elapsed_ms.min(self.config.time_limit_ms));
+        if include_events {
+            self.snapshot_frame += 1;
+        }
         let snapshot = Snapshot {
             tick: self.tick_counter,
+            frame: self.snapshot_frame,
             now_ms: self.started_at_ms + self.elapsed_ms,
-            time_left_ms,
+            time_left_ms: self.time_left_ms(),
             capture_ratio: self.capture_ratio(),
             players: self.players.iter().map(|p| p.view.clone()).collect(),
             ghosts: self.ghosts.iter().map(|g| g.view.clone()).collect(),
@@ -425,6 +521,16 @@ impl GameEngine {
         self.started_at_ms + self.elapsed_ms
     }
 
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+
+    pub fn time_left_ms(&self) -> u64 {
+        self.config
+            .time_limit_ms
+            .saturating_sub(self.elapsed_ms.min(self.config.time_limit_ms))
+    }
+
     pub fn seed(&self) -> u32 {
         self.seed
     }
@@ -439,7 +545,8 @@ impl GameEngine {
             })
     }
 
-    pub fn build_summary(&self) -> GameSummary {
+    // Current standings, highest score first.
+    pub fn score_board(&self) -> Vec<ScoreEntry> {
         let mut ranking: Vec<ScoreEntry> = self
             .players
             .iter()
@@ -451,9 +558,15 @@ impl GameEngine {
                 ghosts: player.stats.ghosts,
                 rescues: player.stats.rescues,
                 captures: player.stats.captures,
+                bot: player.view.bot,
             })
             .collect();
-        ranking.sort_by(|a, b| b.score.cmp(&a.score));
+        ranking.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        ranking
+    }
+
+    pub fn build_summary(&self) -> GameSummary {
+        let ranking = self.score_board();
         let awards = build_awards_from_ranking(&ranking);
 
         GameSummary {
@@ -576,7 +689,9 @@ impl GameEngine {
             return;
         }
 
-        self.players[player_idx].ai_think_at = now_ms + self.rng.int(90, 190) as u64;
+        let profile = AiSkillProfile::for_skill(self.players[player_idx].ai_skill);
+        let (think_min, think_max) = profile.think_interval_ms;
+        self.players[player_idx].ai_think_at = now_ms + self.rng.int(think_min, think_max) as u64;
         let player = self.players[player_idx].view.clone();
         if self.players[player_idx].ai_last_position.x != player.x
             || self.players[player_idx].ai_last_position.y != player.y
@@ -590,21 +705,20 @@ impl GameEngine {
             self.players[player_idx].ai_dot_target = None;
         }
         let nearest_ghost = self.distance_to_nearest_ghost(player.x, player.y);
-        let danger_threshold = if self.is_large_party_endgame_band() {
-            2
-        } else {
-            4
-        };
-        let rescue_threat_threshold = if self.is_large_party_endgame_band() {
-            2
-        } else {
-            3
-        };
-        let cautious_dot_threshold = if self.is_large_party_endgame_band() {
-            3
-        } else {
-            7
-        };
+        let (danger_threshold, rescue_threat_threshold, cautious_dot_threshold) =
+            if self.is_large_party_endgame_band() {
+                (
+                    profile.danger_distance.min(2),
+                    profile.rescue_threat_distance.min(2),
+                    profile.cautious_dot_distance.min(3),
+                )
+            } else {
+                (
+                    profile.danger_distance,
+                    profile.rescue_threat_distance,
+                    profile.cautious_dot_distance,
+                )
+            };
 
         if player.state == PlayerState::Power {
             self.players[player_idx].ai_dot_target = None;
@@ -614,7 +728,10 @@ impl GameEngine {
 
         if let Some(dist) = nearest_ghost {
             if dist <= danger_threshold {
-                if player.stocks > 0 && player.state != PlayerState::Power {
+                if player.stocks > 0
+                    && player.state != PlayerState::Power
+                    && self.ai_rolls(profile.awaken_chance)
+                {
                     self.players[player_idx].awaken_requested = true;
                 }
                 self.players[player_idx].desired_dir =
@@ -623,17 +740,32 @@ impl GameEngine {
             }
         }
 
-        if let Some((down_idx, _)) = self.find_rescue_target(player_idx) {
-            let down = self.players[down_idx].view.clone();
-            let rescue_threat = self.distance_to_nearest_ghost(down.x, down.y).unwrap_or(99);
-            if rescue_threat <= rescue_threat_threshold && player.stocks > 0 {
-                self.players[player_idx].awaken_requested = true;
-            }
-            self.players[player_idx].desired_dir =
-                self.choose_rescue_direction(player.x, player.y, down.x, down.y);
+        if self.ai_rolls(profile.wander_chance) {
+            self.players[player_idx].desired_dir = random_direction(&mut self.rng);
             return;
         }
 
+        if let Some((down_idx, dist)) = self.pick_ai_rescue_target(player_idx) {
+            if dist <= profile.rescue_range {
+                let down = self.players[down_idx].view.clone();
+                let rescue_threat = self.distance_to_nearest_ghost(down.x, down.y).unwrap_or(99);
+                if rescue_threat <= rescue_threat_threshold
+                    && player.stocks > 0
+                    && self.ai_rolls(profile.awaken_chance)
+                {
+                    self.players[player_idx].awaken_requested = true;
+                }
+                self.players[player_idx].desired_dir = self.choose_rescue_direction(
+                    player.x,
+                    player.y,
+                    down.x,
+                    down.y,
+                    profile.path_depth(36),
+                );
+                return;
+            }
+        }
+
         if nearest_ghost.unwrap_or(99) <= cautious_dot_threshold {
             self.players[player_idx].desired_dir =
                 self.choose_ai_dot_direction(player_idx, player.x, player.y, true);
@@ -644,6 +776,38 @@ impl GameEngine {
             self.choose_ai_dot_direction(player_idx, player.x, player.y, false);
     }
 
+    fn pick_ai_rescue_target(&self, player_idx: usize) -> Option<(usize, i32)> {
+        if let Some(target) = self.help_ping_rescue_target(player_idx) {
+            return Some(target);
+        }
+        if !self.squad_planner_enabled {
+            return self.find_rescue_target(player_idx);
+        }
+        if let Some(SquadTask::Rescue(down_idx)) = self.squad_task(player_idx) {
+            let player = &self.players[player_idx].view;
+            let down = &self.players[down_idx].view;
+            if down.state == PlayerState::Down {
+                return Some((down_idx, manhattan(player.x, player.y, down.x, down.y)));
+            }
+        }
+        let planned = self
+            .ai_squad_tasks
+            .iter()
+            .any(|task| matches!(task, Some(SquadTask::Rescue(_))));
+        self.find_rescue_target(player_idx)
+            .filter(|(_, dist)| !planned || *dist <= SQUAD_RESCUE_ASSIST_RANGE)
+    }
+
+    fn ai_rolls(&mut self, chance: f32) -> bool {
+        if chance >= 1.0 {
+            return true;
+        }
+        if chance <= 0.0 {
+            return false;
+        }
+        self.rng.bool(chance)
+    }
+
     fn get_player_speed(&self, idx: usize, now_ms: u64) -> f32 {
         let mut speed = PLAYER_BASE_SPEED;
         let Some(player) = self.players.get(idx) else {
@@ -686,7 +850,7 @@ impl GameEngine {
         if self.is_large_party_endgame_band() {
             1.5
         } else if self.is_ten_player_casual_clearability_band() {
-            1.08
+            self.rules.casual_bands.ten_player_speed_multiplier
         } else {
             1.0
         }
@@ -694,7 +858,7 @@ impl GameEngine {
 
     fn five_player_casual_player_speed_multiplier(&self) -> f32 {
         if self.is_five_player_casual_clearability_band() && self.capture_ratio() >= 0.4 {
-            1.12
+            self.rules.casual_bands.five_player_speed_multiplier
         } else {
             1.0
         }
@@ -704,9 +868,9 @@ impl GameEngine {
         if self.is_large_party_endgame_band() {
             0.05
         } else if self.is_ten_player_casual_clearability_band() {
-            0.65
+            self.rules.casual_bands.ten_player_regen_relief
         } else if self.is_five_player_casual_clearability_band() {
-            0.45
+            self.rules.casual_bands.five_player_regen_relief
         } else {
             1.0
         }
@@ -1029,11 +1193,11 @@ mod tests {
         DOTS_FOR_AWAKEN, MAX_AWAKEN_STOCK, PLAYER_BASE_SPEED, PLAYER_CAPTURED_SPEED_MULTIPLIER,
         TICK_MS,
     };
-    use crate::engine::{GameEngine, GameEngineOptions};
+    use crate::engine::{GameEngine, GameEngineOptions, InputAck};
     use crate::rng::Rng;
     use crate::types::{
-        AwardId, Difficulty, Direction, GateState, PlayerState, RuntimeEvent, ScoreEntry,
-        StartPlayer, Vec2,
+        AwardId, Difficulty, Direction, GameOverReason, GateState, PlayerState, RuntimeEvent,
+        ScoreEntry, StartPlayer, Vec2,
     };
 
     fn make_players(count: usize) -> Vec<StartPlayer> {
@@ -1043,6 +1207,7 @@ mod tests {
                 name: format!("P{}", idx + 1),
                 reconnect_token: format!("token_{}", idx + 1),
                 connected: false,
+                bot: false,
             })
             .collect()
     }
@@ -1099,6 +1264,7 @@ mod tests {
             ghosts,
             rescues,
             captures,
+            bot: false,
         }
     }
 
@@ -1185,6 +1351,7 @@ mod tests {
             424_242,
             GameEngineOptions {
                 time_limit_ms_override: Some(120_000),
+                ..GameEngineOptions::default()
             },
         );
         let mut b = GameEngine::new(
@@ -1193,6 +1360,7 @@ mod tests {
             424_242,
             GameEngineOptions {
                 time_limit_ms_override: Some(120_000),
+                ..GameEngineOptions::default()
             },
         );
 
@@ -1235,6 +1403,7 @@ mod tests {
             name: "P1".to_string(),
             reconnect_token: "token_1".to_string(),
             connected: true,
+            bot: false,
         }];
         let mut engine = GameEngine::new(
             players,
@@ -1242,6 +1411,7 @@ mod tests {
             777,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let start_x = engine.players[0].view.x;
@@ -1256,6 +1426,75 @@ mod tests {
         assert_eq!(engine.players[0].view.dir as u8, Direction::None as u8);
     }
 
+    #[test]
+    fn input_ack_is_echoed_after_step() {
+        let mut engine = GameEngine::new(
+            make_players(2),
+            Difficulty::Normal,
+            779,
+            GameEngineOptions::default(),
+        );
+        engine.set_player_connection("p1", true);
+        let ack = InputAck {
+            seq: 7,
+            client_time_ms: Some(1_234),
+        };
+        engine.acknowledge_input("p1", ack);
+        engine.acknowledge_input("p2", ack);
+        assert_eq!(engine.players[0].view.last_input_seq, None);
+
+        engine.step(TICK_MS);
+        assert_eq!(engine.players[0].view.last_input_seq, Some(7));
+        assert_eq!(engine.players[0].view.last_input_client_ms, Some(1_234));
+        assert_eq!(engine.players[1].view.last_input_seq, None);
+
+        engine.step(TICK_MS);
+        assert_eq!(engine.players[0].view.last_input_seq, Some(7));
+    }
+
+    #[test]
+    fn score_board_matches_summary_ranking() {
+        let mut engine = GameEngine::new(
+            make_players(3),
+            Difficulty::Normal,
+            782,
+            GameEngineOptions::default(),
+        );
+        engine.players[1].view.score = 40;
+        engine.players[2].view.score = 15;
+        let board = engine.score_board();
+        let ids: Vec<&str> = board.iter().map(|entry| entry.player_id.as_str()).collect();
+        assert_eq!(ids[0], engine.players[1].view.id);
+        assert_eq!(ids[1], engine.players[2].view.id);
+        let summary_ids: Vec<String> = engine
+            .build_summary()
+            .ranking
+            .into_iter()
+            .map(|entry| entry.player_id)
+            .collect();
+        assert_eq!(ids, summary_ids);
+    }
+
+    #[test]
+    fn force_end_sets_reason_once() {
+        let mut engine = GameEngine::new(
+            make_players(2),
+            Difficulty::Normal,
+            781,
+            GameEngineOptions::default(),
+        );
+        engine.step(TICK_MS);
+        engine.force_end(GameOverReason::Shutdown, "サーバー停止");
+        engine.force_end(GameOverReason::Victory, "ignored");
+        assert!(engine.is_ended());
+        let summary = engine.build_summary();
+        assert_eq!(summary.reason, GameOverReason::Shutdown);
+        assert_eq!(
+            summary.timeline.last().map(|event| event.label.as_str()),
+            Some("サーバー停止")
+        );
+    }
+
     #[test]
     fn player_stops_after_none_input() {
         let players = vec![StartPlayer {
@@ -1263,6 +1502,7 @@ mod tests {
             name: "P1".to_string(),
             reconnect_token: "token_1".to_string(),
             connected: true,
+            bot: false,
         }];
         let mut engine = GameEngine::new(
             players,
@@ -1270,6 +1510,7 @@ mod tests {
             778,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let player_id = engine.players[0].view.id.clone();
@@ -1307,6 +1548,7 @@ mod tests {
             100,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(1);
@@ -1344,6 +1586,7 @@ mod tests {
             333,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.events.push(RuntimeEvent::Toast {
@@ -1354,6 +1597,8 @@ mod tests {
         let second = engine.build_snapshot(true);
         assert_eq!(first.events.len(), 1);
         assert_eq!(second.events.len(), 0);
+        assert_eq!(second.frame, first.frame + 1);
+        assert_eq!(engine.build_snapshot(false).frame, second.frame);
     }
 
     #[test]
@@ -1364,6 +1609,7 @@ mod tests {
             334,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let snapshot = engine.build_snapshot(false);
@@ -1391,6 +1637,7 @@ mod tests {
             444,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
 
@@ -1423,6 +1670,7 @@ mod tests {
             555,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
 
@@ -1463,6 +1711,7 @@ mod tests {
             777,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.events.push(RuntimeEvent::Toast {
@@ -1486,6 +1735,7 @@ mod tests {
             888,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         for player in &mut engine.players {
@@ -1512,6 +1762,7 @@ mod tests {
             2_001,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.clear();
@@ -1537,6 +1788,7 @@ mod tests {
             2_002,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(1);
@@ -1587,6 +1839,7 @@ mod tests {
             2_004,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(1);
@@ -1622,6 +1875,7 @@ mod tests {
             2_006,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.clear();
@@ -1647,6 +1901,7 @@ mod tests {
             2_005,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(1);
@@ -1672,6 +1927,7 @@ mod tests {
             2_003,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(1);
@@ -1702,6 +1958,7 @@ mod tests {
             889,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let now_ms = engine.started_at_ms + 5_000;
@@ -1740,6 +1997,7 @@ mod tests {
             890,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let dot = engine
@@ -1767,6 +2025,7 @@ mod tests {
             8_001,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -1800,6 +2059,7 @@ mod tests {
             8_002,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -1833,6 +2093,7 @@ mod tests {
             8_003,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -1866,6 +2127,7 @@ mod tests {
             8_004,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -1902,6 +2164,7 @@ mod tests {
             891,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(!engine.ghosts.is_empty());
@@ -1940,6 +2203,7 @@ mod tests {
             8_101,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         sixty.ghosts.truncate(1);
@@ -1960,6 +2224,7 @@ mod tests {
             8_102,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         eighty.ghosts.truncate(1);
@@ -1983,6 +2248,7 @@ mod tests {
             8_103,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let sector_id = 0usize;
@@ -1998,6 +2264,7 @@ mod tests {
             8_108,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         below.world.sectors[sector_id].view.captured = false;
@@ -2016,6 +2283,7 @@ mod tests {
             8_104,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let sector_id = 0usize;
@@ -2041,6 +2309,7 @@ mod tests {
             8_105,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let before = engine.ghosts.len();
@@ -2056,6 +2325,7 @@ mod tests {
             8_106,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         below.ghosts.truncate(40);
@@ -2068,6 +2338,7 @@ mod tests {
             8_107,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         band.ghosts.truncate(40);
@@ -2083,6 +2354,7 @@ mod tests {
             8_109,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -2116,6 +2388,7 @@ mod tests {
             8_110,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -2149,6 +2422,7 @@ mod tests {
             8_111,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -2172,6 +2446,7 @@ mod tests {
             8_112,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -2198,6 +2473,7 @@ mod tests {
             8_114,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(approx_eq(
@@ -2225,6 +2501,7 @@ mod tests {
             8_113,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.ghosts.truncate(4);
@@ -2248,6 +2525,7 @@ mod tests {
             8_115,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         casual_five.world.sectors[sector_id].view.captured = false;
@@ -2269,6 +2547,7 @@ mod tests {
             8_116,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         casual_four.world.sectors[sector_id].view.captured = false;
@@ -2293,6 +2572,7 @@ mod tests {
             999,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         engine.players[0].view.state = PlayerState::Down;
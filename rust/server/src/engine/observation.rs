@@ -0,0 +1,129 @@
+use super::*;
+
+impl GameEngine {
+    pub fn build_bot_observation(&self, player_id: &str, radius: i32) -> Option<BotObservation> {
+        let me = self
+            .players
+            .iter()
+            .find(|player| player.view.id == player_id)?
+            .view
+            .clone();
+        let radius = radius.max(0);
+        let origin = Vec2 {
+            x: me.x - radius,
+            y: me.y - radius,
+        };
+        let in_window = |x: i32, y: i32| (x - me.x).abs() <= radius && (y - me.y).abs() <= radius;
+
+        let grid = (0..=radius * 2)
+            .map(|dy| {
+                (0..=radius * 2)
+                    .map(|dx| self.observation_cell(origin.x + dx, origin.y + dy))
+                    .collect()
+            })
+            .collect();
+
+        Some(BotObservation {
+            tick: self.tick_counter,
+            now_ms: self.current_now_ms(),
+            time_left_ms: self.time_left_ms(),
+            capture_ratio: self.capture_ratio(),
+            origin,
+            radius,
+            grid,
+            ghosts: self
+                .ghosts
+                .iter()
+                .filter(|ghost| in_window(ghost.view.x, ghost.view.y))
+                .map(|ghost| ghost.view.clone())
+                .collect(),
+            players: self
+                .players
+                .iter()
+                .filter(|player| player.view.id != me.id)
+                .filter(|player| in_window(player.view.x, player.view.y))
+                .map(|player| player.view.clone())
+                .collect(),
+            sectors: self.world.sectors.iter().map(|s| s.view.clone()).collect(),
+            me,
+        })
+    }
+
+    fn observation_cell(&self, x: i32, y: i32) -> char {
+        if !is_walkable(&self.world, x, y) {
+            return '#';
+        }
+        let closed_gate = self.world.gates.iter().any(|gate| {
+            !gate.open && ((gate.a.x == x && gate.a.y == y) || (gate.b.x == x && gate.b.y == y))
+        });
+        if closed_gate {
+            return 'G';
+        }
+        let switch = self.world.gates.iter().any(|gate| {
+            !gate.permanent
+                && ((gate.switch_a.x == x && gate.switch_a.y == y)
+                    || (gate.switch_b.x == x && gate.switch_b.y == y))
+        });
+        if switch {
+            return 'S';
+        }
+        if self
+            .world
+            .power_pellets
+            .get(&key_of(x, y))
+            .map(|pellet| pellet.active)
+            .unwrap_or(false)
+        {
+            return 'o';
+        }
+        if self.world.dots.contains(&(x, y)) {
+            return '.';
+        }
+        ' '
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameEngine;
+    use crate::engine::GameEngineOptions;
+    use crate::types::{Difficulty, StartPlayer};
+
+    fn make_players(count: usize) -> Vec<StartPlayer> {
+        (0..count)
+            .map(|idx| StartPlayer {
+                id: format!("p{}", idx + 1),
+                name: format!("P{}", idx + 1),
+                reconnect_token: format!("token_{}", idx + 1),
+                connected: true,
+                bot: idx == 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bot_observation_has_fixed_size_grid_centered_on_player() {
+        let engine = GameEngine::new(
+            make_players(2),
+            Difficulty::Normal,
+            9_101,
+            GameEngineOptions::default(),
+        );
+        let observation = engine
+            .build_bot_observation("p1", 4)
+            .expect("player should exist");
+
+        assert!(observation.me.bot);
+        assert_eq!(observation.grid.len(), 9);
+        assert!(observation.grid.iter().all(|row| row.chars().count() == 9));
+        assert_eq!(observation.origin.x, observation.me.x - 4);
+        assert_eq!(observation.origin.y, observation.me.y - 4);
+        let center = observation.grid[4].chars().nth(4).expect("center cell");
+        assert_ne!(center, '#');
+        assert!(observation.ghosts.iter().all(|ghost| {
+            (ghost.x - observation.me.x).abs() <= 4 && (ghost.y - observation.me.y).abs() <= 4
+        }));
+        assert_eq!(observation.sectors.len(), engine.world.sectors.len());
+        assert!(engine.build_bot_observation("missing", 4).is_none());
+    }
+}
@@ -0,0 +1,372 @@
+use super::*;
+
+const FOCUS_PING_RADIUS: i32 = 6;
+const FOCUS_PING_RESPONSE_RANGE: i32 = 48;
+const FOCUS_PING_BONUS: i32 = 150;
+const FOCUS_PING_DISTANCE_WEIGHT: i32 = 12;
+const DANGER_PING_RADIUS: i32 = 5;
+const DANGER_PING_PENALTY: i32 = 220;
+const HELP_PING_RADIUS: i32 = 3;
+const HELP_PING_RESPONSE_RANGE: i32 = 40;
+pub(super) const FOCUS_PING_SQUAD_SLOTS: i32 = 2;
+
+impl GameEngine {
+    pub fn set_pings(&mut self, pings: &[PingView]) {
+        let now_ms = self.current_now_ms();
+        self.team_pings = pings
+            .iter()
+            .filter(|ping| ping.expires_at_ms > now_ms)
+            .cloned()
+            .collect();
+    }
+
+    // Resolves where a ping lands: the sender's cell, or a map cell or entity within
+    // `max_distance` of the sender.
+    pub fn resolve_ping_target(
+        &self,
+        player_id: &str,
+        target: Option<&PingTarget>,
+        max_distance: i32,
+    ) -> Result<(Vec2, Option<String>), &'static str> {
+        let origin = self
+            .player_position(player_id)
+            .ok_or("player is not in current game")?;
+        match target {
+            None => Ok((origin, None)),
+            Some(PingTarget::Cell(cell)) => {
+                if cell.x < 0
+                    || cell.y < 0
+                    || cell.x >= self.world.width
+                    || cell.y >= self.world.height
+                {
+                    return Err("ping target is out of bounds");
+                }
+                if !is_walkable(&self.world, cell.x, cell.y)
+                    && !is_gate_cell_or_switch(&self.world.gates, cell.x, cell.y)
+                {
+                    return Err("ping target is not walkable");
+                }
+                if manhattan(origin.x, origin.y, cell.x, cell.y) > max_distance {
+                    return Err("ping target is too far");
+                }
+                Ok((*cell, None))
+            }
+            Some(PingTarget::Entity(entity_id)) => {
+                let pos = self
+                    .ping_entity_position(entity_id, origin)
+                    .ok_or("unknown ping target")?;
+                if manhattan(origin.x, origin.y, pos.x, pos.y) > max_distance {
+                    return Err("ping target is too far");
+                }
+                Ok((pos, Some(entity_id.clone())))
+            }
+        }
+    }
+
+    // Ghosts, downed players, gates and active power pellets can carry a ping. A gate ping marks
+    // the switch closest to `near`, since stepping on a switch is what the ping asks for.
+    pub fn ping_entity_position(&self, entity_id: &str, near: Vec2) -> Option<Vec2> {
+        if let Some(ghost) = self.ghosts.iter().find(|ghost| ghost.view.id == entity_id) {
+            return Some(Vec2 {
+                x: ghost.view.x,
+                y: ghost.view.y,
+            });
+        }
+        if let Some(player) = self
+            .players
+            .iter()
+            .find(|player| player.view.id == entity_id)
+        {
+            return (player.view.state == PlayerState::Down).then_some(Vec2 {
+                x: player.view.x,
+                y: player.view.y,
+            });
+        }
+        if let Some(gate) = self.world.gates.iter().find(|gate| gate.id == entity_id) {
+            let distance = |cell: Vec2| manhattan(near.x, near.y, cell.x, cell.y);
+            return Some(if distance(gate.switch_b) < distance(gate.switch_a) {
+                gate.switch_b
+            } else {
+                gate.switch_a
+            });
+        }
+        self.world
+            .power_pellets
+            .get(entity_id)
+            .filter(|pellet| pellet.active)
+            .map(|pellet| Vec2 {
+                x: pellet.x,
+                y: pellet.y,
+            })
+    }
+
+    fn pings_of(&self, kind: PingType) -> impl Iterator<Item = &PingView> {
+        self.team_pings.iter().filter(move |ping| ping.kind == kind)
+    }
+
+    pub(super) fn in_danger_ping_zone(&self, x: i32, y: i32) -> bool {
+        self.pings_of(PingType::Danger)
+            .chain(self.pings_of(PingType::Boss))
+            .any(|ping| manhattan(x, y, ping.x, ping.y) <= DANGER_PING_RADIUS)
+    }
+
+    pub(super) fn ping_dot_score_adjustment(&self, x: i32, y: i32, cell: Vec2) -> i32 {
+        let mut adjustment = 0;
+        // Offsets the travel cost to the ping so focused dots outrank nearby ones.
+        let focus_distance = self
+            .pings_of(PingType::Focus)
+            .filter(|ping| manhattan(cell.x, cell.y, ping.x, ping.y) <= FOCUS_PING_RADIUS)
+            .map(|ping| manhattan(x, y, ping.x, ping.y))
+            .filter(|dist| *dist <= FOCUS_PING_RESPONSE_RANGE)
+            .min();
+        if let Some(dist) = focus_distance {
+            adjustment -= FOCUS_PING_BONUS + dist * FOCUS_PING_DISTANCE_WEIGHT;
+        }
+        if self.in_danger_ping_zone(cell.x, cell.y) {
+            adjustment += DANGER_PING_PENALTY;
+        }
+        adjustment
+    }
+
+    pub(super) fn is_help_pinged(&self, player_idx: usize) -> bool {
+        let view = &self.players[player_idx].view;
+        view.state == PlayerState::Down
+            && self
+                .pings_of(PingType::Help)
+                .any(|ping| manhattan(view.x, view.y, ping.x, ping.y) <= HELP_PING_RADIUS)
+    }
+
+    pub(super) fn help_ping_rescue_target(&self, player_idx: usize) -> Option<(usize, i32)> {
+        let player = &self.players[player_idx].view;
+        self.players
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != player_idx && self.is_help_pinged(*idx))
+            .map(|(idx, target)| {
+                (
+                    idx,
+                    manhattan(player.x, player.y, target.view.x, target.view.y),
+                )
+            })
+            .filter(|(_, dist)| *dist <= HELP_PING_RESPONSE_RANGE)
+            .min_by_key(|(idx, dist)| (*dist, *idx))
+    }
+
+    pub(super) fn focus_ping_sectors(&self) -> Vec<usize> {
+        let mut sectors: Vec<usize> = self
+            .pings_of(PingType::Focus)
+            .filter_map(|ping| self.get_sector_id(ping.x, ping.y))
+            .filter(|sector_id| *sector_id < self.world.sectors.len())
+            .collect();
+        sectors.sort_unstable();
+        sectors.dedup();
+        sectors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::engine_without_ghosts;
+
+    fn ping(engine: &GameEngine, kind: PingType, x: i32, y: i32) -> PingView {
+        let now_ms = engine.current_now_ms();
+        PingView {
+            id: format!("ping_{x}_{y}"),
+            owner_id: "p_human".to_string(),
+            owner_name: "Human".to_string(),
+            x,
+            y,
+            kind,
+            target_id: None,
+            created_at_ms: now_ms,
+            expires_at_ms: now_ms + 8_000,
+        }
+    }
+
+    #[test]
+    fn ai_targets_dots_near_focus_ping() {
+        let mut engine = engine_without_ghosts(7_301, 1);
+        engine.refresh_ai_sector_dot_memory(engine.current_now_ms());
+        let me = engine.players[0].view.clone();
+        let focus = engine
+            .ai_sector_dot_memory
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|cell| {
+                let dist = manhattan(me.x, me.y, cell.x, cell.y);
+                (12..=FOCUS_PING_RESPONSE_RANGE).contains(&dist)
+            })
+            .max_by_key(|cell| manhattan(me.x, me.y, cell.x, cell.y))
+            .expect("remembered dot within response range");
+
+        let baseline = engine
+            .pick_ai_dot_target(0, me.x, me.y, false)
+            .expect("target without ping");
+        assert!(manhattan(baseline.x, baseline.y, focus.x, focus.y) > FOCUS_PING_RADIUS);
+
+        let pings = vec![ping(&engine, PingType::Focus, focus.x, focus.y)];
+        engine.set_pings(&pings);
+        let target = engine
+            .pick_ai_dot_target(0, me.x, me.y, false)
+            .expect("target with focus ping");
+        assert!(manhattan(target.x, target.y, focus.x, focus.y) <= FOCUS_PING_RADIUS);
+    }
+
+    #[test]
+    fn ai_avoids_dots_inside_danger_ping() {
+        let mut engine = engine_without_ghosts(7_302, 1);
+        engine.refresh_ai_sector_dot_memory(engine.current_now_ms());
+        let me = engine.players[0].view.clone();
+        let baseline = engine
+            .pick_ai_dot_target(0, me.x, me.y, false)
+            .expect("target without ping");
+
+        let pings = vec![ping(&engine, PingType::Danger, baseline.x, baseline.y)];
+        engine.set_pings(&pings);
+        let target = engine
+            .pick_ai_dot_target(0, me.x, me.y, false)
+            .expect("target with danger ping");
+        assert!(!engine.in_danger_ping_zone(target.x, target.y));
+    }
+
+    #[test]
+    fn ai_prioritizes_help_pinged_rescue() {
+        let mut engine = engine_without_ghosts(7_303, 3);
+        let now_ms = engine.current_now_ms();
+        let me = engine.players[0].view.clone();
+        for (idx, offset) in [(1, 2), (2, 8)] {
+            engine.players[idx].view.state = PlayerState::Down;
+            engine.players[idx].view.down_since = Some(now_ms);
+            engine.players[idx].view.x = me.x + offset;
+            engine.players[idx].view.y = me.y;
+        }
+        assert_eq!(engine.pick_ai_rescue_target(0).map(|(idx, _)| idx), Some(1));
+
+        let far = engine.players[2].view.clone();
+        let pings = vec![ping(&engine, PingType::Help, far.x, far.y)];
+        engine.set_pings(&pings);
+        assert_eq!(engine.pick_ai_rescue_target(0), Some((2, 8)));
+    }
+
+    #[test]
+    fn ping_targets_are_validated_against_map_and_distance() {
+        let mut engine = engine_without_ghosts(7_305, 2);
+        let me = engine.players[0].view.clone();
+        let cell = |x, y| PingTarget::Cell(Vec2 { x, y });
+
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", None, 24),
+            Ok((Vec2 { x: me.x, y: me.y }, None))
+        );
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&cell(-1, me.y)), 24),
+            Err("ping target is out of bounds")
+        );
+        let wall = (0..engine.world.width)
+            .find(|x| {
+                !is_walkable(&engine.world, *x, me.y)
+                    && !is_gate_cell_or_switch(&engine.world.gates, *x, me.y)
+            })
+            .expect("row has a wall");
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&cell(wall, me.y)), i32::MAX),
+            Err("ping target is not walkable")
+        );
+        let far = engine
+            .world
+            .dots
+            .iter()
+            .find(|(x, y)| manhattan(me.x, me.y, *x, *y) > 24)
+            .copied()
+            .expect("distant floor cell");
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&cell(far.0, far.1)), 24),
+            Err("ping target is too far")
+        );
+        assert!(engine
+            .resolve_ping_target("ai_1", Some(&cell(far.0, far.1)), i32::MAX)
+            .is_ok());
+
+        let entity = |id: &str| PingTarget::Entity(id.to_string());
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity("ai_2")), 24),
+            Err("unknown ping target")
+        );
+        engine.players[1].view.state = PlayerState::Down;
+        let other = engine.players[1].view.clone();
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity("ai_2")), 24),
+            Ok((
+                Vec2 {
+                    x: other.x,
+                    y: other.y
+                },
+                Some("ai_2".to_string())
+            ))
+        );
+        engine.players[1].view.x = me.x + 30;
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity("ai_2")), 24),
+            Err("ping target is too far")
+        );
+
+        let gate = engine.world.gates.first().cloned().expect("map has a gate");
+        assert_eq!(
+            engine.ping_entity_position(&gate.id, gate.switch_a),
+            Some(gate.switch_a)
+        );
+        assert_eq!(
+            engine.ping_entity_position(&gate.id, gate.switch_b),
+            Some(gate.switch_b)
+        );
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity(&gate.id)), i32::MAX),
+            Ok((
+                engine
+                    .ping_entity_position(&gate.id, Vec2 { x: me.x, y: me.y })
+                    .expect("gate resolves"),
+                Some(gate.id.clone())
+            ))
+        );
+
+        let pellet = engine
+            .world
+            .power_pellets
+            .values()
+            .next()
+            .cloned()
+            .expect("map has a power pellet");
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity(&pellet.key)), i32::MAX),
+            Ok((
+                Vec2 {
+                    x: pellet.x,
+                    y: pellet.y
+                },
+                Some(pellet.key.clone())
+            ))
+        );
+        if let Some(stored) = engine.world.power_pellets.get_mut(&pellet.key) {
+            stored.active = false;
+        }
+        assert_eq!(
+            engine.resolve_ping_target("ai_1", Some(&entity(&pellet.key)), i32::MAX),
+            Err("unknown ping target")
+        );
+        assert_eq!(
+            engine.resolve_ping_target("nobody", None, 24),
+            Err("player is not in current game")
+        );
+    }
+
+    #[test]
+    fn expired_pings_are_ignored() {
+        let mut engine = engine_without_ghosts(7_304, 1);
+        let mut stale = ping(&engine, PingType::Danger, 1, 1);
+        stale.expires_at_ms = engine.current_now_ms();
+        engine.set_pings(&[stale]);
+        assert!(!engine.in_danger_ping_zone(1, 1));
+    }
+}
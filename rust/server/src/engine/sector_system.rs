@@ -53,7 +53,7 @@ impl GameEngine {
 
         let capture_ratio = self.capture_ratio();
         self.max_capture_ratio = self.max_capture_ratio.max(capture_ratio);
-        let (grace_ms, regen_multiplier) = get_capture_pressure(capture_ratio);
+        let (grace_ms, regen_multiplier) = self.rules.capture_pressure(capture_ratio);
         let dt_sec = dt_ms as f32 / 1000.0;
 
         for sector_id in 0..self.world.sectors.len() {
@@ -189,7 +189,7 @@ impl GameEngine {
         }
     }
 
-    pub(super) fn capture_ratio(&self) -> f32 {
+    pub fn capture_ratio(&self) -> f32 {
         if self.world.sectors.is_empty() {
             return 0.0;
         }
@@ -210,27 +210,33 @@ impl GameEngine {
         cautious: bool,
     ) -> Direction {
         let min_ghost_distance = if cautious { 2 } else { 0 };
+        let profile = AiSkillProfile::for_skill(self.players[player_idx].ai_skill);
+        let (retarget_depth, search_depth) = (profile.path_depth(48), profile.path_depth(56));
         let mut target = self.players[player_idx].ai_dot_target;
 
         if let Some(cell) = target {
             let has_dot = self.world.dots.contains(&(cell.x, cell.y));
             let reached = cell.x == x && cell.y == y;
-            if !has_dot || reached {
+            if !has_dot || reached || self.in_danger_ping_zone(cell.x, cell.y) {
                 target = None;
             }
         }
 
         if let Some(cell) = target {
-            if let Some(dir) =
-                self.find_shortest_path_direction(x, y, 48, min_ghost_distance, |nx, ny| {
-                    nx == cell.x && ny == cell.y
-                })
-            {
+            if let Some(dir) = self.find_shortest_path_direction(
+                x,
+                y,
+                retarget_depth,
+                min_ghost_distance,
+                |nx, ny| nx == cell.x && ny == cell.y,
+            ) {
                 self.players[player_idx].ai_dot_target = Some(cell);
                 return dir;
             }
-            if let Some(dir) = self
-                .find_shortest_path_direction(x, y, 48, 0, |nx, ny| nx == cell.x && ny == cell.y)
+            if let Some(dir) =
+                self.find_shortest_path_direction(x, y, retarget_depth, 0, |nx, ny| {
+                    nx == cell.x && ny == cell.y
+                })
             {
                 self.players[player_idx].ai_dot_target = Some(cell);
                 return dir;
@@ -241,16 +247,18 @@ impl GameEngine {
         self.players[player_idx].ai_dot_target = target;
 
         if let Some(cell) = target {
-            if let Some(dir) =
-                self.find_shortest_path_direction(x, y, 56, min_ghost_distance, |nx, ny| {
-                    nx == cell.x && ny == cell.y
-                })
-            {
+            if let Some(dir) = self.find_shortest_path_direction(
+                x,
+                y,
+                search_depth,
+                min_ghost_distance,
+                |nx, ny| nx == cell.x && ny == cell.y,
+            ) {
                 return dir;
             }
-            if let Some(dir) = self
-                .find_shortest_path_direction(x, y, 56, 0, |nx, ny| nx == cell.x && ny == cell.y)
-            {
+            if let Some(dir) = self.find_shortest_path_direction(x, y, search_depth, 0, |nx, ny| {
+                nx == cell.x && ny == cell.y
+            }) {
                 return dir;
             }
             return self.choose_toward_direction(x, y, cell.x, cell.y);
@@ -263,7 +271,7 @@ impl GameEngine {
         }
     }
 
-    fn pick_ai_dot_target(
+    pub(super) fn pick_ai_dot_target(
         &self,
         player_idx: usize,
         x: i32,
@@ -294,10 +302,10 @@ impl GameEngine {
         }
 
         let player_sector = self.get_sector_id(x, y);
-        let assigned_sector = if self.world.sectors.is_empty() {
-            0
-        } else {
-            player_idx % self.world.sectors.len()
+        let (assigned_sector, span_weight) = match self.squad_task(player_idx) {
+            Some(SquadTask::Sector(sector_id)) => (sector_id, 24),
+            _ if self.world.sectors.is_empty() => (0, 10),
+            _ => (player_idx % self.world.sectors.len(), 10),
         };
         let assigned_view = self
             .world
@@ -331,7 +339,7 @@ impl GameEngine {
                 if let Some(assigned) = assigned_view {
                     let sector_span =
                         (sector.row - assigned.row).abs() + (sector.col - assigned.col).abs();
-                    score += sector_span * 10;
+                    score += sector_span * span_weight;
                 }
                 if !sector.captured {
                     score -= if capture_ratio >= 0.85 { 110 } else { 170 };
@@ -355,6 +363,7 @@ impl GameEngine {
                 if ghost_dist <= 2 {
                     score += 48;
                 }
+                score += self.ping_dot_score_adjustment(x, y, *cell);
 
                 let candidate_key = (score, dist, cell.y, cell.x);
                 let should_replace = best
@@ -424,18 +433,19 @@ impl GameEngine {
         y: i32,
         tx: i32,
         ty: i32,
+        max_depth: i32,
     ) -> Direction {
         if x == tx && y == ty {
             return Direction::None;
         }
 
         if let Some(dir) =
-            self.find_shortest_path_direction(x, y, 36, 2, |nx, ny| nx == tx && ny == ty)
+            self.find_shortest_path_direction(x, y, max_depth, 2, |nx, ny| nx == tx && ny == ty)
         {
             return dir;
         }
         if let Some(dir) =
-            self.find_shortest_path_direction(x, y, 36, 0, |nx, ny| nx == tx && ny == ty)
+            self.find_shortest_path_direction(x, y, max_depth, 0, |nx, ny| nx == tx && ny == ty)
         {
             return dir;
         }
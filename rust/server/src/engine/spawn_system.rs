@@ -184,6 +184,7 @@ mod tests {
                 name: format!("P{}", idx + 1),
                 reconnect_token: format!("token_{}", idx + 1),
                 connected: false,
+                bot: false,
             })
             .collect()
     }
@@ -203,6 +204,7 @@ mod tests {
             7_777,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         assert!(!engine.ghosts.is_empty());
@@ -246,6 +248,7 @@ mod tests {
             7_778,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
         let mut squad = GameEngine::new(
@@ -254,6 +257,7 @@ mod tests {
             7_779,
             GameEngineOptions {
                 time_limit_ms_override: Some(60_000),
+                ..GameEngineOptions::default()
             },
         );
 
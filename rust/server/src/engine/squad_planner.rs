@@ -0,0 +1,312 @@
+use super::ping_response::FOCUS_PING_SQUAD_SLOTS;
+use super::*;
+
+const SQUAD_REPLAN_MS: u64 = 1_000;
+const SQUAD_PLANNER_MIN_AI: usize = 4;
+const SQUAD_DOTS_PER_SLOT: i32 = 24;
+const SQUAD_MAX_SLOTS_PER_SECTOR: i32 = 4;
+pub(super) const SQUAD_RESCUE_ASSIST_RANGE: i32 = 5;
+pub const SQUAD_PLANNER_AUTO_MIN_PLAYERS: usize = 30;
+
+// `Auto` enables the planner only for large matches, where independent targeting piles AI into
+// the same sectors; smaller matches keep the balance the rules were tuned for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SquadPlannerMode {
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl SquadPlannerMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "on" => Some(Self::On),
+            "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    pub fn enabled_for(self, player_count: usize) -> bool {
+        match self {
+            Self::Auto => player_count >= SQUAD_PLANNER_AUTO_MIN_PLAYERS,
+            Self::On => true,
+            Self::Off => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum SquadTask {
+    Sector(usize),
+    Rescue(usize),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SquadPlanStats {
+    pub rescue: usize,
+    pub defend: usize,
+    pub capture: usize,
+    pub unassigned: usize,
+}
+
+impl GameEngine {
+    pub(super) fn update_squad_plan(&mut self, now_ms: u64) {
+        if !self.squad_planner_enabled || now_ms < self.ai_squad_planned_at {
+            return;
+        }
+        self.ai_squad_planned_at = now_ms + SQUAD_REPLAN_MS;
+        self.ai_squad_tasks = vec![None; self.players.len()];
+
+        let mut free: Vec<usize> = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.view.ai && player.view.state != PlayerState::Down)
+            .map(|(idx, _)| idx)
+            .collect();
+        if free.len() < SQUAD_PLANNER_MIN_AI {
+            return;
+        }
+
+        let mut downed: Vec<usize> = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.view.state == PlayerState::Down)
+            .map(|(idx, _)| idx)
+            .collect();
+        downed.sort_by_key(|idx| {
+            (
+                !self.is_help_pinged(*idx),
+                self.players[*idx].view.down_since.unwrap_or(0),
+                *idx,
+            )
+        });
+        for down_idx in downed {
+            let down = &self.players[down_idx].view;
+            let Some(pos) = free
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, idx)| {
+                    let view = &self.players[**idx].view;
+                    (manhattan(view.x, view.y, down.x, down.y), **idx)
+                })
+                .map(|(pos, _)| pos)
+            else {
+                break;
+            };
+            let rescuer = free.swap_remove(pos);
+            self.ai_squad_tasks[rescuer] = Some(SquadTask::Rescue(down_idx));
+        }
+
+        // (priority, sector_id, slots): focus-pinged and threatened captured sectors first,
+        // then uncaptured ones.
+        let mut demands: Vec<(u8, usize, i32)> = self
+            .focus_ping_sectors()
+            .into_iter()
+            .filter(|sector_id| self.world.sectors[*sector_id].view.dot_count > 0)
+            .map(|sector_id| (0, sector_id, FOCUS_PING_SQUAD_SLOTS))
+            .collect();
+        for (sector_id, sector) in self.world.sectors.iter().enumerate() {
+            let view = &sector.view;
+            if view.captured {
+                let loss_threshold = ((view.total_dots as f32)
+                    * self.large_party_loss_threshold_ratio())
+                .floor() as i32;
+                if view.dot_count > 0 && view.dot_count >= loss_threshold.max(1) {
+                    demands.push((0, sector_id, 1));
+                }
+            } else {
+                let capture_threshold = ((view.total_dots as f32)
+                    * self.large_party_capture_threshold_ratio())
+                .floor() as i32;
+                let remaining = view.dot_count - capture_threshold;
+                if remaining > 0 {
+                    let slots = ((remaining + SQUAD_DOTS_PER_SLOT - 1) / SQUAD_DOTS_PER_SLOT)
+                        .clamp(1, SQUAD_MAX_SLOTS_PER_SECTOR);
+                    demands.push((1, sector_id, slots));
+                }
+            }
+        }
+        if demands.is_empty() {
+            return;
+        }
+
+        let mut candidates: Vec<(u8, i32, usize, usize)> = Vec::new();
+        for (demand_idx, (priority, sector_id, _)) in demands.iter().enumerate() {
+            let (cx, cy) = self.sector_center(*sector_id);
+            for player_idx in &free {
+                let view = &self.players[*player_idx].view;
+                candidates.push((
+                    *priority,
+                    manhattan(view.x, view.y, cx, cy),
+                    *player_idx,
+                    demand_idx,
+                ));
+            }
+        }
+        candidates.sort_unstable();
+
+        let mut remaining_slots: Vec<i32> = demands.iter().map(|(_, _, slots)| *slots).collect();
+        for (_, _, player_idx, demand_idx) in candidates {
+            if remaining_slots[demand_idx] <= 0 || self.ai_squad_tasks[player_idx].is_some() {
+                continue;
+            }
+            remaining_slots[demand_idx] -= 1;
+            self.ai_squad_tasks[player_idx] = Some(SquadTask::Sector(demands[demand_idx].1));
+        }
+
+        let open_sectors: Vec<usize> = demands
+            .iter()
+            .filter(|(priority, _, _)| *priority == 1)
+            .map(|(_, sector_id, _)| *sector_id)
+            .collect();
+        for player_idx in free {
+            if self.ai_squad_tasks[player_idx].is_some() {
+                continue;
+            }
+            let view = &self.players[player_idx].view;
+            let nearest = open_sectors.iter().copied().min_by_key(|sector_id| {
+                let (cx, cy) = self.sector_center(*sector_id);
+                (manhattan(view.x, view.y, cx, cy), *sector_id)
+            });
+            self.ai_squad_tasks[player_idx] = nearest.map(SquadTask::Sector);
+        }
+    }
+
+    pub fn squad_planner_enabled(&self) -> bool {
+        self.squad_planner_enabled
+    }
+
+    pub(super) fn squad_task(&self, player_idx: usize) -> Option<SquadTask> {
+        self.ai_squad_tasks.get(player_idx).copied().flatten()
+    }
+
+    pub fn squad_plan_stats(&self) -> SquadPlanStats {
+        let mut stats = SquadPlanStats::default();
+        for (idx, player) in self.players.iter().enumerate() {
+            if !player.view.ai || player.view.state == PlayerState::Down {
+                continue;
+            }
+            match self.squad_task(idx) {
+                Some(SquadTask::Rescue(_)) => stats.rescue += 1,
+                Some(SquadTask::Sector(sector_id)) => {
+                    if self.world.sectors[sector_id].view.captured {
+                        stats.defend += 1;
+                    } else {
+                        stats.capture += 1;
+                    }
+                }
+                None => stats.unassigned += 1,
+            }
+        }
+        stats
+    }
+
+    pub(super) fn sector_center(&self, sector_id: usize) -> (i32, i32) {
+        let view = &self.world.sectors[sector_id].view;
+        (view.x + view.size / 2, view.y + view.size / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::make_ai_players;
+
+    #[test]
+    fn planner_spreads_ai_over_uncaptured_sectors() {
+        let mut engine = GameEngine::new(
+            make_ai_players(30),
+            Difficulty::Normal,
+            5_150,
+            GameEngineOptions {
+                squad_planner: SquadPlannerMode::On,
+                ..GameEngineOptions::default()
+            },
+        );
+        let now_ms = engine.current_now_ms();
+        engine.update_squad_plan(now_ms);
+
+        let mut per_sector = vec![0; engine.world.sectors.len()];
+        for idx in 0..engine.players.len() {
+            match engine.squad_task(idx) {
+                Some(SquadTask::Sector(sector_id)) => per_sector[sector_id] += 1,
+                other => panic!("expected sector task, got {other:?}"),
+            }
+        }
+        let covered = per_sector.iter().filter(|count| **count > 0).count();
+        assert!(covered >= engine.world.sectors.len().min(30) / 2);
+        assert_eq!(engine.squad_plan_stats().capture, 30);
+    }
+
+    #[test]
+    fn planner_sends_nearest_ai_to_downed_player_and_defends_threatened_sector() {
+        let mut engine = GameEngine::new(
+            make_ai_players(6),
+            Difficulty::Normal,
+            5_151,
+            GameEngineOptions {
+                squad_planner: SquadPlannerMode::On,
+                ..GameEngineOptions::default()
+            },
+        );
+        let now_ms = engine.current_now_ms();
+        engine.players[0].view.state = PlayerState::Down;
+        engine.players[0].view.down_since = Some(now_ms);
+        let down = (engine.players[0].view.x, engine.players[0].view.y);
+        engine.world.sectors[0].view.captured = true;
+        engine.world.sectors[0].view.dot_count = engine.world.sectors[0].view.total_dots;
+        engine.update_squad_plan(now_ms);
+
+        let rescuers: Vec<usize> = (0..engine.players.len())
+            .filter(|idx| engine.squad_task(*idx) == Some(SquadTask::Rescue(0)))
+            .collect();
+        assert_eq!(rescuers.len(), 1);
+        let nearest = (1..engine.players.len())
+            .map(|idx| {
+                manhattan(
+                    engine.players[idx].view.x,
+                    engine.players[idx].view.y,
+                    down.0,
+                    down.1,
+                )
+            })
+            .min()
+            .expect("other players exist");
+        let rescuer = &engine.players[rescuers[0]].view;
+        assert_eq!(manhattan(rescuer.x, rescuer.y, down.0, down.1), nearest);
+        assert!((0..engine.players.len())
+            .any(|idx| engine.squad_task(idx) == Some(SquadTask::Sector(0))));
+        assert_eq!(engine.squad_task(0), None);
+    }
+
+    #[test]
+    fn auto_mode_enables_planner_only_for_large_matches() {
+        let engine_with = |players: usize, squad_planner: SquadPlannerMode| {
+            GameEngine::new(
+                make_ai_players(players),
+                Difficulty::Normal,
+                5_152,
+                GameEngineOptions {
+                    squad_planner,
+                    ..GameEngineOptions::default()
+                },
+            )
+        };
+
+        let mut small = engine_with(10, SquadPlannerMode::Auto);
+        let now_ms = small.current_now_ms();
+        small.update_squad_plan(now_ms);
+        assert!(!small.squad_planner_enabled());
+        assert_eq!(small.squad_plan_stats().unassigned, 10);
+
+        let large = engine_with(SQUAD_PLANNER_AUTO_MIN_PLAYERS, SquadPlannerMode::Auto);
+        assert!(large.squad_planner_enabled());
+        assert!(engine_with(10, SquadPlannerMode::On).squad_planner_enabled());
+        assert!(!engine_with(40, SquadPlannerMode::Off).squad_planner_enabled());
+    }
+}
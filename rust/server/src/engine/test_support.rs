@@ -0,0 +1,25 @@
+use super::{GameEngine, GameEngineOptions};
+use crate::types::{Difficulty, StartPlayer};
+
+pub(super) fn make_ai_players(count: usize) -> Vec<StartPlayer> {
+    (0..count)
+        .map(|idx| StartPlayer {
+            id: format!("ai_{}", idx + 1),
+            name: format!("AI-{:02}", idx + 1),
+            reconnect_token: format!("token_{}", idx + 1),
+            connected: false,
+            bot: false,
+        })
+        .collect()
+}
+
+pub(super) fn engine_without_ghosts(seed: u32, players: usize) -> GameEngine {
+    let mut engine = GameEngine::new(
+        make_ai_players(players),
+        Difficulty::Normal,
+        seed,
+        GameEngineOptions::default(),
+    );
+    engine.ghosts.clear();
+    engine
+}
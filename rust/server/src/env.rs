@@ -0,0 +1,352 @@
+use crate::constants::TICK_MS;
+use crate::engine::{GameEngine, GameEngineOptions};
+use crate::rules::BalanceRules;
+use crate::types::{
+    AiSkill, BotObservation, Difficulty, Direction, GameSummary, PlayerState, RuntimeEvent,
+    StartPlayer,
+};
+
+pub const OBSERVATION_CHANNELS: usize = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RewardConfig {
+    pub dot: f32,
+    pub rescue: f32,
+    pub down: f32,
+    pub capture: f32,
+    pub sector_lost: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            dot: 0.05,
+            rescue: 1.0,
+            down: -1.0,
+            capture: 2.0,
+            sector_lost: -1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub agents: usize,
+    pub ai_players: usize,
+    pub ai_skill: AiSkill,
+    pub difficulty: Difficulty,
+    pub time_limit_ms: Option<u64>,
+    pub observation_radius: i32,
+    pub ticks_per_step: u32,
+    pub rewards: RewardConfig,
+    pub rules: BalanceRules,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            agents: 1,
+            ai_players: 0,
+            ai_skill: AiSkill::default(),
+            difficulty: Difficulty::Normal,
+            time_limit_ms: None,
+            observation_radius: 7,
+            ticks_per_step: 1,
+            rewards: RewardConfig::default(),
+            rules: BalanceRules::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnvAction {
+    pub dir: Option<Direction>,
+    pub awaken: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepResult {
+    pub observations: Vec<BotObservation>,
+    pub rewards: Vec<f32>,
+    pub done: bool,
+}
+
+// Headless wrapper around `GameEngine` with a reset/step interface for agent training.
+// Agents are addressed by index; `agent_ids()[i]` is the engine player id of agent `i`.
+pub struct GameEnv {
+    config: EnvConfig,
+    engine: GameEngine,
+    agent_ids: Vec<String>,
+}
+
+impl GameEnv {
+    pub fn new(seed: u32, config: EnvConfig) -> Self {
+        let (engine, agent_ids) = build_engine(seed, &config);
+        Self {
+            config,
+            engine,
+            agent_ids,
+        }
+    }
+
+    pub fn reset(&mut self, seed: u32, config: EnvConfig) -> Vec<BotObservation> {
+        let (engine, agent_ids) = build_engine(seed, &config);
+        self.config = config;
+        self.engine = engine;
+        self.agent_ids = agent_ids;
+        self.observe()
+    }
+
+    pub fn step(&mut self, actions: &[EnvAction]) -> StepResult {
+        let mut rewards = vec![0.0; self.agent_ids.len()];
+        if !self.engine.is_ended() {
+            for (agent_id, action) in self.agent_ids.iter().zip(actions) {
+                self.engine
+                    .receive_input(agent_id, action.dir, Some(action.awaken));
+            }
+            for _ in 0..self.config.ticks_per_step.max(1) {
+                self.engine.step(TICK_MS);
+                let events = self.engine.build_snapshot(true).events;
+                self.accumulate_rewards(&events, &mut rewards);
+                if self.engine.is_ended() {
+                    break;
+                }
+            }
+        }
+
+        StepResult {
+            observations: self.observe(),
+            rewards,
+            done: self.engine.is_ended(),
+        }
+    }
+
+    pub fn observe(&self) -> Vec<BotObservation> {
+        self.agent_ids
+            .iter()
+            .filter_map(|agent_id| {
+                self.engine
+                    .build_bot_observation(agent_id, self.config.observation_radius)
+            })
+            .collect()
+    }
+
+    pub fn agent_ids(&self) -> &[String] {
+        &self.agent_ids
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    pub fn summary(&self) -> Option<GameSummary> {
+        self.engine.is_ended().then(|| self.engine.build_summary())
+    }
+
+    fn accumulate_rewards(&self, events: &[RuntimeEvent], rewards: &mut [f32]) {
+        let shaping = self.config.rewards;
+        for event in events {
+            match event {
+                RuntimeEvent::DotEaten { by, .. } => {
+                    self.reward_agent(rewards, by, shaping.dot);
+                }
+                RuntimeEvent::PlayerRevived { by, auto, .. } if !auto => {
+                    self.reward_agent(rewards, by, shaping.rescue);
+                }
+                RuntimeEvent::PlayerDown { player_id } => {
+                    self.reward_agent(rewards, player_id, shaping.down);
+                }
+                RuntimeEvent::SectorCaptured { .. } => {
+                    rewards
+                        .iter_mut()
+                        .for_each(|reward| *reward += shaping.capture);
+                }
+                RuntimeEvent::SectorLost { .. } => {
+                    rewards
+                        .iter_mut()
+                        .for_each(|reward| *reward += shaping.sector_lost);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reward_agent(&self, rewards: &mut [f32], player_id: &str, value: f32) {
+        if let Some(idx) = self.agent_ids.iter().position(|id| id == player_id) {
+            rewards[idx] += value;
+        }
+    }
+}
+
+fn build_engine(seed: u32, config: &EnvConfig) -> (GameEngine, Vec<String>) {
+    let mut start_players = Vec::with_capacity(config.agents + config.ai_players);
+    for idx in 0..config.agents {
+        start_players.push(StartPlayer {
+            id: format!("agent_{}", idx + 1),
+            name: format!("Agent-{:02}", idx + 1),
+            reconnect_token: format!("agent_token_{}", idx + 1),
+            connected: true,
+            bot: true,
+        });
+    }
+    for idx in 0..config.ai_players {
+        start_players.push(StartPlayer {
+            id: format!("ai_{}", idx + 1),
+            name: format!("AI-{:02}", idx + 1),
+            reconnect_token: format!("ai_token_{}", idx + 1),
+            connected: false,
+            bot: false,
+        });
+    }
+
+    let ai_skills = start_players
+        .iter()
+        .skip(config.agents)
+        .map(|player| (player.id.clone(), config.ai_skill))
+        .collect();
+    let agent_ids = start_players
+        .iter()
+        .take(config.agents)
+        .map(|player| player.id.clone())
+        .collect();
+    let engine = GameEngine::new(
+        start_players,
+        config.difficulty,
+        seed,
+        GameEngineOptions {
+            time_limit_ms_override: config.time_limit_ms,
+            rules: config.rules.clone(),
+            ai_skills,
+            ..GameEngineOptions::default()
+        },
+    );
+    (engine, agent_ids)
+}
+
+// Channels: wall, dot, power pellet, gate/switch, ghost, active ally, downed ally.
+// Layout is channel-major: `[channel][row][col]`, `side = 2 * radius + 1`.
+pub fn observation_tensor(observation: &BotObservation) -> Vec<f32> {
+    let side = (observation.radius * 2 + 1) as usize;
+    let plane = side * side;
+    let mut tensor = vec![0.0; OBSERVATION_CHANNELS * plane];
+
+    for (row, line) in observation.grid.iter().enumerate() {
+        for (col, cell) in line.chars().enumerate() {
+            let channel = match cell {
+                '#' => 0,
+                '.' => 1,
+                'o' => 2,
+                'G' | 'S' => 3,
+                _ => continue,
+            };
+            tensor[channel * plane + row * side + col] = 1.0;
+        }
+    }
+
+    let mut mark = |channel: usize, x: i32, y: i32| {
+        let col = x - observation.origin.x;
+        let row = y - observation.origin.y;
+        if (0..side as i32).contains(&col) && (0..side as i32).contains(&row) {
+            tensor[channel * plane + row as usize * side + col as usize] = 1.0;
+        }
+    };
+    for ghost in &observation.ghosts {
+        mark(4, ghost.x, ghost.y);
+    }
+    for player in &observation.players {
+        let channel = if player.state == PlayerState::Down {
+            6
+        } else {
+            5
+        };
+        mark(channel, player.x, player.y);
+    }
+
+    tensor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn short_config() -> EnvConfig {
+        EnvConfig {
+            agents: 2,
+            ai_players: 2,
+            time_limit_ms: Some(30_000),
+            observation_radius: 5,
+            ticks_per_step: 10,
+            ..EnvConfig::default()
+        }
+    }
+
+    fn scripted_actions(step: usize) -> Vec<EnvAction> {
+        let dirs = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ];
+        (0..2)
+            .map(|idx| EnvAction {
+                dir: Some(dirs[(step / 3 + idx) % dirs.len()]),
+                awaken: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reset_and_step_run_an_episode_to_completion() {
+        let mut env = GameEnv::new(1, EnvConfig::default());
+        let observations = env.reset(4_242, short_config());
+        assert_eq!(observations.len(), 2);
+        assert_eq!(env.agent_ids(), ["agent_1", "agent_2"]);
+        assert!(observations.iter().all(|obs| obs.grid.len() == 11));
+
+        let mut total_reward = 0.0;
+        let mut steps = 0;
+        loop {
+            let result = env.step(&scripted_actions(steps));
+            assert_eq!(result.rewards.len(), 2);
+            assert_eq!(result.observations.len(), 2);
+            total_reward += result.rewards.iter().sum::<f32>();
+            steps += 1;
+            if result.done {
+                break;
+            }
+            assert!(steps < 1_000, "episode should end within the time limit");
+        }
+        assert!(total_reward != 0.0);
+        assert!(env.summary().is_some());
+        assert!(env
+            .step(&scripted_actions(0))
+            .rewards
+            .iter()
+            .all(|r| *r == 0.0));
+    }
+
+    #[test]
+    fn same_seed_and_actions_yield_same_rewards() {
+        let run = || {
+            let mut env = GameEnv::new(77, short_config());
+            (0..40)
+                .map(|step| env.step(&scripted_actions(step)).rewards)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn observation_tensor_has_fixed_shape() {
+        let env = GameEnv::new(9, short_config());
+        let observation = &env.observe()[0];
+        let tensor = observation_tensor(observation);
+        assert_eq!(tensor.len(), OBSERVATION_CHANNELS * 11 * 11);
+        assert!(tensor.iter().all(|value| *value == 0.0 || *value == 1.0));
+        assert!(tensor.contains(&1.0));
+    }
+}
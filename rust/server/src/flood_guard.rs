@@ -0,0 +1,288 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+pub const RATE_LIMITED_CLOSE_CODE: u16 = 4008;
+pub const MESSAGE_TOO_BIG_CLOSE_CODE: u16 = 1009;
+pub const INVALID_MESSAGE_TYPE: &str = "invalid";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenBucketLimit {
+    pub burst: f64,
+    pub per_second: f64,
+}
+
+impl TokenBucketLimit {
+    pub const fn new(burst: f64, per_second: f64) -> Self {
+        Self { burst, per_second }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FloodGuardOptions {
+    pub max_frame_bytes: usize,
+    pub default_limit: TokenBucketLimit,
+    pub limits: BTreeMap<String, TokenBucketLimit>,
+    pub drop_strikes: u32,
+    pub error_strikes: u32,
+    pub strike_reset_ms: u64,
+}
+
+impl Default for FloodGuardOptions {
+    fn default() -> Self {
+        let limits = [
+            ("hello", TokenBucketLimit::new(5.0, 0.5)),
+            ("input", TokenBucketLimit::new(60.0, 40.0)),
+            ("place_ping", TokenBucketLimit::new(5.0, 1.0)),
+            ("chat", TokenBucketLimit::new(10.0, 2.0)),
+            ("ping", TokenBucketLimit::new(10.0, 5.0)),
+            (INVALID_MESSAGE_TYPE, TokenBucketLimit::new(5.0, 1.0)),
+        ]
+        .into_iter()
+        .map(|(message_type, limit)| (message_type.to_string(), limit))
+        .collect();
+        Self {
+            max_frame_bytes: 16 * 1024,
+            default_limit: TokenBucketLimit::new(20.0, 5.0),
+            limits,
+            drop_strikes: 5,
+            error_strikes: 20,
+            strike_reset_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FloodVerdict {
+    Allow,
+    Drop,
+    Error(String),
+    Close { code: u16, reason: &'static str },
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FloodStats {
+    #[serde(rename = "droppedMessages")]
+    pub dropped_messages: u64,
+    #[serde(rename = "errorResponses")]
+    pub error_responses: u64,
+    #[serde(rename = "closedConnections")]
+    pub closed_connections: u64,
+    #[serde(rename = "oversizedFrames")]
+    pub oversized_frames: u64,
+    #[serde(rename = "limitedByType")]
+    pub limited_by_type: BTreeMap<String, u64>,
+}
+
+// Shared by every connection; operators read it through `snapshot`.
+#[derive(Debug, Default)]
+pub struct FloodCounters {
+    stats: Mutex<FloodStats>,
+}
+
+impl FloodCounters {
+    pub fn snapshot(&self) -> FloodStats {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    fn record(&self, message_type: Option<&str>, verdict: &FloodVerdict) {
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        match verdict {
+            FloodVerdict::Allow => return,
+            FloodVerdict::Drop => stats.dropped_messages += 1,
+            FloodVerdict::Error(_) => stats.error_responses += 1,
+            FloodVerdict::Close { .. } => stats.closed_connections += 1,
+        }
+        match message_type {
+            Some(message_type) => {
+                *stats
+                    .limited_by_type
+                    .entry(message_type.to_string())
+                    .or_default() += 1;
+            }
+            None => stats.oversized_frames += 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at_ms: u64,
+}
+
+// Per-connection limiter. Violations become strikes: the first few are dropped silently,
+// then answered with an error, and finally the connection is closed.
+pub struct FloodGuard {
+    options: FloodGuardOptions,
+    counters: Arc<FloodCounters>,
+    buckets: HashMap<String, Bucket>,
+    strikes: u32,
+    last_strike_at_ms: u64,
+}
+
+impl FloodGuard {
+    pub fn new(options: FloodGuardOptions, counters: Arc<FloodCounters>) -> Self {
+        Self {
+            options,
+            counters,
+            buckets: HashMap::new(),
+            strikes: 0,
+            last_strike_at_ms: 0,
+        }
+    }
+
+    // The size cap itself is enforced by the WebSocket layer (`max_frame_bytes` is passed to the
+    // upgrade); this only counts the rejection and says how to close.
+    pub fn reject_oversized_frame(&mut self) -> FloodVerdict {
+        let verdict = FloodVerdict::Close {
+            code: MESSAGE_TOO_BIG_CLOSE_CODE,
+            reason: "message too large",
+        };
+        self.counters.record(None, &verdict);
+        verdict
+    }
+
+    pub fn check_message(&mut self, message_type: &str, now_ms: u64) -> FloodVerdict {
+        let limit = self
+            .options
+            .limits
+            .get(message_type)
+            .copied()
+            .unwrap_or(self.options.default_limit);
+        let bucket = self
+            .buckets
+            .entry(message_type.to_string())
+            .or_insert(Bucket {
+                tokens: limit.burst,
+                updated_at_ms: now_ms,
+            });
+        let elapsed_ms = now_ms.saturating_sub(bucket.updated_at_ms);
+        bucket.tokens =
+            (bucket.tokens + elapsed_ms as f64 * limit.per_second / 1_000.0).min(limit.burst);
+        bucket.updated_at_ms = now_ms;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return FloodVerdict::Allow;
+        }
+
+        if now_ms.saturating_sub(self.last_strike_at_ms) > self.options.strike_reset_ms {
+            self.strikes = 0;
+        }
+        self.strikes += 1;
+        self.last_strike_at_ms = now_ms;
+        let verdict = if self.strikes <= self.options.drop_strikes {
+            FloodVerdict::Drop
+        } else if self.strikes <= self.options.error_strikes {
+            FloodVerdict::Error(format!("rate limit exceeded: {message_type}"))
+        } else {
+            FloodVerdict::Close {
+                code: RATE_LIMITED_CLOSE_CODE,
+                reason: "message rate limit exceeded",
+            }
+        };
+        self.counters.record(Some(message_type), &verdict);
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(options: FloodGuardOptions) -> (FloodGuard, Arc<FloodCounters>) {
+        let counters = Arc::new(FloodCounters::default());
+        (FloodGuard::new(options, counters.clone()), counters)
+    }
+
+    #[test]
+    fn bucket_allows_burst_then_refills() {
+        let mut options = FloodGuardOptions::default();
+        options
+            .limits
+            .insert("chat".to_string(), TokenBucketLimit::new(2.0, 1.0));
+        let (mut guard, _) = guard(options);
+        assert_eq!(guard.check_message("chat", 0), FloodVerdict::Allow);
+        assert_eq!(guard.check_message("chat", 0), FloodVerdict::Allow);
+        assert_eq!(guard.check_message("chat", 500), FloodVerdict::Drop);
+        assert_eq!(guard.check_message("chat", 1_000), FloodVerdict::Allow);
+        // Other types have their own bucket.
+        assert_eq!(guard.check_message("ready", 1_000), FloodVerdict::Allow);
+    }
+
+    #[test]
+    fn violations_escalate_from_drop_to_error_to_close() {
+        let options = FloodGuardOptions {
+            default_limit: TokenBucketLimit::new(1.0, 0.0),
+            drop_strikes: 1,
+            error_strikes: 2,
+            ..FloodGuardOptions::default()
+        };
+        let (mut guard, counters) = guard(options);
+        assert_eq!(guard.check_message("kick", 0), FloodVerdict::Allow);
+        assert_eq!(guard.check_message("kick", 1), FloodVerdict::Drop);
+        assert_eq!(
+            guard.check_message("kick", 2),
+            FloodVerdict::Error("rate limit exceeded: kick".to_string())
+        );
+        assert_eq!(
+            guard.check_message("kick", 3),
+            FloodVerdict::Close {
+                code: RATE_LIMITED_CLOSE_CODE,
+                reason: "message rate limit exceeded",
+            }
+        );
+
+        let stats = counters.snapshot();
+        assert_eq!(
+            (
+                stats.dropped_messages,
+                stats.error_responses,
+                stats.closed_connections
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(stats.limited_by_type.get("kick"), Some(&3));
+    }
+
+    #[test]
+    fn strikes_reset_after_quiet_period() {
+        let options = FloodGuardOptions {
+            default_limit: TokenBucketLimit::new(1.0, 0.0),
+            drop_strikes: 1,
+            strike_reset_ms: 1_000,
+            ..FloodGuardOptions::default()
+        };
+        let (mut guard, _) = guard(options);
+        guard.check_message("kick", 0);
+        assert_eq!(guard.check_message("kick", 0), FloodVerdict::Drop);
+        assert!(matches!(
+            guard.check_message("kick", 500),
+            FloodVerdict::Error(_)
+        ));
+        assert_eq!(guard.check_message("kick", 1_501), FloodVerdict::Drop);
+    }
+
+    #[test]
+    fn oversized_frames_close_the_connection() {
+        let (mut guard, counters) = guard(FloodGuardOptions::default());
+        assert_eq!(
+            guard.reject_oversized_frame(),
+            FloodVerdict::Close {
+                code: MESSAGE_TOO_BIG_CLOSE_CODE,
+                reason: "message too large",
+            }
+        );
+        let stats = counters.snapshot();
+        assert_eq!((stats.oversized_frames, stats.closed_connections), (1, 1));
+        assert!(stats.limited_by_type.is_empty());
+    }
+}
@@ -1,9 +1,20 @@
+pub mod chat_manager;
 pub mod constants;
 pub mod engine;
+pub mod env;
+pub mod flood_guard;
+pub mod match_checkpoint;
+pub mod matchmaking;
+pub mod metrics;
 pub mod ping_manager;
 pub mod ranking_store;
 pub mod rng;
+pub mod rules;
+pub mod server_config;
+pub mod server_log;
 pub mod server_protocol;
 pub mod server_utils;
+pub mod tick_clock;
 pub mod types;
+pub mod wire_codec;
 pub mod world;
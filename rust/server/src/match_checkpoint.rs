@@ -0,0 +1,191 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::GameEngine;
+use crate::server_utils::write_file_atomic;
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckpointOptions {
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub interval_ms: u64,
+    // Older checkpoints are discarded on boot instead of resuming a match nobody waits for.
+    pub max_age_ms: u64,
+}
+
+impl Default for CheckpointOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PathBuf::from(".data/match_checkpoint.json"),
+            interval_ms: 5_000,
+            max_age_ms: 600_000,
+        }
+    }
+}
+
+// A running match and whatever the server needs to let its players back in. `lobby` belongs to
+// the server binary and is opaque here.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchCheckpoint<L> {
+    pub version: u32,
+    pub saved_at_ms: u64,
+    pub match_id: String,
+    pub game: GameEngine,
+    pub lobby: L,
+}
+
+impl<L: Serialize + DeserializeOwned> MatchCheckpoint<L> {
+    pub fn new(match_id: String, game: GameEngine, lobby: L, saved_at_ms: u64) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            saved_at_ms,
+            match_id,
+            game,
+            lobby,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_json::to_vec(self)
+            .map_err(|error| format!("failed to serialize checkpoint: {error}"))?;
+        write_file_atomic(path, &bytes)
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))
+    }
+
+    // `Ok(None)` when there is no checkpoint to restore.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("failed to read {}: {error}", path.display())),
+        };
+        let version = serde_json::from_slice::<CheckpointHeader>(&bytes)
+            .map_err(|error| format!("failed to parse {}: {error}", path.display()))?
+            .version;
+        if version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "unsupported checkpoint version {version} (expected {CHECKPOINT_VERSION})"
+            ));
+        }
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|error| format!("failed to parse {}: {error}", path.display()))
+    }
+
+    pub fn age_ms(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_sub(self.saved_at_ms)
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckpointHeader {
+    version: u32,
+}
+
+pub fn remove_checkpoint(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(format!("failed to remove {}: {error}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TICK_MS;
+    use crate::engine::GameEngineOptions;
+    use crate::types::{Difficulty, StartPlayer};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "mmo-packman-checkpoint-{}-{name}.json",
+            std::process::id()
+        ))
+    }
+
+    fn make_engine() -> GameEngine {
+        let players = (0..4)
+            .map(|idx| StartPlayer {
+                id: format!("p{}", idx + 1),
+                name: format!("P{}", idx + 1),
+                reconnect_token: format!("token_{}", idx + 1),
+                connected: idx == 0,
+                bot: false,
+            })
+            .collect();
+        GameEngine::new(
+            players,
+            Difficulty::Normal,
+            4_242,
+            GameEngineOptions::default(),
+        )
+    }
+
+    #[test]
+    fn restored_engine_continues_identically() {
+        let path = temp_path("resume");
+        let mut engine = make_engine();
+        for _ in 0..60 {
+            engine.step(TICK_MS);
+        }
+        MatchCheckpoint::new(
+            "match_1".to_string(),
+            engine.clone(),
+            vec!["p1".to_string()],
+            1_000,
+        )
+        .save(&path)
+        .expect("checkpoint should save");
+
+        let restored = MatchCheckpoint::<Vec<String>>::load(&path)
+            .expect("checkpoint should load")
+            .expect("checkpoint should exist");
+        let _ = remove_checkpoint(&path);
+        assert_eq!(restored.match_id, "match_1");
+        assert_eq!(restored.lobby, vec!["p1".to_string()]);
+        assert_eq!(restored.age_ms(4_000), 3_000);
+        assert_eq!(
+            restored.game.get_reconnect_token("p2").as_deref(),
+            Some("token_2")
+        );
+
+        let mut resumed = restored.game;
+        for _ in 0..120 {
+            engine.step(TICK_MS);
+            resumed.step(TICK_MS);
+        }
+        // Hash sets serialize in per-instance order, so compare what clients see instead.
+        assert_eq!(
+            serde_json::to_value(engine.build_snapshot(true)).expect("snapshot serializes"),
+            serde_json::to_value(resumed.build_snapshot(true)).expect("snapshot serializes"),
+        );
+        assert_eq!(
+            serde_json::to_value(engine.build_summary()).expect("summary serializes"),
+            serde_json::to_value(resumed.build_summary()).expect("summary serializes"),
+        );
+    }
+
+    #[test]
+    fn missing_or_foreign_checkpoints_are_not_restored() {
+        let path = temp_path("missing");
+        let _ = remove_checkpoint(&path);
+        assert!(matches!(MatchCheckpoint::<()>::load(&path), Ok(None)));
+
+        fs::write(&path, r#"{"version": 99}"#).expect("temp file should be writable");
+        let result = MatchCheckpoint::<()>::load(&path);
+        assert!(remove_checkpoint(&path).is_ok());
+        assert!(result
+            .expect_err("version mismatch should fail")
+            .contains("unsupported checkpoint version 99"));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Difficulty;
+
+const RECENT_MATCH_SAMPLES: usize = 8;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatchmakingOptions {
+    pub wait_threshold_ms: u64,
+    pub default_party_size: usize,
+    pub max_party_size: usize,
+    pub default_match_duration_ms: u64,
+}
+
+impl Default for MatchmakingOptions {
+    fn default() -> Self {
+        Self {
+            wait_threshold_ms: 30_000,
+            default_party_size: 4,
+            max_party_size: 16,
+            default_match_duration_ms: 5 * 60_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueuePreferences {
+    pub difficulty: Option<Difficulty>,
+    pub party_size: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueueEntry {
+    pub player_id: String,
+    pub preferences: QueuePreferences,
+    pub joined_at_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormedMatch {
+    pub player_ids: Vec<String>,
+    // The queue entries taken for this match, in queue order, so they can be put back.
+    pub entries: Vec<QueueEntry>,
+    pub difficulty: Difficulty,
+    pub party_size: usize,
+    pub ai_fill: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueStatus {
+    pub position: usize,
+    pub queue_size: usize,
+    pub waited_ms: u64,
+    pub eta_ms: u64,
+}
+
+pub struct MatchmakingQueue {
+    options: MatchmakingOptions,
+    entries: Vec<QueueEntry>,
+    recent_match_durations: VecDeque<u64>,
+}
+
+impl MatchmakingQueue {
+    pub fn new(options: MatchmakingOptions) -> Self {
+        Self {
+            options,
+            entries: Vec::new(),
+            recent_match_durations: VecDeque::new(),
+        }
+    }
+
+    // Re-joining keeps the original place in line but updates the preferences.
+    pub fn enqueue(&mut self, player_id: &str, mut preferences: QueuePreferences, now_ms: u64) {
+        preferences.party_size = preferences
+            .party_size
+            .map(|size| size.clamp(1, self.options.max_party_size));
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.player_id == player_id)
+        {
+            entry.preferences = preferences;
+            return;
+        }
+        self.entries.push(QueueEntry {
+            player_id: player_id.to_string(),
+            preferences,
+            joined_at_ms: now_ms,
+        });
+    }
+
+    // Puts entries from a match that failed to start back at the head of the line.
+    pub fn requeue_front(&mut self, entries: Vec<QueueEntry>) {
+        let entries: Vec<QueueEntry> = entries
+            .into_iter()
+            .filter(|entry| !self.contains(&entry.player_id))
+            .collect();
+        self.entries.splice(0..0, entries);
+    }
+
+    pub fn leave(&mut self, player_id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.player_id != player_id);
+        self.entries.len() != before
+    }
+
+    pub fn contains(&self, player_id: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.player_id == player_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[QueueEntry] {
+        &self.entries
+    }
+
+    pub fn record_match_duration(&mut self, duration_ms: u64) {
+        self.recent_match_durations.push_back(duration_ms);
+        while self.recent_match_durations.len() > RECENT_MATCH_SAMPLES {
+            self.recent_match_durations.pop_front();
+        }
+    }
+
+    // `busy_for_ms` is how long the room stays occupied by the match in progress.
+    pub fn status(&self, player_id: &str, now_ms: u64, busy_for_ms: u64) -> Option<QueueStatus> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.player_id == player_id)?;
+        let entry = &self.entries[index];
+        let waited_ms = now_ms.saturating_sub(entry.joined_at_ms);
+        let party_size = entry
+            .preferences
+            .party_size
+            .unwrap_or(self.options.default_party_size);
+        let matches_ahead = (index / party_size.max(1)) as u64;
+        let oldest_waited = now_ms.saturating_sub(self.entries[0].joined_at_ms);
+        let fill_wait = self.options.wait_threshold_ms.saturating_sub(oldest_waited);
+        let eta_ms = if matches_ahead == 0 {
+            busy_for_ms.max(fill_wait)
+        } else {
+            busy_for_ms + matches_ahead * self.average_match_duration_ms()
+        };
+        Some(QueueStatus {
+            position: index + 1,
+            queue_size: self.entries.len(),
+            waited_ms,
+            eta_ms,
+        })
+    }
+
+    // Picks the oldest entry that can start: either its party is full, or it has waited past
+    // the threshold and the remaining slots are filled with AI.
+    pub fn try_form_match(&mut self, now_ms: u64) -> Option<FormedMatch> {
+        for anchor in 0..self.entries.len() {
+            let (picked, preferences) = self.collect_party(anchor);
+            let party_size = preferences
+                .party_size
+                .unwrap_or(self.options.default_party_size);
+            let waited_ms = now_ms.saturating_sub(self.entries[anchor].joined_at_ms);
+            if picked.len() < party_size && waited_ms < self.options.wait_threshold_ms {
+                continue;
+            }
+
+            let entries: Vec<QueueEntry> = picked
+                .iter()
+                .map(|idx| self.entries[*idx].clone())
+                .collect();
+            let player_ids: Vec<String> = entries
+                .iter()
+                .map(|entry| entry.player_id.clone())
+                .collect();
+            self.entries
+                .retain(|entry| !player_ids.contains(&entry.player_id));
+            return Some(FormedMatch {
+                ai_fill: party_size.saturating_sub(player_ids.len()),
+                player_ids,
+                entries,
+                difficulty: preferences.difficulty.unwrap_or(Difficulty::Normal),
+                party_size,
+            });
+        }
+        None
+    }
+
+    fn collect_party(&self, anchor: usize) -> (Vec<usize>, QueuePreferences) {
+        let mut preferences = self.entries[anchor].preferences;
+        let mut picked = vec![anchor];
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let limit = preferences
+                .party_size
+                .unwrap_or(self.options.default_party_size);
+            if picked.len() >= limit {
+                break;
+            }
+            if idx == anchor || !compatible(preferences, entry.preferences) {
+                continue;
+            }
+            if entry
+                .preferences
+                .party_size
+                .is_some_and(|size| picked.len() >= size)
+            {
+                continue;
+            }
+            preferences.difficulty = preferences.difficulty.or(entry.preferences.difficulty);
+            preferences.party_size = preferences.party_size.or(entry.preferences.party_size);
+            picked.push(idx);
+        }
+        picked.sort_unstable();
+        (picked, preferences)
+    }
+
+    fn average_match_duration_ms(&self) -> u64 {
+        if self.recent_match_durations.is_empty() {
+            return self.options.default_match_duration_ms;
+        }
+        self.recent_match_durations.iter().sum::<u64>() / self.recent_match_durations.len() as u64
+    }
+}
+
+fn compatible(a: QueuePreferences, b: QueuePreferences) -> bool {
+    let difficulty_ok = match (a.difficulty, b.difficulty) {
+        (Some(x), Some(y)) => x == y,
+        _ => true,
+    };
+    let size_ok = match (a.party_size, b.party_size) {
+        (Some(x), Some(y)) => x == y,
+        _ => true,
+    };
+    difficulty_ok && size_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefs(difficulty: Option<Difficulty>, party_size: Option<usize>) -> QueuePreferences {
+        QueuePreferences {
+            difficulty,
+            party_size,
+        }
+    }
+
+    #[test]
+    fn full_party_starts_immediately_with_matching_preferences() {
+        let mut queue = MatchmakingQueue::new(MatchmakingOptions::default());
+        queue.enqueue("p1", prefs(Some(Difficulty::Hard), Some(2)), 0);
+        queue.enqueue("p2", prefs(Some(Difficulty::Casual), Some(2)), 10);
+        queue.enqueue("p3", prefs(None, None), 20);
+        let formed = queue.try_form_match(30).expect("party of two is full");
+        assert_eq!(formed.player_ids, vec!["p1", "p3"]);
+        assert_eq!(formed.difficulty, Difficulty::Hard);
+        assert_eq!(formed.ai_fill, 0);
+        assert_eq!(queue.entries().len(), 1);
+        assert!(queue.contains("p2"));
+    }
+
+    #[test]
+    fn partial_party_waits_for_threshold_then_fills_with_ai() {
+        let options = MatchmakingOptions {
+            wait_threshold_ms: 1_000,
+            ..MatchmakingOptions::default()
+        };
+        let mut queue = MatchmakingQueue::new(options);
+        queue.enqueue("p1", prefs(None, Some(4)), 0);
+        queue.enqueue("p2", prefs(Some(Difficulty::Nightmare), None), 100);
+        assert_eq!(queue.try_form_match(999), None);
+
+        let formed = queue.try_form_match(1_000).expect("threshold reached");
+        assert_eq!(formed.player_ids, vec!["p1", "p2"]);
+        assert_eq!(formed.difficulty, Difficulty::Nightmare);
+        assert_eq!(formed.party_size, 4);
+        assert_eq!(formed.ai_fill, 2);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn requeued_entries_go_back_to_the_front() {
+        let mut queue = MatchmakingQueue::new(MatchmakingOptions::default());
+        queue.enqueue("p1", prefs(None, Some(1)), 0);
+        let formed = queue.try_form_match(10).expect("solo party is full");
+        queue.enqueue("p2", prefs(None, None), 20);
+
+        queue.requeue_front(formed.entries);
+        let order: Vec<&str> = queue
+            .entries()
+            .iter()
+            .map(|entry| entry.player_id.as_str())
+            .collect();
+        assert_eq!(order, vec!["p1", "p2"]);
+        assert_eq!(queue.entries()[0].joined_at_ms, 0);
+        assert_eq!(queue.entries()[0].preferences.party_size, Some(1));
+    }
+
+    #[test]
+    fn rejoin_keeps_place_and_leave_removes_entry() {
+        let mut queue = MatchmakingQueue::new(MatchmakingOptions::default());
+        queue.enqueue("p1", prefs(None, None), 0);
+        queue.enqueue("p2", prefs(None, None), 10);
+        queue.enqueue("p1", prefs(Some(Difficulty::Hard), Some(99)), 20);
+        assert_eq!(queue.entries()[0].player_id, "p1");
+        assert_eq!(queue.entries()[0].preferences.party_size, Some(16));
+        assert!(queue.leave("p1"));
+        assert!(!queue.leave("p1"));
+        assert_eq!(queue.status("p2", 20, 0).map(|s| s.position), Some(1));
+    }
+
+    #[test]
+    fn status_reports_position_and_eta() {
+        let options = MatchmakingOptions {
+            wait_threshold_ms: 10_000,
+            default_party_size: 2,
+            default_match_duration_ms: 60_000,
+            ..MatchmakingOptions::default()
+        };
+        let mut queue = MatchmakingQueue::new(options);
+        for (idx, id) in ["p1", "p2", "p3"].iter().enumerate() {
+            queue.enqueue(id, prefs(None, None), idx as u64 * 1_000);
+        }
+
+        let first = queue.status("p1", 4_000, 0).expect("queued");
+        assert_eq!((first.position, first.queue_size), (1, 3));
+        assert_eq!(first.waited_ms, 4_000);
+        assert_eq!(first.eta_ms, 6_000);
+        assert_eq!(
+            queue.status("p2", 4_000, 30_000).map(|s| s.eta_ms),
+            Some(30_000)
+        );
+
+        queue.record_match_duration(120_000);
+        let third = queue.status("p3", 4_000, 30_000).expect("queued");
+        assert_eq!(third.position, 3);
+        assert_eq!(third.eta_ms, 150_000);
+        assert_eq!(queue.status("nobody", 0, 0), None);
+    }
+}
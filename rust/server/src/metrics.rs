@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::types::GameOverReason;
+
+pub const TICK_DURATION_BUCKETS_SECONDS: [f64; 9] =
+    [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5];
+
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    // Cumulative counts are produced at render time; each slot here holds one bucket only.
+    counts: [u64; TICK_DURATION_BUCKETS_SECONDS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let slot = TICK_DURATION_BUCKETS_SECONDS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(TICK_DURATION_BUCKETS_SECONDS.len());
+        self.counts[slot] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Counters {
+    tick_duration: Histogram,
+    snapshot_bytes: BTreeMap<&'static str, u64>,
+    snapshot_last_bytes: BTreeMap<&'static str, u64>,
+    dropped_sends: u64,
+    full_queue_disconnects: u64,
+    shutdown_send_failures: u64,
+    invalid_messages: u64,
+    pings_placed: u64,
+    pings_rejected: BTreeMap<&'static str, u64>,
+    matches_ended: BTreeMap<&'static str, u64>,
+}
+
+// Point-in-time values read from the server state when `/metrics` is scraped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsGauges {
+    pub clients: usize,
+    pub spectators: usize,
+    pub running_games: usize,
+    pub ai_fill_players: usize,
+    pub ai_substitute_players: usize,
+}
+
+// Process-wide counters rendered in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+pub struct ServerMetrics {
+    counters: Mutex<Counters>,
+}
+
+impl ServerMetrics {
+    pub fn observe_tick_duration(&self, seconds: f64) {
+        self.update(|counters| counters.tick_duration.observe(seconds));
+    }
+
+    // `format` is the wire format of the frame: `json` or `binary`.
+    pub fn record_snapshot(&self, format: &'static str, frame_bytes: usize, recipients: usize) {
+        self.update(|counters| {
+            *counters.snapshot_bytes.entry(format).or_default() +=
+                (frame_bytes * recipients) as u64;
+            counters
+                .snapshot_last_bytes
+                .insert(format, frame_bytes as u64);
+        });
+    }
+
+    pub fn record_dropped_send(&self) {
+        self.update(|counters| counters.dropped_sends += 1);
+    }
+
+    pub fn record_full_queue_disconnect(&self) {
+        self.update(|counters| counters.full_queue_disconnects += 1);
+    }
+
+    pub fn record_shutdown_send_failure(&self) {
+        self.update(|counters| counters.shutdown_send_failures += 1);
+    }
+
+    pub fn record_invalid_message(&self) {
+        self.update(|counters| counters.invalid_messages += 1);
+    }
+
+    pub fn record_ping(&self, rejected: Option<&'static str>) {
+        self.update(|counters| match rejected {
+            Some(reason) => *counters.pings_rejected.entry(reason).or_default() += 1,
+            None => counters.pings_placed += 1,
+        });
+    }
+
+    pub fn record_match_ended(&self, reason: GameOverReason) {
+        let label = match reason {
+            GameOverReason::Victory => "victory",
+            GameOverReason::Timeout => "timeout",
+            GameOverReason::AllDown => "all_down",
+            GameOverReason::Collapse => "collapse",
+            GameOverReason::Shutdown => "shutdown",
+        };
+        self.update(|counters| *counters.matches_ended.entry(label).or_default() += 1);
+    }
+
+    pub fn render(&self, gauges: &MetricsGauges) -> String {
+        let counters = self
+            .counters
+            .lock()
+            .map(|counters| counters.clone())
+            .unwrap_or_default();
+        let mut out = String::new();
+
+        gauge(
+            &mut out,
+            "connected_clients",
+            "Open WebSocket connections.",
+            gauges.clients,
+        );
+        gauge(
+            &mut out,
+            "spectators",
+            "Connected lobby members watching.",
+            gauges.spectators,
+        );
+        gauge(
+            &mut out,
+            "running_games",
+            "Matches in progress.",
+            gauges.running_games,
+        );
+        header(
+            &mut out,
+            "ai_players",
+            "AI-controlled players in the running match.",
+            "gauge",
+        );
+        let _ = writeln!(
+            out,
+            "mmo_packman_ai_players{{kind=\"fill\"}} {}",
+            gauges.ai_fill_players
+        );
+        let _ = writeln!(
+            out,
+            "mmo_packman_ai_players{{kind=\"substitute\"}} {}",
+            gauges.ai_substitute_players
+        );
+
+        header(
+            &mut out,
+            "tick_duration_seconds",
+            "Wall time of one tick loop iteration.",
+            "histogram",
+        );
+        let histogram = &counters.tick_duration;
+        let mut cumulative = 0;
+        for (bound, count) in TICK_DURATION_BUCKETS_SECONDS.iter().zip(&histogram.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "mmo_packman_tick_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "mmo_packman_tick_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "mmo_packman_tick_duration_seconds_sum {}",
+            histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "mmo_packman_tick_duration_seconds_count {}",
+            histogram.count
+        );
+
+        labeled(
+            &mut out,
+            "snapshot_bytes_total",
+            "State frame bytes queued to clients.",
+            "counter",
+            "format",
+            &counters.snapshot_bytes,
+        );
+        labeled(
+            &mut out,
+            "snapshot_last_bytes",
+            "Size of the most recent state frame.",
+            "gauge",
+            "format",
+            &counters.snapshot_last_bytes,
+        );
+        counter(
+            &mut out,
+            "dropped_sends_total",
+            "DropOnFull messages dropped because the client queue was full.",
+            counters.dropped_sends,
+        );
+        counter(
+            &mut out,
+            "full_queue_disconnects_total",
+            "Clients disconnected because a DisconnectOnFull send found the queue full.",
+            counters.full_queue_disconnects,
+        );
+        counter(
+            &mut out,
+            "shutdown_send_failures_total",
+            "Shutdown notices or close frames that could not be written to the socket.",
+            counters.shutdown_send_failures,
+        );
+        counter(
+            &mut out,
+            "invalid_messages_total",
+            "Client messages that could not be parsed.",
+            counters.invalid_messages,
+        );
+        counter(
+            &mut out,
+            "pings_placed_total",
+            "Pings placed by players and AI.",
+            counters.pings_placed,
+        );
+        labeled(
+            &mut out,
+            "pings_rejected_total",
+            "Pings rejected, by reason.",
+            "counter",
+            "reason",
+            &counters.pings_rejected,
+        );
+        labeled(
+            &mut out,
+            "matches_ended_total",
+            "Finished matches, by game over reason.",
+            "counter",
+            "reason",
+            &counters.matches_ended,
+        );
+        out
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut Counters)) {
+        if let Ok(mut counters) = self.counters.lock() {
+            apply(&mut counters);
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP mmo_packman_{name} {help}");
+    let _ = writeln!(out, "# TYPE mmo_packman_{name} {kind}");
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "mmo_packman_{name} {value}");
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "mmo_packman_{name} {value}");
+}
+
+fn labeled<K: AsRef<str>>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    label: &str,
+    values: &BTreeMap<K, u64>,
+) {
+    header(out, name, help, kind);
+    for (key, value) in values {
+        let _ = writeln!(
+            out,
+            "mmo_packman_{name}{{{label}=\"{}\"}} {value}",
+            key.as_ref()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_histogram_is_cumulative() {
+        let metrics = ServerMetrics::default();
+        metrics.observe_tick_duration(0.004);
+        metrics.observe_tick_duration(0.02);
+        metrics.observe_tick_duration(0.7);
+        let text = metrics.render(&MetricsGauges::default());
+        assert!(text.contains("mmo_packman_tick_duration_seconds_bucket{le=\"0.0025\"} 0\n"));
+        assert!(text.contains("mmo_packman_tick_duration_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("mmo_packman_tick_duration_seconds_bucket{le=\"0.025\"} 2\n"));
+        assert!(text.contains("mmo_packman_tick_duration_seconds_bucket{le=\"0.5\"} 2\n"));
+        assert!(text.contains("mmo_packman_tick_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("mmo_packman_tick_duration_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn counters_and_gauges_render_with_labels() {
+        let metrics = ServerMetrics::default();
+        metrics.record_snapshot("json", 1_000, 3);
+        metrics.record_snapshot("json", 400, 2);
+        metrics.record_dropped_send();
+        metrics.record_shutdown_send_failure();
+        metrics.record_ping(None);
+        metrics.record_ping(Some("rate_limited"));
+        metrics.record_ping(Some("rate_limited"));
+        metrics.record_match_ended(GameOverReason::AllDown);
+        let text = metrics.render(&MetricsGauges {
+            clients: 5,
+            ai_fill_players: 2,
+            ..MetricsGauges::default()
+        });
+
+        for line in [
+            "# TYPE mmo_packman_connected_clients gauge",
+            "mmo_packman_connected_clients 5",
+            "mmo_packman_ai_players{kind=\"fill\"} 2",
+            "mmo_packman_snapshot_bytes_total{format=\"json\"} 3800",
+            "mmo_packman_snapshot_last_bytes{format=\"json\"} 400",
+            "mmo_packman_dropped_sends_total 1",
+            "mmo_packman_shutdown_send_failures_total 1",
+            "mmo_packman_pings_placed_total 1",
+            "mmo_packman_pings_rejected_total{reason=\"rate_limited\"} 2",
+            "mmo_packman_matches_ended_total{reason=\"all_down\"} 1",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing `{line}`");
+        }
+    }
+}
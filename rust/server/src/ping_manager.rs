@@ -1,17 +1,26 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::types::{PingType, PingView};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{PingType, PingView, Vec2};
 
 static NEXT_PING_ID: AtomicU64 = AtomicU64::new(1);
 
-#[derive(Clone, Copy, Debug)]
+// Machine-readable rejection codes, used as metric labels.
+pub const PING_REJECTED_SPECTATOR: &str = "spectator";
+pub const PING_REJECTED_RATE_LIMITED: &str = "rate_limited";
+pub const PING_REJECTED_INVALID_TARGET: &str = "invalid_target";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingManagerOptions {
     pub ttl_ms: u64,
     pub max_active_pings: usize,
     pub max_per_player: usize,
     pub rate_window_ms: u64,
     pub max_per_window: usize,
+    pub max_target_distance: i32,
 }
 
 impl Default for PingManagerOptions {
@@ -22,6 +31,7 @@ impl Default for PingManagerOptions {
             max_per_player: 4,
             rate_window_ms: 4_000,
             max_per_window: 3,
+            max_target_distance: 24,
         }
     }
 }
@@ -33,6 +43,7 @@ pub struct PlacePingInput {
     pub x: i32,
     pub y: i32,
     pub kind: PingType,
+    pub target_id: Option<String>,
     pub now_ms: u64,
     pub spectator: bool,
 }
@@ -40,6 +51,7 @@ pub struct PlacePingInput {
 #[derive(Clone, Debug)]
 pub struct PlacePingResult {
     pub ok: bool,
+    pub code: Option<&'static str>,
     pub reason: Option<String>,
 }
 
@@ -47,13 +59,15 @@ impl PlacePingResult {
     fn ok() -> Self {
         Self {
             ok: true,
+            code: None,
             reason: None,
         }
     }
 
-    fn err(reason: &str) -> Self {
+    fn err(code: &'static str, reason: &str) -> Self {
         Self {
             ok: false,
+            code: Some(code),
             reason: Some(reason.to_string()),
         }
     }
@@ -83,7 +97,7 @@ impl PingManager {
         self.prune(input.now_ms);
 
         if input.spectator {
-            return PlacePingResult::err("spectator cannot place ping");
+            return PlacePingResult::err(PING_REJECTED_SPECTATOR, "spectator cannot place ping");
         }
 
         let history = self
@@ -92,7 +106,7 @@ impl PingManager {
             .or_default();
         history.retain(|at| input.now_ms.saturating_sub(*at) <= self.options.rate_window_ms);
         if history.len() >= self.options.max_per_window {
-            return PlacePingResult::err("ping rate limit exceeded");
+            return PlacePingResult::err(PING_REJECTED_RATE_LIMITED, "ping rate limit exceeded");
         }
         history.push(input.now_ms);
 
@@ -108,12 +122,39 @@ impl PingManager {
             x: input.x,
             y: input.y,
             kind: input.kind,
+            target_id: input.target_id,
             created_at_ms: input.now_ms,
             expires_at_ms: input.now_ms + self.options.ttl_ms,
         });
         PlacePingResult::ok()
     }
 
+    pub fn options(&self) -> &PingManagerOptions {
+        &self.options
+    }
+
+    // Moves entity pings onto their target; pings whose target is gone are dropped.
+    // `position_of` gets the target id and the ping's current cell.
+    pub fn follow_targets(&mut self, position_of: impl Fn(&str, Vec2) -> Option<Vec2>) {
+        self.pings.retain_mut(|ping| {
+            let Some(target_id) = ping.target_id.as_deref() else {
+                return true;
+            };
+            let Some(pos) = position_of(
+                target_id,
+                Vec2 {
+                    x: ping.x,
+                    y: ping.y,
+                },
+            ) else {
+                return false;
+            };
+            ping.x = pos.x;
+            ping.y = pos.y;
+            true
+        });
+    }
+
     pub fn snapshot(&mut self, now_ms: u64) -> Vec<PingView> {
         self.prune(now_ms);
         self.pings.clone()
@@ -170,6 +211,7 @@ mod tests {
             x: pos.0,
             y: pos.1,
             kind,
+            target_id: None,
             now_ms,
             spectator,
         })
@@ -361,4 +403,34 @@ mod tests {
         let owners: Vec<String> = pings.iter().map(|ping| ping.owner_id.clone()).collect();
         assert_eq!(owners, vec!["p2", "p3", "p4"]);
     }
+
+    #[test]
+    fn entity_pings_follow_target_and_drop_when_gone() {
+        let mut manager = PingManager::new(PingManagerOptions::default());
+        assert!(
+            manager
+                .place(PlacePingInput {
+                    owner_id: "p1".to_string(),
+                    owner_name: "Alice".to_string(),
+                    x: 3,
+                    y: 3,
+                    kind: PingType::Boss,
+                    target_id: Some("ghost_1".to_string()),
+                    now_ms: 0,
+                    spectator: false,
+                })
+                .ok
+        );
+        assert!(place(&mut manager, "p2", "Bob", PingType::Focus, 0, false, (1, 1)).ok);
+
+        manager.follow_targets(|id, _| (id == "ghost_1").then_some(Vec2 { x: 7, y: 2 }));
+        let pings = manager.snapshot(100);
+        assert_eq!(pings.len(), 2);
+        assert_eq!((pings[0].x, pings[0].y), (7, 2));
+
+        manager.follow_targets(|_, _| None);
+        let pings = manager.snapshot(200);
+        assert_eq!(pings.len(), 1);
+        assert_eq!(pings[0].owner_id, "p2");
+    }
 }
@@ -6,7 +6,10 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::{SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+use crate::server_log::{self, LogIds};
+use crate::server_utils::write_file_atomic;
 use crate::types::{
     GameOverReason, GameSummary, PersistentRankingEntry, RankingResponse, ScoreEntry,
 };
@@ -30,26 +33,36 @@ struct StoredRankingEntry {
 struct RankingStoreFile {
     version: u8,
     players: HashMap<String, StoredRankingEntry>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    bots: HashMap<String, StoredRankingEntry>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 struct RankingStoreFileRaw {
     version: u8,
     players: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    bots: HashMap<String, serde_json::Value>,
 }
 
 pub struct RankingStore {
     file_path: PathBuf,
     players: HashMap<String, StoredRankingEntry>,
+    bots: HashMap<String, StoredRankingEntry>,
 }
 
 impl RankingStore {
     pub fn new(file_path: PathBuf) -> Self {
-        let players = load_players(&file_path);
-        Self { file_path, players }
+        let (players, bots) = load_entries(&file_path);
+        Self {
+            file_path,
+            players,
+            bots,
+        }
     }
 
-    pub fn record_match(&mut self, summary: &GameSummary) {
+    // `ids` tags the save log line; the server passes the id of the match being recorded.
+    pub fn record_match(&mut self, summary: &GameSummary, ids: LogIds) {
         let won = summary.reason == GameOverReason::Victory;
         let now_ms = now_ms();
 
@@ -62,18 +75,20 @@ impl RankingStore {
             if key.is_empty() {
                 continue;
             }
-            let current = self
-                .players
-                .entry(key)
-                .or_insert_with(|| StoredRankingEntry {
-                    name: entry.name.trim().to_string(),
-                    matches: 0,
-                    wins: 0,
-                    total_capture_ratio: 0.0,
-                    total_rescues: 0.0,
-                    best_score: 0,
-                    updated_at_ms: now_ms,
-                });
+            let entries = if entry.bot {
+                &mut self.bots
+            } else {
+                &mut self.players
+            };
+            let current = entries.entry(key).or_insert_with(|| StoredRankingEntry {
+                name: entry.name.trim().to_string(),
+                matches: 0,
+                wins: 0,
+                total_capture_ratio: 0.0,
+                total_rescues: 0.0,
+                best_score: 0,
+                updated_at_ms: now_ms,
+            });
 
             current.name = entry.name.trim().to_string();
             current.matches += 1;
@@ -86,132 +101,189 @@ impl RankingStore {
             current.updated_at_ms = now_ms;
         }
 
-        self.save();
+        self.save(ids);
     }
 
     pub fn build_response(&self, requested_limit: Option<usize>) -> RankingResponse {
         RankingResponse {
             generated_at_iso: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
-            entries: self.get_top(requested_limit),
+            entries: get_top(&self.players, requested_limit),
         }
     }
 
-    fn get_top(&self, requested_limit: Option<usize>) -> Vec<PersistentRankingEntry> {
-        let normalized_limit = requested_limit.unwrap_or(10).clamp(1, 100);
-        let mut entries: Vec<PersistentRankingEntry> = self
-            .players
-            .values()
-            .map(|entry| {
-                let matches = entry.matches as f64;
-                let (win_rate, avg_capture_ratio, avg_rescues) = if entry.matches > 0 {
-                    (
-                        entry.wins as f64 / matches,
-                        entry.total_capture_ratio / matches,
-                        entry.total_rescues / matches,
-                    )
-                } else {
-                    (0.0, 0.0, 0.0)
-                };
-                PersistentRankingEntry {
-                    name: entry.name.clone(),
-                    matches: entry.matches,
-                    wins: entry.wins.min(entry.matches),
-                    win_rate,
-                    avg_capture_ratio,
-                    avg_rescues,
-                    best_score: entry.best_score,
-                    updated_at_ms: entry.updated_at_ms,
-                }
-            })
-            .collect();
-
-        entries.sort_by(|a, b| {
-            cmp_desc_f64(a.win_rate, b.win_rate)
-                .then_with(|| cmp_desc_f64(a.avg_capture_ratio, b.avg_capture_ratio))
-                .then_with(|| cmp_desc_f64(a.avg_rescues, b.avg_rescues))
-                .then_with(|| b.best_score.cmp(&a.best_score))
-                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-        });
-        entries.truncate(normalized_limit);
-        entries
+    pub fn build_bot_response(&self, requested_limit: Option<usize>) -> RankingResponse {
+        RankingResponse {
+            generated_at_iso: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            entries: get_top(&self.bots, requested_limit),
+        }
     }
 
-    fn save(&self) {
-        if let Some(parent) = self.file_path.parent() {
-            if let Err(error) = fs::create_dir_all(parent) {
-                eprintln!(
-                    "[ranking-store] failed to create parent dir {}: {error}",
-                    parent.display()
-                );
-                return;
-            }
+    // Clears the stats but keeps the entry (and its display name). Returns false if unknown.
+    pub fn reset_player(&mut self, name: &str, bot: bool) -> bool {
+        let entries = if bot {
+            &mut self.bots
+        } else {
+            &mut self.players
+        };
+        let Some(entry) = entries.get_mut(&ranking_key(name)) else {
+            return false;
+        };
+        entry.matches = 0;
+        entry.wins = 0;
+        entry.total_capture_ratio = 0.0;
+        entry.total_rescues = 0.0;
+        entry.best_score = 0;
+        entry.updated_at_ms = now_ms();
+        self.save(LogIds::default());
+        true
+    }
+
+    pub fn delete_player(&mut self, name: &str, bot: bool) -> bool {
+        let entries = if bot {
+            &mut self.bots
+        } else {
+            &mut self.players
+        };
+        let removed = entries.remove(&ranking_key(name)).is_some();
+        if removed {
+            self.save(LogIds::default());
         }
+        removed
+    }
 
+    pub fn flush(&self) -> Result<(), String> {
         let payload = RankingStoreFile {
             version: 1,
             players: self.players.clone(),
+            bots: self.bots.clone(),
         };
-        match serde_json::to_string_pretty(&payload) {
-            Ok(text) => {
-                if let Err(error) = fs::write(&self.file_path, text) {
-                    eprintln!(
-                        "[ranking-store] failed to write {}: {error}",
-                        self.file_path.display()
-                    );
-                }
-            }
-            Err(error) => {
-                eprintln!(
-                    "[ranking-store] failed to serialize payload for {}: {error}",
-                    self.file_path.display()
-                );
-            }
+        let text = serde_json::to_string_pretty(&payload)
+            .map_err(|error| format!("failed to serialize payload: {error}"))?;
+        write_file_atomic(&self.file_path, text.as_bytes())
+            .map_err(|error| format!("failed to write {}: {error}", self.file_path.display()))
+    }
+
+    fn save(&self, ids: LogIds) {
+        if let Err(error) = self.flush() {
+            server_log::error(
+                "ranking-store",
+                "failed to save",
+                ids,
+                json!({ "path": self.file_path.display().to_string(), "error": error }),
+            );
         }
     }
 }
 
+fn get_top(
+    entries: &HashMap<String, StoredRankingEntry>,
+    requested_limit: Option<usize>,
+) -> Vec<PersistentRankingEntry> {
+    let normalized_limit = requested_limit.unwrap_or(10).clamp(1, 100);
+    let mut entries: Vec<PersistentRankingEntry> = entries
+        .values()
+        .map(|entry| {
+            let matches = entry.matches as f64;
+            let (win_rate, avg_capture_ratio, avg_rescues) = if entry.matches > 0 {
+                (
+                    entry.wins as f64 / matches,
+                    entry.total_capture_ratio / matches,
+                    entry.total_rescues / matches,
+                )
+            } else {
+                (0.0, 0.0, 0.0)
+            };
+            PersistentRankingEntry {
+                name: entry.name.clone(),
+                matches: entry.matches,
+                wins: entry.wins.min(entry.matches),
+                win_rate,
+                avg_capture_ratio,
+                avg_rescues,
+                best_score: entry.best_score,
+                updated_at_ms: entry.updated_at_ms,
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        cmp_desc_f64(a.win_rate, b.win_rate)
+            .then_with(|| cmp_desc_f64(a.avg_capture_ratio, b.avg_capture_ratio))
+            .then_with(|| cmp_desc_f64(a.avg_rescues, b.avg_rescues))
+            .then_with(|| b.best_score.cmp(&a.best_score))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    entries.truncate(normalized_limit);
+    entries
+}
+
 fn cmp_desc_f64(a: f64, b: f64) -> Ordering {
     b.partial_cmp(&a).unwrap_or(Ordering::Equal)
 }
 
-fn load_players(path: &Path) -> HashMap<String, StoredRankingEntry> {
+type RankingEntries = HashMap<String, StoredRankingEntry>;
+
+fn load_entries(path: &Path) -> (RankingEntries, RankingEntries) {
     let text = match fs::read_to_string(path) {
         Ok(value) => value,
         Err(error) => {
             if error.kind() != std::io::ErrorKind::NotFound {
-                eprintln!("[ranking-store] failed to read {}: {error}", path.display());
+                server_log::warn(
+                    "ranking-store",
+                    "failed to read",
+                    LogIds::default(),
+                    json!({ "path": path.display().to_string(), "error": error.to_string() }),
+                );
             }
-            return HashMap::new();
+            return (HashMap::new(), HashMap::new());
         }
     };
     let parsed: RankingStoreFileRaw = match serde_json::from_str::<RankingStoreFileRaw>(&text) {
         Ok(value) if value.version == 1 => value,
         Ok(value) => {
-            eprintln!(
-                "[ranking-store] unsupported version {} at {}",
-                value.version,
-                path.display()
+            server_log::warn(
+                "ranking-store",
+                "unsupported version",
+                LogIds::default(),
+                json!({ "path": path.display().to_string(), "version": value.version }),
             );
-            return HashMap::new();
+            return (HashMap::new(), HashMap::new());
         }
         Err(error) => {
-            eprintln!(
-                "[ranking-store] failed to parse {}: {error}",
-                path.display()
+            server_log::warn(
+                "ranking-store",
+                "failed to parse",
+                LogIds::default(),
+                json!({ "path": path.display().to_string(), "error": error.to_string() }),
             );
-            return HashMap::new();
+            return (HashMap::new(), HashMap::new());
         }
     };
 
-    let mut sanitized = HashMap::<String, StoredRankingEntry>::new();
-    for (player_key, raw_value) in parsed.players {
+    (
+        sanitize_entries(parsed.players, path),
+        sanitize_entries(parsed.bots, path),
+    )
+}
+
+fn sanitize_entries(
+    raw_entries: HashMap<String, serde_json::Value>,
+    path: &Path,
+) -> RankingEntries {
+    let mut sanitized = RankingEntries::new();
+    for (player_key, raw_value) in raw_entries {
         let value: StoredRankingEntry = match serde_json::from_value(raw_value) {
             Ok(entry) => entry,
             Err(error) => {
-                eprintln!(
-                    "[ranking-store] failed to parse player entry '{}' in {}: {error}",
-                    player_key,
-                    path.display()
+                server_log::warn(
+                    "ranking-store",
+                    "failed to parse player entry",
+                    LogIds::default(),
+                    json!({
+                        "path": path.display().to_string(),
+                        "entry": player_key,
+                        "error": error.to_string(),
+                    }),
                 );
                 continue;
             }
@@ -310,6 +382,7 @@ mod tests {
                     ghosts: 0,
                     rescues,
                     captures: 0,
+                    bot: false,
                 })
                 .collect(),
             awards: vec![AwardEntry {
@@ -339,16 +412,22 @@ mod tests {
     fn record_match_aggregates_humans_only() {
         let path = temp_file("ranking-store-record");
         let mut store = RankingStore::new(path.clone());
-        store.record_match(&make_summary(
-            GameOverReason::Victory,
-            0.8,
-            vec![("p1", "Alice", 100, 3), ("ai_1", "AI-01", 200, 0)],
-        ));
-        store.record_match(&make_summary(
-            GameOverReason::Timeout,
-            0.4,
-            vec![("p1", "Alice", 50, 1), ("p2", "Bob", 80, 2)],
-        ));
+        store.record_match(
+            &make_summary(
+                GameOverReason::Victory,
+                0.8,
+                vec![("p1", "Alice", 100, 3), ("ai_1", "AI-01", 200, 0)],
+            ),
+            LogIds::default(),
+        );
+        store.record_match(
+            &make_summary(
+                GameOverReason::Timeout,
+                0.4,
+                vec![("p1", "Alice", 50, 1), ("p2", "Bob", 80, 2)],
+            ),
+            LogIds::default(),
+        );
 
         let response = store.build_response(Some(10));
         assert_eq!(response.entries.len(), 2);
@@ -369,14 +448,70 @@ mod tests {
     }
 
     #[test]
-    fn record_match_keeps_human_name_even_if_prefixed_with_ai() {
-        let path = temp_file("ranking-store-human-ai-prefix");
+    fn flush_replaces_the_file_without_leaving_a_temp_file() {
+        let path = temp_file("ranking-store-flush");
         let mut store = RankingStore::new(path.clone());
-        store.record_match(&make_summary(
+        store.record_match(
+            &make_summary(GameOverReason::Shutdown, 0.3, vec![("p1", "Alice", 40, 0)]),
+            LogIds::default(),
+        );
+        store.flush().expect("flush should succeed");
+
+        let reloaded = RankingStore::new(path.clone());
+        let alice = &reloaded.build_response(Some(10)).entries[0];
+        assert_eq!((alice.matches, alice.wins), (1, 0));
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        assert!(!PathBuf::from(temp_path).exists());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn reset_and_delete_target_one_board() {
+        let path = temp_file("ranking-store-admin");
+        let mut store = RankingStore::new(path.clone());
+        let mut summary = make_summary(
             GameOverReason::Victory,
             0.9,
-            vec![("p1", "AI-Human", 10, 1), ("ai_1", "AI-01", 5, 0)],
-        ));
+            vec![("p1", "Alice", 120, 2), ("p2", "Bob", 80, 1)],
+        );
+        store.record_match(&summary, LogIds::default());
+        summary.ranking[1].bot = true;
+        store.record_match(&summary, LogIds::default());
+
+        assert!(store.reset_player(" ALICE ", false));
+        assert!(!store.reset_player("Carol", false));
+        let alice = store
+            .build_response(Some(10))
+            .entries
+            .into_iter()
+            .find(|entry| entry.name == "Alice")
+            .expect("reset keeps the entry");
+        assert_eq!((alice.matches, alice.wins, alice.best_score), (0, 0, 0));
+
+        assert!(store.delete_player("bob", true));
+        assert!(store.build_bot_response(Some(10)).entries.is_empty());
+        assert_eq!(store.build_response(Some(10)).entries.len(), 2);
+        assert!(!store.delete_player("bob", true));
+
+        let reloaded = RankingStore::new(path.clone());
+        assert!(reloaded.build_bot_response(Some(10)).entries.is_empty());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn record_match_keeps_human_name_even_if_prefixed_with_ai() {
+        let path = temp_file("ranking-store-human-ai-prefix");
+        let mut store = RankingStore::new(path.clone());
+        store.record_match(
+            &make_summary(
+                GameOverReason::Victory,
+                0.9,
+                vec![("p1", "AI-Human", 10, 1), ("ai_1", "AI-01", 5, 0)],
+            ),
+            LogIds::default(),
+        );
 
         let response = store.build_response(Some(10));
         assert_eq!(response.entries.len(), 1);
@@ -387,6 +522,32 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn record_match_ranks_bots_separately() {
+        let path = temp_file("ranking-store-bots");
+        let mut store = RankingStore::new(path.clone());
+        let mut summary = make_summary(
+            GameOverReason::Victory,
+            0.7,
+            vec![("p1", "Alice", 10, 1), ("p2", "GreedyBot", 40, 0)],
+        );
+        summary.ranking[1].bot = true;
+        store.record_match(&summary, LogIds::default());
+
+        let humans = store.build_response(Some(10));
+        assert_eq!(humans.entries.len(), 1);
+        assert_eq!(humans.entries[0].name, "Alice");
+        let bots = store.build_bot_response(Some(10));
+        assert_eq!(bots.entries.len(), 1);
+        assert_eq!(bots.entries[0].name, "GreedyBot");
+
+        let reloaded = RankingStore::new(path.clone());
+        assert_eq!(reloaded.build_response(Some(10)).entries.len(), 1);
+        assert_eq!(reloaded.build_bot_response(Some(10)).entries.len(), 1);
+
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn load_merges_case_insensitive_names() {
         let path = temp_file("ranking-store-load");
@@ -470,16 +631,19 @@ mod tests {
         let path = temp_file("ranking-store-limit");
         let mut store = RankingStore::new(path.clone());
         for idx in 0..3 {
-            store.record_match(&make_summary(
-                GameOverReason::Timeout,
-                0.3,
-                vec![(
-                    &format!("p{}", idx + 1),
-                    &format!("P{}", idx + 1),
-                    idx + 1,
-                    0,
-                )],
-            ));
+            store.record_match(
+                &make_summary(
+                    GameOverReason::Timeout,
+                    0.3,
+                    vec![(
+                        &format!("p{}", idx + 1),
+                        &format!("P{}", idx + 1),
+                        idx + 1,
+                        0,
+                    )],
+                ),
+                LogIds::default(),
+            );
         }
 
         assert_eq!(store.build_response(Some(1)).entries.len(), 1);
@@ -0,0 +1,260 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Difficulty;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyMultiplier {
+    #[serde(rename = "ghostSpeed")]
+    pub ghost_speed: f32,
+    pub maintenance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyMultipliers {
+    pub casual: DifficultyMultiplier,
+    pub normal: DifficultyMultiplier,
+    pub hard: DifficultyMultiplier,
+    pub nightmare: DifficultyMultiplier,
+}
+
+impl DifficultyMultipliers {
+    pub fn get(&self, difficulty: Difficulty) -> DifficultyMultiplier {
+        match difficulty {
+            Difficulty::Casual => self.casual,
+            Difficulty::Normal => self.normal,
+            Difficulty::Hard => self.hard,
+            Difficulty::Nightmare => self.nightmare,
+        }
+    }
+
+    pub fn get_mut(&mut self, difficulty: Difficulty) -> &mut DifficultyMultiplier {
+        match difficulty {
+            Difficulty::Casual => &mut self.casual,
+            Difficulty::Normal => &mut self.normal,
+            Difficulty::Hard => &mut self.hard,
+            Difficulty::Nightmare => &mut self.nightmare,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturePressureBand {
+    #[serde(rename = "maxCaptureRatio")]
+    pub max_capture_ratio: f32,
+    #[serde(rename = "graceMs")]
+    pub grace_ms: u64,
+    #[serde(rename = "regenMultiplier")]
+    pub regen_multiplier: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturePressureDefault {
+    #[serde(rename = "graceMs")]
+    pub grace_ms: u64,
+    #[serde(rename = "regenMultiplier")]
+    pub regen_multiplier: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CasualBandRules {
+    #[serde(rename = "fivePlayerSpeedMultiplier")]
+    pub five_player_speed_multiplier: f32,
+    #[serde(rename = "fivePlayerRegenRelief")]
+    pub five_player_regen_relief: f32,
+    #[serde(rename = "tenPlayerSpeedMultiplier")]
+    pub ten_player_speed_multiplier: f32,
+    #[serde(rename = "tenPlayerRegenRelief")]
+    pub ten_player_regen_relief: f32,
+}
+
+impl Default for CasualBandRules {
+    fn default() -> Self {
+        Self {
+            five_player_speed_multiplier: 1.12,
+            five_player_regen_relief: 0.45,
+            ten_player_speed_multiplier: 1.08,
+            ten_player_regen_relief: 0.65,
+        }
+    }
+}
+
+// Runtime-adjustable subset of `config/game_rules.json`. Unknown keys are ignored, so the
+// shared rules file can be loaded as-is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BalanceRules {
+    #[serde(rename = "difficultyMultipliers")]
+    pub difficulty_multipliers: DifficultyMultipliers,
+    #[serde(rename = "capturePressure")]
+    pub capture_pressure: Vec<CapturePressureBand>,
+    #[serde(rename = "capturePressureDefault")]
+    pub capture_pressure_default: CapturePressureDefault,
+    #[serde(rename = "casualBands", default)]
+    pub casual_bands: CasualBandRules,
+}
+
+impl Default for BalanceRules {
+    fn default() -> Self {
+        let multiplier = |ghost_speed, maintenance| DifficultyMultiplier {
+            ghost_speed,
+            maintenance,
+        };
+        let band = |max_capture_ratio, grace_ms, regen_multiplier| CapturePressureBand {
+            max_capture_ratio,
+            grace_ms,
+            regen_multiplier,
+        };
+        Self {
+            difficulty_multipliers: DifficultyMultipliers {
+                casual: multiplier(0.8, 0.6),
+                normal: multiplier(1.0, 1.0),
+                hard: multiplier(1.2, 1.4),
+                nightmare: multiplier(1.5, 2.0),
+            },
+            capture_pressure: vec![
+                band(0.3, 120_000, 1.0),
+                band(0.5, 90_000, 1.3),
+                band(0.7, 60_000, 1.8),
+                band(0.85, 40_000, 2.5),
+                band(0.95, 25_000, 3.5),
+            ],
+            capture_pressure_default: CapturePressureDefault {
+                grace_ms: 15_000,
+                regen_multiplier: 5.0,
+            },
+            casual_bands: CasualBandRules::default(),
+        }
+    }
+}
+
+impl BalanceRules {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let rules: Self = serde_json::from_str(&text)
+            .map_err(|error| format!("failed to parse {}: {error}", path.display()))?;
+        rules
+            .validate()
+            .map_err(|error| format!("invalid rules in {}: {error}", path.display()))?;
+        Ok(rules)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, multiplier) in [
+            ("casual", self.difficulty_multipliers.casual),
+            ("normal", self.difficulty_multipliers.normal),
+            ("hard", self.difficulty_multipliers.hard),
+            ("nightmare", self.difficulty_multipliers.nightmare),
+        ] {
+            if !is_positive(multiplier.ghost_speed) || !is_positive(multiplier.maintenance) {
+                return Err(format!("difficultyMultipliers.{name} must be positive"));
+            }
+        }
+
+        let mut previous = f32::NEG_INFINITY;
+        for (index, band) in self.capture_pressure.iter().enumerate() {
+            if !band.max_capture_ratio.is_finite() || band.max_capture_ratio <= previous {
+                return Err(format!(
+                    "capturePressure[{index}].maxCaptureRatio must be strictly ascending"
+                ));
+            }
+            if band.regen_multiplier.is_nan() || band.regen_multiplier < 0.0 {
+                return Err(format!(
+                    "capturePressure[{index}].regenMultiplier must be >= 0"
+                ));
+            }
+            previous = band.max_capture_ratio;
+        }
+        if self.capture_pressure_default.regen_multiplier.is_nan()
+            || self.capture_pressure_default.regen_multiplier < 0.0
+        {
+            return Err("capturePressureDefault.regenMultiplier must be >= 0".to_string());
+        }
+
+        let casual = self.casual_bands;
+        for (name, value) in [
+            (
+                "fivePlayerSpeedMultiplier",
+                casual.five_player_speed_multiplier,
+            ),
+            ("fivePlayerRegenRelief", casual.five_player_regen_relief),
+            (
+                "tenPlayerSpeedMultiplier",
+                casual.ten_player_speed_multiplier,
+            ),
+            ("tenPlayerRegenRelief", casual.ten_player_regen_relief),
+        ] {
+            if !is_positive(value) {
+                return Err(format!("casualBands.{name} must be positive"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn difficulty_multiplier(&self, difficulty: Difficulty) -> (f32, f32) {
+        let multiplier = self.difficulty_multipliers.get(difficulty);
+        (multiplier.ghost_speed, multiplier.maintenance)
+    }
+
+    pub fn capture_pressure(&self, capture_ratio: f32) -> (u64, f32) {
+        self.capture_pressure
+            .iter()
+            .find(|band| capture_ratio <= band.max_capture_ratio)
+            .map(|band| (band.grace_ms, band.regen_multiplier))
+            .unwrap_or((
+                self.capture_pressure_default.grace_ms,
+                self.capture_pressure_default.regen_multiplier,
+            ))
+    }
+}
+
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{get_capture_pressure, get_difficulty_multiplier};
+
+    #[test]
+    fn default_rules_match_generated_constants() {
+        let rules = BalanceRules::default();
+        for ratio in [
+            0.0, 0.3, 0.31, 0.5, 0.6, 0.7, 0.8, 0.85, 0.9, 0.95, 0.99, 1.0,
+        ] {
+            assert_eq!(rules.capture_pressure(ratio), get_capture_pressure(ratio));
+        }
+        for difficulty in [
+            Difficulty::Casual,
+            Difficulty::Normal,
+            Difficulty::Hard,
+            Difficulty::Nightmare,
+        ] {
+            assert_eq!(
+                rules.difficulty_multiplier(difficulty),
+                get_difficulty_multiplier(difficulty)
+            );
+        }
+    }
+
+    #[test]
+    fn shared_game_rules_file_is_loadable() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../config/game_rules.json");
+        let rules = BalanceRules::load(&path).expect("config/game_rules.json should load");
+        assert_eq!(rules, BalanceRules::default());
+    }
+
+    #[test]
+    fn validate_rejects_unordered_capture_bands() {
+        let mut rules = BalanceRules::default();
+        rules.capture_pressure.swap(0, 1);
+        assert!(rules.validate().is_err());
+
+        let mut rules = BalanceRules::default();
+        rules.difficulty_multipliers.hard.ghost_speed = 0.0;
+        assert!(rules.validate().is_err());
+    }
+}
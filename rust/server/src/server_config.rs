@@ -0,0 +1,405 @@
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chat_manager::ChatManagerOptions;
+use crate::engine::SquadPlannerMode;
+use crate::flood_guard::FloodGuardOptions;
+use crate::match_checkpoint::CheckpointOptions;
+use crate::matchmaking::MatchmakingOptions;
+use crate::ping_manager::PingManagerOptions;
+use crate::rules::BalanceRules;
+use crate::server_utils::LobbyLimits;
+use crate::tick_clock::TickClockOptions;
+
+pub const DEFAULT_BOT_DECISION_DEADLINE_MS: u64 = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomLimits {
+    pub max_players: usize,
+    pub max_spectators: usize,
+}
+
+impl Default for RoomLimits {
+    fn default() -> Self {
+        Self {
+            max_players: 100,
+            max_spectators: 100,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminOptions {
+    // Shared secret for `/api/admin/*`; the admin API is disabled while unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+// Everything `server` can be tuned with. Sources are applied in order: defaults, config file,
+// environment variables, command line flags.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub ranking_db_path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules_file: Option<PathBuf>,
+    pub client_channel_size: usize,
+    pub bot_decision_deadline_ms: u64,
+    // How long open sockets get to complete the close handshake after a termination signal.
+    pub shutdown_grace_ms: u64,
+    // `auto` runs the AI squad planner from `SQUAD_PLANNER_AUTO_MIN_PLAYERS` players up;
+    // `on` / `off` force it.
+    pub squad_planner: SquadPlannerMode,
+    pub room: RoomLimits,
+    pub lobby: LobbyLimits,
+    pub pings: PingManagerOptions,
+    pub chat: ChatManagerOptions,
+    pub flood: FloodGuardOptions,
+    pub matchmaking: MatchmakingOptions,
+    pub tick: TickClockOptions,
+    pub checkpoint: CheckpointOptions,
+    pub admin: AdminOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8080,
+            ranking_db_path: PathBuf::from(".data/ranking.json"),
+            static_dir: None,
+            rules_file: None,
+            client_channel_size: 256,
+            bot_decision_deadline_ms: DEFAULT_BOT_DECISION_DEADLINE_MS,
+            shutdown_grace_ms: 5_000,
+            squad_planner: SquadPlannerMode::Auto,
+            room: RoomLimits::default(),
+            lobby: LobbyLimits::default(),
+            pings: PingManagerOptions::default(),
+            chat: ChatManagerOptions::default(),
+            flood: FloodGuardOptions::default(),
+            matchmaking: MatchmakingOptions::default(),
+            tick: TickClockOptions::default(),
+            checkpoint: CheckpointOptions::default(),
+            admin: AdminOptions::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    // `.json` files are read as JSON, anything else as TOML. Keys missing from the file keep
+    // their defaults; unknown keys are rejected.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&text)
+                .map_err(|error| format!("failed to parse {}: {error}", path.display()))
+        } else {
+            toml::from_str(&text)
+                .map_err(|error| format!("failed to parse {}: {error}", path.display()))
+        }
+    }
+
+    // `PORT`, `RANKING_DB_PATH`, `CHECKPOINT_PATH`, `STATIC_DIR`, `BOT_DECISION_DEADLINE_MS`,
+    // `ADMIN_API_TOKEN` and `CHAT_BANNED_WORDS` (comma separated) override the file.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let non_empty = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        if let Some(raw) = non_empty("PORT") {
+            self.port = raw
+                .trim()
+                .parse()
+                .map_err(|_| format!("PORT must be a port number: {raw}"))?;
+        }
+        if let Some(raw) = non_empty("RANKING_DB_PATH") {
+            self.ranking_db_path = PathBuf::from(raw);
+        }
+        if let Some(raw) = non_empty("CHECKPOINT_PATH") {
+            self.checkpoint.path = PathBuf::from(raw);
+        }
+        if let Some(raw) = non_empty("STATIC_DIR") {
+            self.static_dir = Some(PathBuf::from(raw));
+        }
+        if let Some(raw) = non_empty("BOT_DECISION_DEADLINE_MS") {
+            self.bot_decision_deadline_ms = raw
+                .trim()
+                .parse()
+                .map_err(|_| format!("BOT_DECISION_DEADLINE_MS must be an integer: {raw}"))?;
+        }
+        if let Some(raw) = non_empty("ADMIN_API_TOKEN") {
+            self.admin.token = Some(raw.trim().to_string());
+        }
+        if let Some(raw) = non_empty("CHAT_BANNED_WORDS") {
+            self.chat.banned_words = raw.split(',').map(str::to_string).collect();
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, message: &str| {
+            if !ok {
+                problems.push(message.to_string());
+            }
+        };
+
+        check(
+            self.client_channel_size >= 1,
+            "client_channel_size must be >= 1",
+        );
+        check(
+            self.bot_decision_deadline_ms >= 1,
+            "bot_decision_deadline_ms must be >= 1",
+        );
+        check(self.room.max_players >= 1, "room.max_players must be >= 1");
+
+        let lobby = &self.lobby;
+        check(
+            lobby.min_time_limit_minutes >= 1,
+            "lobby.min_time_limit_minutes must be >= 1",
+        );
+        check(
+            lobby.min_time_limit_minutes <= lobby.max_time_limit_minutes,
+            "lobby.min_time_limit_minutes must be <= lobby.max_time_limit_minutes",
+        );
+
+        let pings = &self.pings;
+        check(pings.ttl_ms >= 1, "pings.ttl_ms must be >= 1");
+        check(
+            pings.max_active_pings >= 1,
+            "pings.max_active_pings must be >= 1",
+        );
+        check(
+            pings.max_per_player >= 1,
+            "pings.max_per_player must be >= 1",
+        );
+        check(
+            pings.max_per_window >= 1,
+            "pings.max_per_window must be >= 1",
+        );
+        check(
+            pings.max_target_distance >= 0,
+            "pings.max_target_distance must be >= 0",
+        );
+
+        check(self.chat.max_length >= 1, "chat.max_length must be >= 1");
+        check(
+            self.chat.max_per_window >= 1,
+            "chat.max_per_window must be >= 1",
+        );
+
+        let flood = &self.flood;
+        check(
+            flood.max_frame_bytes >= 1_024,
+            "flood.max_frame_bytes must be >= 1024",
+        );
+        check(
+            flood.drop_strikes <= flood.error_strikes,
+            "flood.drop_strikes must be <= flood.error_strikes",
+        );
+        for (name, limit) in std::iter::once(("default_limit", &flood.default_limit)).chain(
+            flood
+                .limits
+                .iter()
+                .map(|(name, limit)| (name.as_str(), limit)),
+        ) {
+            check(
+                limit.burst >= 1.0 && limit.burst.is_finite(),
+                &format!("flood limit `{name}` burst must be >= 1"),
+            );
+            check(
+                limit.per_second >= 0.0 && limit.per_second.is_finite(),
+                &format!("flood limit `{name}` per_second must be >= 0"),
+            );
+        }
+
+        let matchmaking = &self.matchmaking;
+        check(
+            matchmaking.max_party_size >= 1,
+            "matchmaking.max_party_size must be >= 1",
+        );
+        check(
+            (1..=matchmaking.max_party_size).contains(&matchmaking.default_party_size),
+            "matchmaking.default_party_size must be between 1 and max_party_size",
+        );
+        check(
+            self.tick.max_catch_up_steps >= 1,
+            "tick.max_catch_up_steps must be >= 1",
+        );
+        check(
+            !self.checkpoint.enabled || self.checkpoint.interval_ms >= 100,
+            "checkpoint.interval_ms must be >= 100",
+        );
+        check(
+            self.admin
+                .token
+                .as_ref()
+                .is_none_or(|token| token.trim().len() >= 16),
+            "admin.token must be at least 16 characters",
+        );
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    pub fn load_rules(&self) -> Result<BalanceRules, String> {
+        match &self.rules_file {
+            Some(path) => BalanceRules::load(path),
+            None => Ok(BalanceRules::default()),
+        }
+    }
+
+    // Secrets are left out so the dump can be shared or committed.
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut public = self.clone();
+        public.admin.token = None;
+        toml::to_string_pretty(&public)
+            .map_err(|error| format!("failed to serialize config: {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    use super::*;
+
+    fn write_temp(name: &str, text: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("mmo-packman-config-{}-{name}", std::process::id()));
+        fs::write(&path, text).expect("temp config should be writable");
+        path
+    }
+
+    #[test]
+    fn defaults_are_valid_and_round_trip_through_toml() {
+        let config = ServerConfig::default();
+        assert_eq!(config.validate(), Ok(()));
+        let text = config.to_toml().expect("defaults should serialize");
+        let parsed: ServerConfig = toml::from_str(&text).expect("dump should parse");
+        assert_eq!(parsed.port, 8080);
+        assert_eq!(parsed.client_channel_size, 256);
+        assert_eq!(parsed.flood.limits, config.flood.limits);
+        assert_eq!(parsed.tick.tick_ms, config.tick.tick_ms);
+    }
+
+    #[test]
+    fn admin_token_is_validated_and_never_dumped() {
+        let mut config = ServerConfig::default();
+        config
+            .apply_env(|name| (name == "ADMIN_API_TOKEN").then(|| "short".to_string()))
+            .expect("env should apply");
+        assert!(config
+            .validate()
+            .expect_err("short token should be rejected")
+            .contains("admin.token"));
+
+        config.admin.token = Some("0123456789abcdef".to_string());
+        assert_eq!(config.validate(), Ok(()));
+        let text = config.to_toml().expect("config should serialize");
+        assert!(!text.contains("0123456789abcdef"));
+        assert_eq!(config.admin.token.as_deref(), Some("0123456789abcdef"));
+    }
+
+    #[test]
+    fn partial_files_keep_defaults_for_missing_keys() {
+        let path = write_temp(
+            "partial.toml",
+            "port = 9000\n\n[lobby]\nmax_ai_players = 12\n\n[chat]\nmax_length = 80\n\n[flood.limits.chat]\nburst = 3.0\nper_second = 1.0\n",
+        );
+        let config = ServerConfig::load(&path).expect("partial toml should load");
+        let _ = fs::remove_file(&path);
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.lobby.max_ai_players, 12);
+        assert_eq!(config.lobby.max_time_limit_minutes, 10);
+        assert_eq!(config.chat.max_length, 80);
+        assert_eq!(config.chat.history_size, 50);
+        assert_eq!(config.flood.limits.get("chat").map(|l| l.burst), Some(3.0));
+        assert_eq!(config.pings.ttl_ms, PingManagerOptions::default().ttl_ms);
+
+        let path = write_temp(
+            "partial.json",
+            r#"{"room": {"max_players": 8}, "matchmaking": {"wait_threshold_ms": 5000}}"#,
+        );
+        let config = ServerConfig::load(&path).expect("partial json should load");
+        let _ = fs::remove_file(&path);
+        assert_eq!(config.room.max_players, 8);
+        assert_eq!(config.room.max_spectators, 100);
+        assert_eq!(config.matchmaking.wait_threshold_ms, 5_000);
+    }
+
+    #[test]
+    fn bind_address_accepts_ipv6_and_rejects_host_names() {
+        let path = write_temp("ipv6.toml", "bind_address = \"::\"\nport = 8080\n");
+        let config = ServerConfig::load(&path).expect("ipv6 bind address should load");
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            SocketAddr::new(config.bind_address, config.port).to_string(),
+            "[::]:8080"
+        );
+
+        let path = write_temp("hostname.toml", "bind_address = \"localhost:80\"\n");
+        let result = ServerConfig::load(&path);
+        let _ = fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let path = write_temp("typo.toml", "[pings]\nttl = 10\n");
+        let result = ServerConfig::load(&path);
+        let _ = fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let env = HashMap::from([
+            ("PORT", "7000"),
+            ("RANKING_DB_PATH", "/tmp/ranking.json"),
+            ("STATIC_DIR", ""),
+            ("CHAT_BANNED_WORDS", "spam,scam"),
+        ]);
+        let mut config = ServerConfig::default();
+        config
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .expect("env should apply");
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.ranking_db_path, PathBuf::from("/tmp/ranking.json"));
+        assert_eq!(config.static_dir, None);
+        assert_eq!(config.chat.banned_words, vec!["spam", "scam"]);
+
+        let mut config = ServerConfig::default();
+        assert!(config
+            .apply_env(|name| (name == "PORT").then(|| "http".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut config = ServerConfig::default();
+        config.lobby.min_time_limit_minutes = 20;
+        config.matchmaking.default_party_size = 0;
+        config.chat.max_per_window = 0;
+        let error = config.validate().expect_err("config should be invalid");
+        assert!(error.contains("lobby.min_time_limit_minutes must be <="));
+        assert!(error.contains("matchmaking.default_party_size"));
+        assert!(error.contains("chat.max_per_window"));
+    }
+}
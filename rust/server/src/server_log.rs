@@ -0,0 +1,247 @@
+use std::sync::OnceLock;
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+static OPTIONS: OnceLock<LogOptions> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" | "warning" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogOptions {
+    pub level: LogLevel,
+    pub format: LogFormat,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+        }
+    }
+}
+
+impl LogOptions {
+    // `LOG_LEVEL` = error | warn | info | debug, `LOG_FORMAT` = text | json.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            level: std::env::var("LOG_LEVEL")
+                .ok()
+                .and_then(|value| LogLevel::parse(&value))
+                .unwrap_or(defaults.level),
+            format: std::env::var("LOG_FORMAT")
+                .ok()
+                .and_then(|value| LogFormat::parse(&value))
+                .unwrap_or(defaults.format),
+        }
+    }
+}
+
+// Correlation ids carried by every line; missing ones are printed as `-` / `null`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogIds<'a> {
+    pub client_id: Option<&'a str>,
+    pub player_id: Option<&'a str>,
+    pub match_id: Option<&'a str>,
+}
+
+// The first call wins; logging before `init` uses the defaults.
+pub fn init(options: LogOptions) {
+    let _ = OPTIONS.set(options);
+}
+
+pub fn options() -> LogOptions {
+    OPTIONS.get().copied().unwrap_or_default()
+}
+
+pub fn log(level: LogLevel, target: &str, message: &str, ids: LogIds, fields: Value) {
+    let options = options();
+    if level > options.level {
+        return;
+    }
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let line = format_line(
+        options.format,
+        &timestamp,
+        level,
+        target,
+        message,
+        ids,
+        &fields,
+    );
+    if level <= LogLevel::Warn {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+pub fn error(target: &str, message: &str, ids: LogIds, fields: Value) {
+    log(LogLevel::Error, target, message, ids, fields);
+}
+
+pub fn warn(target: &str, message: &str, ids: LogIds, fields: Value) {
+    log(LogLevel::Warn, target, message, ids, fields);
+}
+
+pub fn info(target: &str, message: &str, ids: LogIds, fields: Value) {
+    log(LogLevel::Info, target, message, ids, fields);
+}
+
+pub fn debug(target: &str, message: &str, ids: LogIds, fields: Value) {
+    log(LogLevel::Debug, target, message, ids, fields);
+}
+
+// `fields` is a JSON object; anything else is logged under a single `value` key.
+pub fn format_line(
+    format: LogFormat,
+    timestamp: &str,
+    level: LogLevel,
+    target: &str,
+    message: &str,
+    ids: LogIds,
+    fields: &Value,
+) -> String {
+    let fields = match fields {
+        Value::Object(map) => map.clone(),
+        Value::Null => Map::new(),
+        other => Map::from_iter([("value".to_string(), other.clone())]),
+    };
+    match format {
+        LogFormat::Json => {
+            let mut line = Map::new();
+            line.insert("ts".to_string(), timestamp.into());
+            line.insert("level".to_string(), level.as_str().into());
+            line.insert("target".to_string(), target.into());
+            line.insert("msg".to_string(), message.into());
+            line.insert("clientId".to_string(), ids.client_id.into());
+            line.insert("playerId".to_string(), ids.player_id.into());
+            line.insert("matchId".to_string(), ids.match_id.into());
+            for (key, value) in fields {
+                line.entry(key).or_insert(value);
+            }
+            Value::Object(line).to_string()
+        }
+        LogFormat::Text => {
+            let mut line = format!(
+                "{timestamp} {:<5} [{target}] {message} client={} player={} match={}",
+                level.as_str().to_ascii_uppercase(),
+                ids.client_id.unwrap_or("-"),
+                ids.player_id.unwrap_or("-"),
+                ids.match_id.unwrap_or("-"),
+            );
+            for (key, value) in fields {
+                line.push_str(&format!(" {key}={value}"));
+            }
+            line
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const TS: &str = "2026-01-02T03:04:05.678Z";
+
+    #[test]
+    fn text_lines_always_carry_correlation_ids() {
+        let ids = LogIds {
+            client_id: Some("client_1"),
+            player_id: Some("player_2"),
+            match_id: None,
+        };
+        let line = format_line(
+            LogFormat::Text,
+            TS,
+            LogLevel::Info,
+            "server",
+            "player joined",
+            ids,
+            &json!({ "name": "A B", "spectator": false }),
+        );
+        assert_eq!(
+            line,
+            "2026-01-02T03:04:05.678Z INFO  [server] player joined client=client_1 \
+             player=player_2 match=- name=\"A B\" spectator=false"
+        );
+    }
+
+    #[test]
+    fn json_lines_keep_reserved_keys() {
+        let ids = LogIds {
+            match_id: Some("match_9"),
+            ..LogIds::default()
+        };
+        let line = format_line(
+            LogFormat::Json,
+            TS,
+            LogLevel::Warn,
+            "ranking-store",
+            "write failed",
+            ids,
+            &json!({ "path": "/tmp/x", "msg": "shadowed" }),
+        );
+        let parsed: Value = serde_json::from_str(&line).expect("line should be json");
+        assert_eq!(parsed["level"], "warn");
+        assert_eq!(parsed["msg"], "write failed");
+        assert_eq!(parsed["matchId"], "match_9");
+        assert_eq!(parsed["clientId"], Value::Null);
+        assert_eq!(parsed["path"], "/tmp/x");
+    }
+
+    #[test]
+    fn levels_and_formats_parse() {
+        assert_eq!(LogLevel::parse(" WARNING "), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("trace"), None);
+        assert!(LogLevel::Debug > LogLevel::Info);
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+    }
+}
@@ -1,6 +1,15 @@
 use serde_json::Value;
 
-use crate::types::{Difficulty, Direction, PingType};
+use crate::types::{AiSkill, ChatChannel, Difficulty, Direction, PingTarget, PingType, Vec2};
+
+pub const PROTOCOL_VERSION: u32 = 2;
+// Version 2 changed `Snapshot`: pings gained `targetId` and the `gate`/`boss` kinds.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+// Clients that predate version negotiation send no `protocolVersion`.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+pub const PROTOCOL_TOO_OLD_CLOSE_CODE: u16 = 4010;
+pub const SERVER_CAPABILITIES: &[&str] =
+    &["input_ack", "entity_pings", "chat", "matchmaking", "binary"];
 
 #[derive(Debug)]
 pub enum ParsedClientMessage {
@@ -9,24 +18,84 @@ pub enum ParsedClientMessage {
         reconnect_token: Option<String>,
         spectator: bool,
         room_id: Option<String>,
+        bot: bool,
+        bot_token: Option<String>,
+        protocol_version: u32,
+        capabilities: Vec<String>,
     },
+    // `time_limit_minutes`: absent leaves it as is, `null` resets it to the difficulty default.
     LobbyStart {
         difficulty: Option<Difficulty>,
         ai_player_count: Option<i64>,
-        time_limit_minutes: Option<i64>,
+        time_limit_minutes: Option<Option<i64>>,
+        ai_skill: Option<AiSkill>,
+        ai_skills: Option<Vec<AiSkill>>,
+    },
+    LobbySettings {
+        difficulty: Option<Difficulty>,
+        ai_player_count: Option<i64>,
+        time_limit_minutes: Option<Option<i64>>,
+        ai_skill: Option<AiSkill>,
+        require_ready: Option<bool>,
+    },
+    Ready {
+        ready: bool,
+    },
+    HostTransfer {
+        player_id: String,
     },
     Input {
         dir: Option<Direction>,
         awaken: Option<bool>,
+        seq: Option<u64>,
+        client_time_ms: Option<u64>,
     },
     PlacePing {
         kind: PingType,
+        target: Option<PingTarget>,
+    },
+    Chat {
+        channel: ChatChannel,
+        text: String,
+    },
+    ChatMute {
+        player_id: String,
+        muted: bool,
+    },
+    Kick {
+        player_id: String,
+    },
+    QueueJoin {
+        difficulty: Option<Difficulty>,
+        party_size: Option<i64>,
     },
+    QueueLeave,
     Ping {
         t: f64,
     },
 }
 
+impl ParsedClientMessage {
+    // Wire name of the message, used as the key for per-type limits.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            Self::Hello { .. } => "hello",
+            Self::LobbyStart { .. } => "lobby_start",
+            Self::LobbySettings { .. } => "lobby_settings",
+            Self::Ready { .. } => "ready",
+            Self::HostTransfer { .. } => "host_transfer",
+            Self::Input { .. } => "input",
+            Self::PlacePing { .. } => "place_ping",
+            Self::Chat { .. } => "chat",
+            Self::ChatMute { .. } => "chat_mute",
+            Self::Kick { .. } => "kick",
+            Self::QueueJoin { .. } => "queue_join",
+            Self::QueueLeave => "queue_leave",
+            Self::Ping { .. } => "ping",
+        }
+    }
+}
+
 pub fn parse_client_message(raw: &str) -> Option<ParsedClientMessage> {
     let value: Value = serde_json::from_str(raw).ok()?;
     let object = value.as_object()?;
@@ -47,29 +116,84 @@ pub fn parse_client_message(raw: &str) -> Option<ParsedClientMessage> {
                 None => None,
                 Some(value) => Some(value.as_str()?.to_string()),
             };
+            let bot = match object.get("bot") {
+                None => false,
+                Some(value) => value.as_bool()?,
+            };
+            let bot_token = match object.get("botToken") {
+                None => None,
+                Some(value) => Some(value.as_str()?.to_string()),
+            };
+            let protocol_version = match parse_optional_i64(object.get("protocolVersion"))? {
+                None => LEGACY_PROTOCOL_VERSION,
+                Some(version) => u32::try_from(version).ok()?,
+            };
+            let capabilities = match object.get("capabilities") {
+                None => Vec::new(),
+                Some(value) => value
+                    .as_array()?
+                    .iter()
+                    .map(|item| item.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()?,
+            };
             Some(ParsedClientMessage::Hello {
                 name,
                 reconnect_token,
                 spectator,
                 room_id,
+                bot,
+                bot_token,
+                protocol_version,
+                capabilities,
             })
         }
         "lobby_start" => {
-            let difficulty = match object.get("difficulty") {
+            let difficulty = parse_optional_difficulty(object.get("difficulty"))?;
+            let ai_player_count = parse_optional_i64(object.get("aiPlayerCount"))?;
+            let time_limit_minutes = parse_nullable_i64(object.get("timeLimitMinutes"))?;
+            let ai_skill = parse_optional_ai_skill(object.get("aiSkill"))?;
+            let ai_skills = match object.get("aiSkills") {
                 None => None,
-                Some(value) => Difficulty::parse(value.as_str()?),
+                Some(value) => Some(
+                    value
+                        .as_array()?
+                        .iter()
+                        .map(|item| AiSkill::parse(item.as_str()?))
+                        .collect::<Option<Vec<_>>>()?,
+                ),
             };
-            if object.get("difficulty").is_some() && difficulty.is_none() {
-                return None;
-            }
-            let ai_player_count = parse_optional_i64(object.get("aiPlayerCount"))?;
-            let time_limit_minutes = parse_optional_i64(object.get("timeLimitMinutes"))?;
             Some(ParsedClientMessage::LobbyStart {
                 difficulty,
                 ai_player_count,
                 time_limit_minutes,
+                ai_skill,
+                ai_skills,
             })
         }
+        "lobby_settings" => {
+            let require_ready = match object.get("requireReady") {
+                None => None,
+                Some(value) => Some(value.as_bool()?),
+            };
+            Some(ParsedClientMessage::LobbySettings {
+                difficulty: parse_optional_difficulty(object.get("difficulty"))?,
+                ai_player_count: parse_optional_i64(object.get("aiPlayerCount"))?,
+                time_limit_minutes: parse_nullable_i64(object.get("timeLimitMinutes"))?,
+                ai_skill: parse_optional_ai_skill(object.get("aiSkill"))?,
+                require_ready,
+            })
+        }
+        "ready" => {
+            let ready = match object.get("ready") {
+                None => true,
+                Some(value) => value.as_bool()?,
+            };
+            Some(ParsedClientMessage::Ready { ready })
+        }
+        "host_transfer" => {
+            let player_id = object.get("playerId")?.as_str()?.to_string();
+            Some(ParsedClientMessage::HostTransfer { player_id })
+        }
         "input" => {
             let dir = match object.get("dir") {
                 None => None,
@@ -82,12 +206,61 @@ pub fn parse_client_message(raw: &str) -> Option<ParsedClientMessage> {
                 None => None,
                 Some(value) => Some(value.as_bool()?),
             };
-            Some(ParsedClientMessage::Input { dir, awaken })
+            let seq = parse_optional_u64(object.get("seq"))?;
+            let client_time_ms = parse_optional_u64(object.get("clientTimeMs"))?;
+            Some(ParsedClientMessage::Input {
+                dir,
+                awaken,
+                seq,
+                client_time_ms,
+            })
         }
         "place_ping" => {
             let kind = PingType::parse(object.get("kind")?.as_str()?)?;
-            Some(ParsedClientMessage::PlacePing { kind })
+            let x = parse_optional_i64(object.get("x"))?;
+            let y = parse_optional_i64(object.get("y"))?;
+            let target_id = match object.get("targetId") {
+                None => None,
+                Some(value) => Some(value.as_str()?.to_string()),
+            };
+            let target = match (x, y, target_id) {
+                (None, None, None) => None,
+                (Some(x), Some(y), None) => Some(PingTarget::Cell(Vec2 {
+                    x: i32::try_from(x).ok()?,
+                    y: i32::try_from(y).ok()?,
+                })),
+                (None, None, Some(target_id)) if !target_id.is_empty() => {
+                    Some(PingTarget::Entity(target_id))
+                }
+                _ => return None,
+            };
+            Some(ParsedClientMessage::PlacePing { kind, target })
+        }
+        "chat" => {
+            let channel = match object.get("channel") {
+                None => ChatChannel::default(),
+                Some(value) => ChatChannel::parse(value.as_str()?)?,
+            };
+            let text = object.get("text")?.as_str()?.to_string();
+            Some(ParsedClientMessage::Chat { channel, text })
         }
+        "chat_mute" => {
+            let player_id = object.get("playerId")?.as_str()?.to_string();
+            let muted = match object.get("muted") {
+                None => true,
+                Some(value) => value.as_bool()?,
+            };
+            Some(ParsedClientMessage::ChatMute { player_id, muted })
+        }
+        "kick" => {
+            let player_id = object.get("playerId")?.as_str()?.to_string();
+            Some(ParsedClientMessage::Kick { player_id })
+        }
+        "queue_join" => Some(ParsedClientMessage::QueueJoin {
+            difficulty: parse_optional_difficulty(object.get("difficulty"))?,
+            party_size: parse_optional_i64(object.get("partySize"))?,
+        }),
+        "queue_leave" => Some(ParsedClientMessage::QueueLeave),
         "ping" => {
             let t = object.get("t")?.as_f64()?;
             if !t.is_finite() {
@@ -99,6 +272,44 @@ pub fn parse_client_message(raw: &str) -> Option<ParsedClientMessage> {
     }
 }
 
+// Capabilities both sides support, in the server's order.
+pub fn negotiate_capabilities(requested: &[String]) -> Vec<String> {
+    SERVER_CAPABILITIES
+        .iter()
+        .filter(|capability| requested.iter().any(|item| item == *capability))
+        .map(|capability| capability.to_string())
+        .collect()
+}
+
+fn parse_optional_difficulty(value: Option<&Value>) -> Option<Option<Difficulty>> {
+    match value {
+        None => Some(None),
+        Some(value) => Some(Some(Difficulty::parse(value.as_str()?)?)),
+    }
+}
+
+fn parse_optional_ai_skill(value: Option<&Value>) -> Option<Option<AiSkill>> {
+    match value {
+        None => Some(None),
+        Some(value) => Some(Some(AiSkill::parse(value.as_str()?)?)),
+    }
+}
+
+fn parse_optional_u64(value: Option<&Value>) -> Option<Option<u64>> {
+    match parse_optional_i64(value)? {
+        None => Some(None),
+        Some(number) => Some(Some(u64::try_from(number).ok()?)),
+    }
+}
+
+// Like `parse_optional_i64`, but an explicit `null` is kept apart from a missing key.
+fn parse_nullable_i64(value: Option<&Value>) -> Option<Option<Option<i64>>> {
+    match value {
+        Some(Value::Null) => Some(Some(None)),
+        value => parse_optional_i64(value).map(|parsed| parsed.map(Some)),
+    }
+}
+
 fn parse_optional_i64(value: Option<&Value>) -> Option<Option<i64>> {
     const MAX_SAFE_INTEGER_F64: f64 = 9_007_199_254_740_991.0;
 
@@ -140,11 +351,19 @@ mod tests {
                 reconnect_token,
                 spectator,
                 room_id,
+                bot,
+                bot_token,
+                protocol_version,
+                capabilities,
             } => {
                 assert_eq!(name, "A");
                 assert_eq!(reconnect_token, None);
                 assert!(spectator);
                 assert_eq!(room_id, None);
+                assert!(!bot);
+                assert_eq!(bot_token, None);
+                assert_eq!(protocol_version, LEGACY_PROTOCOL_VERSION);
+                assert!(capabilities.is_empty());
             }
             _ => panic!("expected hello message"),
         }
@@ -162,6 +381,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_hello_message_with_bot_flag() {
+        let parsed =
+            parse_client_message(r#"{"type":"hello","name":"B","bot":true,"botToken":"secret"}"#)
+                .expect("bot hello should parse");
+        match parsed {
+            ParsedClientMessage::Hello { bot, bot_token, .. } => {
+                assert!(bot);
+                assert_eq!(bot_token.as_deref(), Some("secret"));
+            }
+            _ => panic!("expected hello message"),
+        }
+        assert!(parse_client_message(r#"{"type":"hello","name":"B","bot":"yes"}"#).is_none());
+    }
+
+    #[test]
+    fn parse_hello_protocol_version_and_capabilities() {
+        let parsed = parse_client_message(
+            r#"{"type":"hello","name":"A","protocolVersion":2,"capabilities":["chat","delta"]}"#,
+        )
+        .expect("hello should parse");
+        match parsed {
+            ParsedClientMessage::Hello {
+                protocol_version,
+                capabilities,
+                ..
+            } => {
+                assert_eq!(protocol_version, 2);
+                assert_eq!(negotiate_capabilities(&capabilities), vec!["chat"]);
+            }
+            _ => panic!("expected hello message"),
+        }
+        for raw in [
+            r#"{"type":"hello","name":"A","protocolVersion":-1}"#,
+            r#"{"type":"hello","name":"A","protocolVersion":"2"}"#,
+            r#"{"type":"hello","name":"A","capabilities":"chat"}"#,
+            r#"{"type":"hello","name":"A","capabilities":[1]}"#,
+        ] {
+            assert!(parse_client_message(raw).is_none(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn negotiate_capabilities_keeps_server_order() {
+        let requested = vec![
+            "matchmaking".to_string(),
+            "unknown".to_string(),
+            "input_ack".to_string(),
+        ];
+        assert_eq!(
+            negotiate_capabilities(&requested),
+            vec!["input_ack", "matchmaking"]
+        );
+        assert!(negotiate_capabilities(&[]).is_empty());
+    }
+
     #[test]
     fn parse_lobby_start_message() {
         let parsed = parse_client_message(
@@ -173,13 +448,43 @@ mod tests {
                 difficulty,
                 ai_player_count,
                 time_limit_minutes,
+                ai_skill,
+                ai_skills,
             } => {
                 assert_eq!(difficulty as Option<Difficulty>, Some(Difficulty::Hard));
                 assert_eq!(ai_player_count, Some(5));
-                assert_eq!(time_limit_minutes, Some(3));
+                assert_eq!(time_limit_minutes, Some(Some(3)));
+                assert_eq!(ai_skill, None);
+                assert_eq!(ai_skills, None);
+            }
+            _ => panic!("expected lobby_start message"),
+        }
+    }
+
+    #[test]
+    fn parse_lobby_start_ai_skills() {
+        let parsed = parse_client_message(
+            r#"{"type":"lobby_start","aiPlayerCount":3,"aiSkill":"expert","aiSkills":["beginner","intermediate"]}"#,
+        )
+        .expect("lobby start message should parse");
+        match parsed {
+            ParsedClientMessage::LobbyStart {
+                ai_skill,
+                ai_skills,
+                ..
+            } => {
+                assert_eq!(ai_skill, Some(AiSkill::Expert));
+                assert_eq!(
+                    ai_skills,
+                    Some(vec![AiSkill::Beginner, AiSkill::Intermediate])
+                );
             }
             _ => panic!("expected lobby_start message"),
         }
+        assert!(parse_client_message(r#"{"type":"lobby_start","aiSkill":"godlike"}"#).is_none());
+        assert!(
+            parse_client_message(r#"{"type":"lobby_start","aiSkills":["expert",1]}"#).is_none()
+        );
     }
 
     #[test]
@@ -200,6 +505,32 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_input_sequence_and_client_time() {
+        let parsed =
+            parse_client_message(r#"{"type":"input","dir":"up","seq":42,"clientTimeMs":1234.75}"#);
+        assert!(matches!(
+            parsed,
+            Some(ParsedClientMessage::Input {
+                dir: Some(Direction::Up),
+                seq: Some(42),
+                client_time_ms: Some(1_234),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"input","awaken":true}"#),
+            Some(ParsedClientMessage::Input {
+                seq: None,
+                client_time_ms: None,
+                ..
+            })
+        ));
+        assert!(parse_client_message(r#"{"type":"input","seq":-1}"#).is_none());
+        assert!(parse_client_message(r#"{"type":"input","seq":"1"}"#).is_none());
+        assert!(parse_client_message(r#"{"type":"input","clientTimeMs":-5}"#).is_none());
+    }
+
     #[test]
     fn parse_ping_requires_finite_number() {
         let parsed = parse_client_message(r#"{"type":"ping","t":12.5}"#);
@@ -212,9 +543,122 @@ mod tests {
         assert!(matches!(
             parsed,
             Some(ParsedClientMessage::PlacePing {
-                kind: PingType::Help
+                kind: PingType::Help,
+                target: None,
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_place_ping_with_target() {
+        let parsed = parse_client_message(r#"{"type":"place_ping","kind":"gate","x":4,"y":9}"#);
+        assert!(matches!(
+            parsed,
+            Some(ParsedClientMessage::PlacePing {
+                kind: PingType::Gate,
+                target: Some(PingTarget::Cell(Vec2 { x: 4, y: 9 })),
+            })
+        ));
+
+        let parsed =
+            parse_client_message(r#"{"type":"place_ping","kind":"boss","targetId":"ghost_3"}"#);
+        assert!(matches!(
+            parsed,
+            Some(ParsedClientMessage::PlacePing {
+                kind: PingType::Boss,
+                target: Some(PingTarget::Entity(ref id)),
+            }) if id == "ghost_3"
+        ));
+
+        for raw in [
+            r#"{"type":"place_ping","kind":"focus","x":4}"#,
+            r#"{"type":"place_ping","kind":"focus","x":4,"y":1,"targetId":"p_1"}"#,
+            r#"{"type":"place_ping","kind":"focus","targetId":""}"#,
+            r#"{"type":"place_ping","kind":"focus","x":"4","y":1}"#,
+        ] {
+            assert!(parse_client_message(raw).is_none(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn parse_chat_and_moderation_messages() {
+        assert!(matches!(
+            parse_client_message(r#"{"type":"chat","text":"hi"}"#),
+            Some(ParsedClientMessage::Chat {
+                channel: ChatChannel::Lobby,
+                ref text,
+            }) if text == "hi"
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"chat","channel":"team","text":"go"}"#),
+            Some(ParsedClientMessage::Chat {
+                channel: ChatChannel::Team,
+                ..
+            })
+        ));
+        assert!(parse_client_message(r#"{"type":"chat","channel":"all","text":"x"}"#).is_none());
+        assert!(parse_client_message(r#"{"type":"chat"}"#).is_none());
+        assert!(matches!(
+            parse_client_message(r#"{"type":"chat_mute","playerId":"player_2"}"#),
+            Some(ParsedClientMessage::ChatMute { muted: true, .. })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"chat_mute","playerId":"player_2","muted":false}"#),
+            Some(ParsedClientMessage::ChatMute { muted: false, .. })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"kick","playerId":"player_2"}"#),
+            Some(ParsedClientMessage::Kick { ref player_id }) if player_id == "player_2"
+        ));
+        assert!(parse_client_message(r#"{"type":"kick"}"#).is_none());
+    }
+
+    #[test]
+    fn parse_lobby_commands() {
+        match parse_client_message(
+            r#"{"type":"lobby_settings","difficulty":"hard","aiPlayerCount":4,"requireReady":true}"#,
+        ) {
+            Some(ParsedClientMessage::LobbySettings {
+                difficulty,
+                ai_player_count,
+                time_limit_minutes,
+                ai_skill,
+                require_ready,
+            }) => {
+                assert_eq!(difficulty, Some(Difficulty::Hard));
+                assert_eq!(ai_player_count, Some(4));
+                assert_eq!(time_limit_minutes, None);
+                assert_eq!(ai_skill, None);
+                assert_eq!(require_ready, Some(true));
+            }
+            other => panic!("unexpected parse result: {other:?}"),
+        }
+        assert!(matches!(
+            parse_client_message(r#"{"type":"lobby_settings","timeLimitMinutes":null}"#),
+            Some(ParsedClientMessage::LobbySettings {
+                time_limit_minutes: Some(None),
+                ..
             })
         ));
+        assert!(
+            parse_client_message(r#"{"type":"lobby_settings","difficulty":"extreme"}"#).is_none()
+        );
+        assert!(
+            parse_client_message(r#"{"type":"lobby_settings","requireReady":"yes"}"#).is_none()
+        );
+        assert!(matches!(
+            parse_client_message(r#"{"type":"ready"}"#),
+            Some(ParsedClientMessage::Ready { ready: true })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"ready","ready":false}"#),
+            Some(ParsedClientMessage::Ready { ready: false })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"host_transfer","playerId":"player_3"}"#),
+            Some(ParsedClientMessage::HostTransfer { ref player_id }) if player_id == "player_3"
+        ));
+        assert!(parse_client_message(r#"{"type":"host_transfer"}"#).is_none());
     }
 
     #[test]
@@ -230,7 +674,7 @@ mod tests {
                 ..
             } => {
                 assert_eq!(ai_player_count, Some(1));
-                assert_eq!(time_limit_minutes, Some(-2));
+                assert_eq!(time_limit_minutes, Some(Some(-2)));
             }
             _ => panic!("expected lobby_start message"),
         }
@@ -253,4 +697,30 @@ mod tests {
             parse_client_message(r#"{"type":"lobby_start","aiPlayerCount":9.223372036854776e18}"#);
         assert!(parsed.is_none());
     }
+
+    #[test]
+    fn parse_queue_messages() {
+        let parsed =
+            parse_client_message(r#"{"type":"queue_join","difficulty":"hard","partySize":3}"#);
+        assert!(matches!(
+            parsed,
+            Some(ParsedClientMessage::QueueJoin {
+                difficulty: Some(Difficulty::Hard),
+                party_size: Some(3),
+            })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"queue_join"}"#),
+            Some(ParsedClientMessage::QueueJoin {
+                difficulty: None,
+                party_size: None,
+            })
+        ));
+        assert!(matches!(
+            parse_client_message(r#"{"type":"queue_leave"}"#),
+            Some(ParsedClientMessage::QueueLeave)
+        ));
+        assert!(parse_client_message(r#"{"type":"queue_join","partySize":"4"}"#).is_none());
+        assert!(parse_client_message(r#"{"type":"queue_join","difficulty":"easy"}"#).is_none());
+    }
 }
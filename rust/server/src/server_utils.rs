@@ -1,3 +1,28 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+// Bounds applied to host-provided lobby settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LobbyLimits {
+    pub max_ai_players: usize,
+    pub min_time_limit_minutes: u64,
+    pub max_time_limit_minutes: u64,
+}
+
+impl Default for LobbyLimits {
+    fn default() -> Self {
+        Self {
+            max_ai_players: 100,
+            min_time_limit_minutes: 1,
+            max_time_limit_minutes: 10,
+        }
+    }
+}
+
 pub fn sanitize_name(value: &str) -> String {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -6,6 +31,17 @@ pub fn sanitize_name(value: &str) -> String {
     trimmed.chars().take(16).collect()
 }
 
+pub fn sanitize_chat_text(value: &str, max_len: usize) -> Option<String> {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(max_len)
+        .collect();
+    let cleaned = cleaned.trim_end().to_string();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
 pub fn is_supported_room(raw: Option<&str>) -> bool {
     match raw {
         None => true,
@@ -16,12 +52,29 @@ pub fn is_supported_room(raw: Option<&str>) -> bool {
     }
 }
 
-pub fn normalize_ai_count(value: Option<i64>) -> usize {
-    value.unwrap_or(0).clamp(0, 100) as usize
+pub fn normalize_ai_count(value: Option<i64>, limits: &LobbyLimits) -> usize {
+    let max = i64::try_from(limits.max_ai_players).unwrap_or(i64::MAX);
+    value.unwrap_or(0).clamp(0, max) as usize
 }
 
-pub fn normalize_time_limit_ms(value: Option<i64>) -> Option<u64> {
-    value.map(|minutes| minutes.clamp(1, 10) as u64 * 60_000)
+pub fn normalize_time_limit_ms(value: Option<i64>, limits: &LobbyLimits) -> Option<u64> {
+    value.map(|minutes| {
+        let minutes = u64::try_from(minutes.max(0)).unwrap_or(0);
+        minutes.clamp(limits.min_time_limit_minutes, limits.max_time_limit_minutes) * 60_000
+    })
+}
+
+// Writes to `<path>.tmp` and renames it into place, so a process killed mid-write leaves the
+// previous file intact. Missing parent directories are created.
+pub fn write_file_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
 }
 
 pub fn player_order_key(player_id: &str) -> u64 {
@@ -36,10 +89,52 @@ pub fn parse_ranking_limit(raw: Option<&str>) -> Option<usize> {
     raw.and_then(|value| value.parse::<usize>().ok())
 }
 
+// Compares a client-supplied secret without stopping at the first differing byte.
+pub fn secret_matches(provided: &str, expected: &str) -> bool {
+    let (provided, expected) = (provided.as_bytes(), expected.as_bytes());
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// Checks an `Authorization: Bearer <token>` header value.
+pub fn bearer_token_matches(header: Option<&str>, expected: &str) -> bool {
+    header
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| secret_matches(provided.trim(), expected))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn bearer_token_requires_exact_match() {
+        let token = "s3cret-admin-token";
+        assert!(bearer_token_matches(
+            Some("Bearer s3cret-admin-token"),
+            token
+        ));
+        assert!(!bearer_token_matches(
+            Some("Bearer s3cret-admin-tokeN"),
+            token
+        ));
+        assert!(!bearer_token_matches(Some("Bearer s3cret"), token));
+        assert!(!bearer_token_matches(Some("s3cret-admin-token"), token));
+        assert!(!bearer_token_matches(None, token));
+    }
+
+    #[test]
+    fn secret_requires_exact_match() {
+        assert!(secret_matches("bot-token", "bot-token"));
+        assert!(!secret_matches("bot-tokeN", "bot-token"));
+        assert!(!secret_matches("bot", "bot-token"));
+        assert!(!secret_matches("", "bot-token"));
+    }
+
     #[test]
     fn player_order_key_uses_numeric_suffix() {
         assert!(player_order_key("player_2") < player_order_key("player_10"));
@@ -71,19 +166,48 @@ mod tests {
         assert_eq!(sanitize_name("12345678901234567890"), "1234567890123456");
     }
 
+    #[test]
+    fn sanitize_chat_text_trims_strips_controls_and_limits_length() {
+        assert_eq!(sanitize_chat_text("   ", 10), None);
+        assert_eq!(sanitize_chat_text("\u{7}", 10), None);
+        assert_eq!(
+            sanitize_chat_text(" hi\nthere ", 10).as_deref(),
+            Some("hithere")
+        );
+        assert_eq!(
+            sanitize_chat_text("あいうえおかきくけこさ", 10).as_deref(),
+            Some("あいうえおかきくけこ")
+        );
+        assert_eq!(sanitize_chat_text("abc    def", 5).as_deref(), Some("abc"));
+    }
+
     #[test]
     fn normalize_ai_count_clamps_range() {
-        assert_eq!(normalize_ai_count(None), 0);
-        assert_eq!(normalize_ai_count(Some(-10)), 0);
-        assert_eq!(normalize_ai_count(Some(3)), 3);
-        assert_eq!(normalize_ai_count(Some(999)), 100);
+        let limits = LobbyLimits::default();
+        assert_eq!(normalize_ai_count(None, &limits), 0);
+        assert_eq!(normalize_ai_count(Some(-10), &limits), 0);
+        assert_eq!(normalize_ai_count(Some(3), &limits), 3);
+        assert_eq!(normalize_ai_count(Some(999), &limits), 100);
+        let limits = LobbyLimits {
+            max_ai_players: 8,
+            ..LobbyLimits::default()
+        };
+        assert_eq!(normalize_ai_count(Some(999), &limits), 8);
     }
 
     #[test]
     fn normalize_time_limit_ms_clamps_minutes() {
-        assert_eq!(normalize_time_limit_ms(None), None);
-        assert_eq!(normalize_time_limit_ms(Some(-10)), Some(60_000));
-        assert_eq!(normalize_time_limit_ms(Some(3)), Some(180_000));
-        assert_eq!(normalize_time_limit_ms(Some(999)), Some(600_000));
+        let limits = LobbyLimits::default();
+        assert_eq!(normalize_time_limit_ms(None, &limits), None);
+        assert_eq!(normalize_time_limit_ms(Some(-10), &limits), Some(60_000));
+        assert_eq!(normalize_time_limit_ms(Some(3), &limits), Some(180_000));
+        assert_eq!(normalize_time_limit_ms(Some(999), &limits), Some(600_000));
+        let limits = LobbyLimits {
+            min_time_limit_minutes: 5,
+            max_time_limit_minutes: 30,
+            ..LobbyLimits::default()
+        };
+        assert_eq!(normalize_time_limit_ms(Some(3), &limits), Some(300_000));
+        assert_eq!(normalize_time_limit_ms(Some(20), &limits), Some(1_200_000));
     }
 }
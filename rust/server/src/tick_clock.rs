@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::TICK_MS;
+
+const DURATION_SAMPLES: usize = 256;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TickClockOptions {
+    // The simulation step is fixed at `TICK_MS`; not configurable.
+    #[serde(skip)]
+    pub tick_ms: u64,
+    pub max_catch_up_steps: u32,
+    pub overrun_log_interval_ms: u64,
+}
+
+impl Default for TickClockOptions {
+    fn default() -> Self {
+        Self {
+            tick_ms: TICK_MS,
+            max_catch_up_steps: 4,
+            overrun_log_interval_ms: 5_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickPlan {
+    pub steps: u32,
+    pub dropped_steps: u32,
+}
+
+// Fixed-timestep accumulator: wall time is converted into whole simulation steps so game time
+// keeps pace with the clock. A long stall is only caught up to `max_catch_up_steps`; the rest
+// is dropped rather than replayed in one burst.
+pub struct TickClock {
+    options: TickClockOptions,
+    accumulator_ms: u64,
+    last_ms: u64,
+}
+
+impl TickClock {
+    pub fn new(options: TickClockOptions, now_ms: u64) -> Self {
+        Self {
+            options,
+            accumulator_ms: 0,
+            last_ms: now_ms,
+        }
+    }
+
+    pub fn advance(&mut self, now_ms: u64) -> TickPlan {
+        let tick_ms = self.options.tick_ms.max(1);
+        self.accumulator_ms += now_ms.saturating_sub(self.last_ms);
+        self.last_ms = self.last_ms.max(now_ms);
+
+        let due = self.accumulator_ms / tick_ms;
+        let steps = due.min(u64::from(self.options.max_catch_up_steps.max(1)));
+        self.accumulator_ms -= due * tick_ms;
+        TickPlan {
+            steps: steps as u32,
+            dropped_steps: (due - steps) as u32,
+        }
+    }
+
+    pub fn until_next_step_ms(&self) -> u64 {
+        self.options
+            .tick_ms
+            .max(1)
+            .saturating_sub(self.accumulator_ms)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TickStats {
+    #[serde(rename = "tickMs")]
+    pub tick_ms: u64,
+    pub ticks: u64,
+    pub steps: u64,
+    #[serde(rename = "catchUpSteps")]
+    pub catch_up_steps: u64,
+    #[serde(rename = "droppedSteps")]
+    pub dropped_steps: u64,
+    pub overruns: u64,
+    #[serde(rename = "lastDurationMs")]
+    pub last_duration_ms: f64,
+    #[serde(rename = "avgDurationMs")]
+    pub avg_duration_ms: f64,
+    #[serde(rename = "p95DurationMs")]
+    pub p95_duration_ms: f64,
+    #[serde(rename = "maxDurationMs")]
+    pub max_duration_ms: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverrunReport {
+    pub overruns: u64,
+    pub dropped_steps: u64,
+    pub worst_duration_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct TickRecorder {
+    stats: TickStats,
+    total_duration_us: u64,
+    recent_us: VecDeque<u64>,
+    pending: Option<OverrunReport>,
+    last_report_ms: Option<u64>,
+}
+
+// Shared between the tick loop and the stats endpoint.
+#[derive(Debug, Default)]
+pub struct TickCounters {
+    recorder: Mutex<TickRecorder>,
+}
+
+impl TickCounters {
+    pub fn snapshot(&self) -> TickStats {
+        let Ok(recorder) = self.recorder.lock() else {
+            return TickStats::default();
+        };
+        let mut stats = recorder.stats.clone();
+        if stats.ticks > 0 {
+            stats.avg_duration_ms =
+                recorder.total_duration_us as f64 / stats.ticks as f64 / 1_000.0;
+        }
+        let mut recent: Vec<u64> = recorder.recent_us.iter().copied().collect();
+        recent.sort_unstable();
+        if let Some(p95) = recent.get((recent.len() * 95).div_ceil(100).saturating_sub(1)) {
+            stats.p95_duration_ms = *p95 as f64 / 1_000.0;
+        }
+        stats
+    }
+
+    // Overruns are aggregated and handed back at most once per `overrun_log_interval_ms`
+    // so a struggling server does not also flood its log.
+    pub fn record(
+        &self,
+        options: &TickClockOptions,
+        plan: TickPlan,
+        duration_us: u64,
+        now_ms: u64,
+    ) -> Option<OverrunReport> {
+        let Ok(mut recorder) = self.recorder.lock() else {
+            return None;
+        };
+        let duration_ms = duration_us as f64 / 1_000.0;
+        let overrun = duration_us > options.tick_ms * 1_000;
+
+        recorder.total_duration_us += duration_us;
+        recorder.recent_us.push_back(duration_us);
+        while recorder.recent_us.len() > DURATION_SAMPLES {
+            recorder.recent_us.pop_front();
+        }
+        let stats = &mut recorder.stats;
+        stats.tick_ms = options.tick_ms;
+        stats.ticks += 1;
+        stats.steps += u64::from(plan.steps);
+        stats.catch_up_steps += u64::from(plan.steps.saturating_sub(1));
+        stats.dropped_steps += u64::from(plan.dropped_steps);
+        stats.overruns += u64::from(overrun);
+        stats.last_duration_ms = duration_ms;
+        stats.max_duration_ms = stats.max_duration_ms.max(duration_ms);
+
+        if overrun || plan.dropped_steps > 0 {
+            let pending = recorder.pending.get_or_insert(OverrunReport {
+                overruns: 0,
+                dropped_steps: 0,
+                worst_duration_ms: 0.0,
+            });
+            pending.overruns += u64::from(overrun);
+            pending.dropped_steps += u64::from(plan.dropped_steps);
+            pending.worst_duration_ms = pending.worst_duration_ms.max(duration_ms);
+        }
+        let due = recorder
+            .last_report_ms
+            .is_none_or(|last| now_ms.saturating_sub(last) >= options.overrun_log_interval_ms);
+        if !due {
+            return None;
+        }
+        let report = recorder.pending.take()?;
+        recorder.last_report_ms = Some(now_ms);
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> TickClockOptions {
+        TickClockOptions {
+            tick_ms: 50,
+            max_catch_up_steps: 3,
+            overrun_log_interval_ms: 1_000,
+        }
+    }
+
+    #[test]
+    fn accumulator_keeps_game_time_in_step_with_wall_time() {
+        let mut clock = TickClock::new(options(), 1_000);
+        assert_eq!(clock.advance(1_030).steps, 0);
+        assert_eq!(clock.until_next_step_ms(), 20);
+        assert_eq!(clock.advance(1_060).steps, 1);
+        // A late wake-up is made up on the next advance instead of being lost.
+        assert_eq!(clock.advance(1_170).steps, 2);
+        assert_eq!(clock.until_next_step_ms(), 30);
+    }
+
+    #[test]
+    fn catch_up_is_capped_and_the_rest_dropped() {
+        let mut clock = TickClock::new(options(), 0);
+        assert_eq!(
+            clock.advance(520),
+            TickPlan {
+                steps: 3,
+                dropped_steps: 7,
+            }
+        );
+        assert_eq!(clock.until_next_step_ms(), 30);
+        assert_eq!(clock.advance(550).steps, 1);
+    }
+
+    #[test]
+    fn stats_track_durations_and_overruns() {
+        let counters = TickCounters::default();
+        let options = options();
+        let one = TickPlan {
+            steps: 1,
+            dropped_steps: 0,
+        };
+        assert_eq!(counters.record(&options, one, 10_000, 0), None);
+        let catch_up = TickPlan {
+            steps: 3,
+            dropped_steps: 2,
+        };
+        assert_eq!(
+            counters.record(&options, catch_up, 80_000, 100),
+            Some(OverrunReport {
+                overruns: 1,
+                dropped_steps: 2,
+                worst_duration_ms: 80.0,
+            })
+        );
+        // Inside the log interval the next overrun is held back and reported later.
+        assert_eq!(counters.record(&options, one, 60_000, 500), None);
+        assert_eq!(
+            counters.record(&options, one, 30_000, 1_100),
+            Some(OverrunReport {
+                overruns: 1,
+                dropped_steps: 0,
+                worst_duration_ms: 60.0,
+            })
+        );
+
+        let stats = counters.snapshot();
+        assert_eq!((stats.ticks, stats.steps), (4, 6));
+        assert_eq!((stats.catch_up_steps, stats.dropped_steps), (2, 2));
+        assert_eq!(stats.overruns, 2);
+        assert_eq!(stats.avg_duration_ms, 45.0);
+        assert_eq!(stats.max_duration_ms, 80.0);
+        assert_eq!(stats.p95_duration_ms, 80.0);
+        assert_eq!(stats.last_duration_ms, 30.0);
+    }
+}
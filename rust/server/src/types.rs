@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
     Up,
@@ -23,7 +23,7 @@ impl Direction {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PlayerState {
     Normal,
@@ -31,7 +31,7 @@ pub enum PlayerState {
     Down,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GhostType {
     Random,
@@ -42,7 +42,7 @@ pub enum GhostType {
     Boss,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SectorType {
     Normal,
@@ -53,7 +53,7 @@ pub enum SectorType {
     Nest,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FruitType {
     Cherry,
@@ -64,7 +64,7 @@ pub enum FruitType {
     Grape,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Difficulty {
     Casual,
@@ -85,12 +85,52 @@ impl Difficulty {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiSkill {
+    Beginner,
+    Intermediate,
+    #[default]
+    Advanced,
+    Expert,
+}
+
+impl AiSkill {
+    pub const ALL: [AiSkill; 4] = [
+        AiSkill::Beginner,
+        AiSkill::Intermediate,
+        AiSkill::Advanced,
+        AiSkill::Expert,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "beginner" => Some(Self::Beginner),
+            "intermediate" => Some(Self::Intermediate),
+            "advanced" => Some(Self::Advanced),
+            "expert" => Some(Self::Expert),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Beginner => "beginner",
+            Self::Intermediate => "intermediate",
+            Self::Advanced => "advanced",
+            Self::Expert => "expert",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PingType {
     Focus,
     Danger,
     Help,
+    Gate,
+    Boss,
 }
 
 impl PingType {
@@ -99,27 +139,64 @@ impl PingType {
             "focus" => Some(Self::Focus),
             "danger" => Some(Self::Danger),
             "help" => Some(Self::Help),
+            "gate" => Some(Self::Gate),
+            "boss" => Some(Self::Boss),
             _ => None,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatChannel {
+    #[default]
+    Lobby,
+    Team,
+    Spectator,
+}
+
+impl ChatChannel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lobby" => Some(Self::Lobby),
+            "team" => Some(Self::Team),
+            "spectator" => Some(Self::Spectator),
+            _ => None,
+        }
+    }
+
+    pub fn is_visible_to(self, spectator: bool) -> bool {
+        match self {
+            Self::Lobby => true,
+            Self::Team => !spectator,
+            Self::Spectator => spectator,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PingTarget {
+    Cell(Vec2),
+    Entity(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GameOverReason {
     Victory,
     Timeout,
     AllDown,
     Collapse,
+    Shutdown,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GateState {
     pub id: String,
     pub a: Vec2,
@@ -132,7 +209,7 @@ pub struct GateState {
     pub permanent: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SectorState {
     pub id: usize,
     pub row: i32,
@@ -173,7 +250,7 @@ pub struct WorldInit {
     pub power_pellets: Vec<PowerPelletView>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameConfig {
     #[serde(rename = "tickRate")]
     pub tick_rate: u32,
@@ -192,7 +269,7 @@ pub struct GameConfig {
     pub difficulty: Difficulty,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlayerView {
     pub id: String,
     pub name: String,
@@ -207,15 +284,20 @@ pub struct PlayerView {
     pub score: i32,
     pub connected: bool,
     pub ai: bool,
+    pub bot: bool,
     #[serde(rename = "speedBuffUntil")]
     pub speed_buff_until: u64,
     #[serde(rename = "powerUntil")]
     pub power_until: u64,
     #[serde(rename = "downSince")]
     pub down_since: Option<u64>,
+    #[serde(rename = "lastInputSeq", skip_serializing_if = "Option::is_none")]
+    pub last_input_seq: Option<u64>,
+    #[serde(rename = "lastInputClientMs", skip_serializing_if = "Option::is_none")]
+    pub last_input_client_ms: Option<u64>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GhostView {
     pub id: String,
     pub x: i32,
@@ -228,7 +310,7 @@ pub struct GhostView {
     pub stunned_until: u64,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FruitView {
     pub id: String,
     #[serde(rename = "type")]
@@ -239,7 +321,7 @@ pub struct FruitView {
     pub spawned_at: u64,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PingView {
     pub id: String,
     #[serde(rename = "ownerId")]
@@ -249,6 +331,8 @@ pub struct PingView {
     pub x: i32,
     pub y: i32,
     pub kind: PingType,
+    #[serde(rename = "targetId", skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
     #[serde(rename = "createdAtMs")]
     pub created_at_ms: u64,
     #[serde(rename = "expiresAtMs")]
@@ -256,13 +340,26 @@ pub struct PingView {
 }
 
 #[derive(Clone, Debug, Serialize)]
+pub struct ChatMessageView {
+    pub id: String,
+    #[serde(rename = "senderId")]
+    pub sender_id: String,
+    #[serde(rename = "senderName")]
+    pub sender_name: String,
+    pub channel: ChatChannel,
+    pub text: String,
+    #[serde(rename = "sentAtMs")]
+    pub sent_at_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimelineEvent {
     #[serde(rename = "atMs")]
     pub at_ms: u64,
     pub label: String,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RuntimeEvent {
     DotEaten {
@@ -326,6 +423,9 @@ pub enum RuntimeEvent {
 #[derive(Clone, Debug, Serialize)]
 pub struct Snapshot {
     pub tick: u64,
+    // Counts broadcast snapshots, so receivers can tell dropped frames from catch-up steps
+    // (`tick` may advance by several fixed steps per frame).
+    pub frame: u64,
     #[serde(rename = "nowMs")]
     pub now_ms: u64,
     #[serde(rename = "timeLeftMs")]
@@ -342,6 +442,25 @@ pub struct Snapshot {
     pub timeline: Vec<TimelineEvent>,
 }
 
+#[derive(Clone, Debug, Serialize)]
+pub struct BotObservation {
+    pub tick: u64,
+    #[serde(rename = "nowMs")]
+    pub now_ms: u64,
+    #[serde(rename = "timeLeftMs")]
+    pub time_left_ms: u64,
+    #[serde(rename = "captureRatio")]
+    pub capture_ratio: f32,
+    #[serde(rename = "self")]
+    pub me: PlayerView,
+    pub origin: Vec2,
+    pub radius: i32,
+    pub grid: Vec<String>,
+    pub ghosts: Vec<GhostView>,
+    pub players: Vec<PlayerView>,
+    pub sectors: Vec<SectorState>,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ScoreEntry {
     #[serde(rename = "playerId")]
@@ -352,6 +471,7 @@ pub struct ScoreEntry {
     pub ghosts: i32,
     pub rescues: i32,
     pub captures: i32,
+    pub bot: bool,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -422,4 +542,5 @@ pub struct StartPlayer {
     pub name: String,
     pub reconnect_token: String,
     pub connected: bool,
+    pub bot: bool,
 }
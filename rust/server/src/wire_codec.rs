@@ -0,0 +1,66 @@
+use serde::Serialize;
+use serde_json::Value;
+
+pub const BINARY_CAPABILITY: &str = "binary";
+
+// Only the large per-match payloads switch encoding; everything else stays JSON.
+pub fn is_binary_message(message: &Value) -> bool {
+    matches!(
+        message.get("type").and_then(Value::as_str),
+        Some("state" | "game_init")
+    )
+}
+
+// MessagePack with field names, so clients decode it into the same shape as the JSON message.
+pub fn encode_binary<T: Serialize + ?Sized>(message: &T) -> Option<Vec<u8>> {
+    rmp_serde::to_vec_named(message).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::engine::{GameEngine, GameEngineOptions};
+    use crate::types::{Difficulty, StartPlayer};
+
+    fn state_message(players: usize) -> Value {
+        let start_players = (0..players)
+            .map(|idx| StartPlayer {
+                id: format!("ai_{}", idx + 1),
+                name: format!("AI-{:02}", idx + 1),
+                reconnect_token: format!("token_{}", idx + 1),
+                connected: false,
+                bot: false,
+            })
+            .collect();
+        let mut engine = GameEngine::new(
+            start_players,
+            Difficulty::Normal,
+            9_101,
+            GameEngineOptions::default(),
+        );
+        engine.step(50);
+        json!({
+            "type": "state",
+            "snapshot": engine.build_snapshot(true),
+        })
+    }
+
+    #[test]
+    fn only_state_and_game_init_are_binary() {
+        assert!(is_binary_message(&json!({ "type": "state" })));
+        assert!(is_binary_message(&json!({ "type": "game_init" })));
+        assert!(!is_binary_message(&json!({ "type": "lobby" })));
+        assert!(!is_binary_message(&json!({ "snapshot": {} })));
+    }
+
+    #[test]
+    fn binary_state_round_trips_and_is_smaller() {
+        let message = state_message(12);
+        let binary = encode_binary(&message).expect("state should encode");
+        let decoded: Value = rmp_serde::from_slice(&binary).expect("state should decode");
+        assert_eq!(decoded, message);
+        assert!(binary.len() < message.to_string().len());
+    }
+}
@@ -1,10 +1,12 @@
 use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 
+use serde::{Deserialize, Serialize};
+
 use crate::constants::{get_map_side_by_player_count, SECTOR_SIZE};
 use crate::rng::Rng;
 use crate::types::{GateState, SectorState, SectorType, Vec2, WorldInit};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PowerPelletInternal {
     pub key: String,
     pub x: i32,
@@ -13,7 +15,7 @@ pub struct PowerPelletInternal {
     pub respawn_at: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SectorInternal {
     pub view: SectorState,
     pub floor_cells: Vec<Vec2>,
@@ -22,7 +24,7 @@ pub struct SectorInternal {
     pub regen_accumulator: f32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GeneratedWorld {
     pub width: i32,
     pub height: i32,
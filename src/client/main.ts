@@ -1,5 +1,7 @@
 import type {
   AwardEntry,
+  ChatChannel,
+  ChatMessageView,
   ClientMessage,
   Direction,
   Difficulty,
@@ -8,10 +10,12 @@ import type {
   GameSummary,
   GhostView,
   LobbyPlayer,
+  LobbySettings,
   PersistentRankingEntry,
   PingType,
   PingView,
   PlayerView,
+  QueueStatus,
   RankingResponse,
   RuntimeEvent,
   ServerMessage,
@@ -23,6 +27,7 @@ import {
   PLAYER_BASE_SPEED,
   PLAYER_CAPTURED_SPEED_MULTIPLIER,
 } from '../shared/constants.js';
+import { decodeMsgpack } from './msgpack.js';
 import { findReplayFrameIndex, parseReplayLog, type ReplayFrame, type ReplayLog } from './replay_parser.js';
 import { cloneSnapshot, cloneWorld, normalizeSummary, normalizeSnapshot } from './replay_model.js';
 
@@ -40,6 +45,12 @@ const dotSet = new Set<string>();
 const pelletMap = new Map<string, { x: number; y: number; active: boolean }>();
 const SOUND_VOLUME_KEY = 'mmo-packman-sound-volume';
 const SOUND_MUTED_KEY = 'mmo-packman-sound-muted';
+const PROTOCOL_VERSION = 2;
+const KICKED_CLOSE_CODE = 4003;
+const PROTOCOL_TOO_OLD_CLOSE_CODE = 4010;
+// `?wire=json` keeps every frame as JSON for debugging in devtools.
+const PREFER_JSON_WIRE = new URLSearchParams(window.location.search).get('wire') === 'json';
+const CLIENT_CAPABILITIES = ['input_ack', 'entity_pings', 'chat', 'matchmaking', ...(PREFER_JSON_WIRE ? [] : ['binary'])];
 
 interface InterpolationState {
   fromX: number;
@@ -106,6 +117,8 @@ let config: GameConfig | null = null;
 let snapshot: Snapshot | null = null;
 let summary: GameSummary | null = null;
 let lobbyMessage = '';
+let queueStatus: QueueStatus | null = null;
+let inputSeq = 0;
 let logs: string[] = [];
 let rankingEntries: PersistentRankingEntry[] = [];
 let rankingFetchError = '';
@@ -152,6 +165,7 @@ function start(): void {
 function connect(): void {
   const url = wsUrl();
   ws = new WebSocket(url);
+  ws.binaryType = 'arraybuffer';
 
   ws.addEventListener('open', () => {
     currentDir = 'none';
@@ -159,7 +173,7 @@ function connect(): void {
   });
 
   ws.addEventListener('message', (event) => {
-    const msg = safeParse(event.data.toString());
+    const msg = event.data instanceof ArrayBuffer ? safeDecodeBinary(event.data) : safeParse(event.data.toString());
     if (!msg) {
       return;
     }
@@ -172,6 +186,14 @@ function connect(): void {
       pushLog('このタブの接続は他の接続に置き換えられました');
       return;
     }
+    if (event.code === KICKED_CLOSE_CODE) {
+      pushLog('ロビーからキックされました');
+      return;
+    }
+    if (event.code === PROTOCOL_TOO_OLD_CLOSE_CODE) {
+      pushLog('クライアントが古いため接続できません。ページを再読み込みしてください');
+      return;
+    }
 
     if (reconnectTimer) {
       window.clearTimeout(reconnectTimer);
@@ -189,6 +211,8 @@ function sendHello(): void {
     reconnectToken: reconnectToken || undefined,
     spectator: preferSpectator,
     roomId,
+    protocolVersion: PROTOCOL_VERSION,
+    capabilities: CLIENT_CAPABILITIES,
   };
   send(hello);
 }
@@ -205,6 +229,14 @@ function wsUrl(): string {
   return `${proto}//${window.location.hostname}${port ? `:${port}` : ''}/ws`;
 }
 
+function safeDecodeBinary(raw: ArrayBuffer): ServerMessage | null {
+  try {
+    return decodeMsgpack(new Uint8Array(raw)) as ServerMessage;
+  } catch {
+    return null;
+  }
+}
+
 function safeParse(raw: string): ServerMessage | null {
   try {
     return JSON.parse(raw) as ServerMessage;
@@ -220,6 +252,9 @@ function handleServerMessage(message: ServerMessage): void {
     reconnectToken = message.reconnectToken;
     isSpectator = message.isSpectator;
     localStorage.setItem('mmo-packman-token', reconnectToken);
+    for (const chat of message.chatHistory ?? []) {
+      pushLog(formatChatLine(chat));
+    }
     updateTouchControlsVisibility();
     updateStatusPanels();
     return;
@@ -233,7 +268,7 @@ function handleServerMessage(message: ServerMessage): void {
     if (inRunningMatch) {
       lobby.classList.add('hidden');
     } else {
-      renderLobby(message.players, message.running, message.canStart, message.spectatorCount);
+      renderLobby(message.players, message.running, message.canStart, message.spectatorCount, message.settings);
     }
     updateStatusPanels();
     return;
@@ -250,6 +285,9 @@ function handleServerMessage(message: ServerMessage): void {
     currentMatchStartedAtMs = message.startedAtMs;
     currentDir = 'none';
     isSpectator = message.isSpectator;
+    if (!isSpectator) {
+      queueStatus = null;
+    }
     summary = null;
     snapshot = null;
     logs = [];
@@ -308,6 +346,23 @@ function handleServerMessage(message: ServerMessage): void {
     return;
   }
 
+  if (message.type === 'queue_status') {
+    queueStatus = message.queued ? message : null;
+    updateQueueStatusView();
+    return;
+  }
+
+  if (message.type === 'chat') {
+    pushLog(formatChatLine(message.message));
+    updateStatusPanels();
+    return;
+  }
+
+  if (message.type === 'server_shutdown') {
+    pushLog(message.message);
+    return;
+  }
+
   if (message.type === 'error') {
     pushLog(`ERROR: ${message.message}`);
   }
@@ -464,8 +519,18 @@ function renderRankingBoard(): string {
   `;
 }
 
-function renderLobby(players: LobbyPlayer[], running: boolean, canStart: boolean, spectatorCount: number): void {
+function renderLobby(
+  players: LobbyPlayer[],
+  running: boolean,
+  canStart: boolean,
+  spectatorCount: number,
+  settings: LobbySettings,
+): void {
   lobby.classList.remove('hidden');
+  const settingsLocked = !isHost || running;
+  const shownAiCount = isHost ? requestedAiCount : settings.aiPlayerCount;
+  const shownMinutes = isHost ? requestedTestMinutes : (settings.timeLimitMinutes ?? requestedTestMinutes);
+  const me = players.find((p) => p.id === sessionId);
 
   const difficultyOptions: Array<{ value: Difficulty; label: string }> = [
     { value: 'casual', label: 'Casual' },
@@ -497,21 +562,35 @@ function renderLobby(players: LobbyPlayer[], running: boolean, canStart: boolean
       </label>
 
       <label>難易度
-        <select id="difficulty-select">
-          ${difficultyOptions.map((o) => `<option value="${o.value}">${o.label}</option>`).join('')}
+        <select id="difficulty-select" ${settingsLocked ? 'disabled' : ''}>
+          ${difficultyOptions
+            .map((o) => `<option value="${o.value}" ${o.value === settings.difficulty ? 'selected' : ''}>${o.label}</option>`)
+            .join('')}
         </select>
       </label>
 
       <label>AIプレイヤー数（0-100）
-        <input id="ai-count" type="number" min="0" max="100" value="${requestedAiCount}" />
+        <input id="ai-count" type="number" min="0" max="100" value="${shownAiCount}" ${settingsLocked ? 'disabled' : ''} />
       </label>
 
       <label>テスト時間（分, 1-10）
-        <input id="test-minutes" type="number" min="1" max="10" value="${requestedTestMinutes}" />
+        <input id="test-minutes" type="number" min="1" max="10" value="${shownMinutes}" ${settingsLocked ? 'disabled' : ''} />
+      </label>
+
+      <label>
+        <input id="require-ready" type="checkbox" ${settings.requireReady ? 'checked' : ''} ${settingsLocked ? 'disabled' : ''} />
+        全員の準備完了を待って開始
       </label>
 
       <button id="save-profile">設定を保存</button>
+      ${
+        me && !me.spectator && !isHost && !running
+          ? `<button id="toggle-ready" type="button">${me.ready ? '準備解除' : '準備完了'}</button>`
+          : ''
+      }
       <button id="start-game" ${isHost && canStart && !running ? '' : 'disabled'}>${running ? '進行中' : 'テスト開始'}</button>
+      <button id="toggle-queue" type="button">${queueStatus ? 'マッチング取消' : 'マッチング参加'}</button>
+      <p id="queue-status" class="muted">${formatQueueStatus()}</p>
       <p class="muted">room: ${escapeHtml(roomId)}</p>
       <p class="muted">${lobbyMessage || 'Host が開始します。観戦者は進行中でも接続可能です。'}</p>
 
@@ -525,10 +604,18 @@ function renderLobby(players: LobbyPlayer[], running: boolean, canStart: boolean
               p.spectator ? '[観戦]' : '[参加]',
               p.connected ? '' : '(切断)',
               p.ai ? '[AI代行]' : '',
+              p.muted ? '[ミュート]' : '',
+              p.ready && !p.spectator ? '[準備OK]' : '',
             ]
               .filter(Boolean)
               .join(' ');
-            return `<li>${escapeHtml(p.name)} ${tags}</li>`;
+            const moderation =
+              isHost && p.id !== sessionId
+                ? ` <button type="button" data-mute="${escapeHtml(p.id)}" data-muted="${p.muted ? '1' : '0'}">${p.muted ? 'ミュート解除' : 'ミュート'}</button>` +
+                  ` <button type="button" data-kick="${escapeHtml(p.id)}">キック</button>` +
+                  (p.connected ? ` <button type="button" data-host="${escapeHtml(p.id)}">ホスト委譲</button>` : '')
+                : '';
+            return `<li>${escapeHtml(p.name)} ${tags}${moderation}</li>`;
           })
           .join('')}
       </ul>
@@ -537,13 +624,60 @@ function renderLobby(players: LobbyPlayer[], running: boolean, canStart: boolean
       <div id="ranking-board">${renderRankingBoard()}</div>
 
       <p class="hint">AI-onlyテスト: 観戦モード + AI人数(2/5など) で開始</p>
-      <p class="hint">プレイヤー操作: 方向キー/WASD, 覚醒: Space/E/Enter, ピン: G(注目)/V(危険)/B(救助)</p>
+      <p class="hint">プレイヤー操作: 方向キー/WASD, 覚醒: Space/E/Enter, ピン: G(注目)/V(危険)/B(救助)/N(スイッチ)/M(ボス), チャット: T</p>
     </div>
   `;
 
   const saveProfile = document.getElementById('save-profile');
   const startButton = document.getElementById('start-game');
 
+  lobby.querySelectorAll<HTMLButtonElement>('button[data-mute]').forEach((button) => {
+    button.addEventListener('click', () => {
+      send({ type: 'chat_mute', playerId: button.dataset.mute ?? '', muted: button.dataset.muted !== '1' });
+    });
+  });
+  lobby.querySelectorAll<HTMLButtonElement>('button[data-kick]').forEach((button) => {
+    button.addEventListener('click', () => {
+      send({ type: 'kick', playerId: button.dataset.kick ?? '' });
+    });
+  });
+  lobby.querySelectorAll<HTMLButtonElement>('button[data-host]').forEach((button) => {
+    button.addEventListener('click', () => {
+      send({ type: 'host_transfer', playerId: button.dataset.host ?? '' });
+    });
+  });
+  document.getElementById('toggle-queue')?.addEventListener('click', () => {
+    if (queueStatus) {
+      send({ type: 'queue_leave' });
+      return;
+    }
+    const select = document.getElementById('difficulty-select') as HTMLSelectElement | null;
+    send({ type: 'queue_join', difficulty: (select?.value as Difficulty) ?? settings.difficulty });
+  });
+  document.getElementById('toggle-ready')?.addEventListener('click', () => {
+    send({ type: 'ready', ready: !me?.ready });
+  });
+  if (!settingsLocked) {
+    const sendSettings = (): void => {
+      const select = document.getElementById('difficulty-select') as HTMLSelectElement | null;
+      const aiInput = document.getElementById('ai-count') as HTMLInputElement | null;
+      const minutesInput = document.getElementById('test-minutes') as HTMLInputElement | null;
+      const requireReady = document.getElementById('require-ready') as HTMLInputElement | null;
+      requestedAiCount = normalizeNumber(aiInput?.value ?? '', requestedAiCount, 0, 100);
+      requestedTestMinutes = normalizeNumber(minutesInput?.value ?? '', requestedTestMinutes, 1, 10);
+      send({
+        type: 'lobby_settings',
+        difficulty: (select?.value as Difficulty) ?? settings.difficulty,
+        aiPlayerCount: requestedAiCount,
+        timeLimitMinutes: requestedTestMinutes,
+        requireReady: requireReady?.checked ?? settings.requireReady,
+      });
+    };
+    for (const id of ['difficulty-select', 'ai-count', 'test-minutes', 'require-ready']) {
+      document.getElementById(id)?.addEventListener('change', sendSettings);
+    }
+  }
+
   saveProfile?.addEventListener('click', () => {
     const nameInput = document.getElementById('name-input') as HTMLInputElement | null;
     const roomInput = document.getElementById('room-input') as HTMLInputElement | null;
@@ -592,6 +726,25 @@ function renderLobby(players: LobbyPlayer[], running: boolean, canStart: boolean
   });
 }
 
+function formatQueueStatus(): string {
+  if (!queueStatus) {
+    return '';
+  }
+  const etaSec = Math.ceil((queueStatus.etaMs ?? 0) / 1000);
+  return `マッチング待機中: ${queueStatus.position ?? '-'}/${queueStatus.queueSize ?? '-'} 位 (目安 ${etaSec}秒)`;
+}
+
+function updateQueueStatusView(): void {
+  const status = document.getElementById('queue-status');
+  if (status) {
+    status.textContent = formatQueueStatus();
+  }
+  const button = document.getElementById('toggle-queue');
+  if (button) {
+    button.textContent = queueStatus ? 'マッチング取消' : 'マッチング参加';
+  }
+}
+
 function showResult(): void {
   if (!summary) {
     return;
@@ -742,6 +895,8 @@ function wireKeyboard(): void {
     g: 'focus',
     v: 'danger',
     b: 'help',
+    n: 'gate',
+    m: 'boss',
   };
   const pressedDirs: Array<'up' | 'down' | 'left' | 'right'> = [];
 
@@ -755,7 +910,7 @@ function wireKeyboard(): void {
       if (localPlayerPrediction) {
         localPlayerPrediction.desiredDir = nextDir;
       }
-      send({ type: 'input', dir: nextDir });
+      sendInput({ dir: nextDir });
     }
   };
 
@@ -763,6 +918,11 @@ function wireKeyboard(): void {
     const rawKey = event.key;
     const key = rawKey.length === 1 ? rawKey.toLowerCase() : rawKey;
     const typingInForm = isTypingInFormElement(event.target);
+    if (key === 't' && !typingInForm) {
+      event.preventDefault();
+      promptChat();
+      return;
+    }
 
     if (isSpectator) {
       if (typingInForm) {
@@ -812,7 +972,7 @@ function wireKeyboard(): void {
     }
 
     if (key === ' ' || key === 'e' || key === 'Enter') {
-      send({ type: 'input', awaken: true });
+      sendInput({ awaken: true });
     }
   });
 
@@ -856,13 +1016,13 @@ function wireTouchControls(): void {
       if (localPlayerPrediction) {
         localPlayerPrediction.desiredDir = dir;
       }
-      send({ type: 'input', dir });
+      sendInput({ dir });
       return;
     }
 
     const action = target.getAttribute('data-action');
     if (action === 'awaken') {
-      send({ type: 'input', awaken: true });
+      sendInput({ awaken: true });
     }
   });
 
@@ -897,6 +1057,29 @@ function placePing(kind: PingType): void {
   });
 }
 
+function promptChat(): void {
+  const channel: ChatChannel = isSpectator ? 'spectator' : snapshot ? 'team' : 'lobby';
+  const text = window.prompt(`チャット (${chatChannelLabel(channel)})`);
+  if (!text || text.trim().length === 0) {
+    return;
+  }
+  send({ type: 'chat', channel, text });
+}
+
+function chatChannelLabel(channel: ChatChannel): string {
+  if (channel === 'team') {
+    return 'チーム';
+  }
+  if (channel === 'spectator') {
+    return '観戦';
+  }
+  return 'ロビー';
+}
+
+function formatChatLine(chat: ChatMessageView): string {
+  return `[${chatChannelLabel(chat.channel)}] ${chat.senderName}: ${chat.text}`;
+}
+
 function cycleSpectatorTarget(delta = 1): void {
   if (!snapshot) {
     return;
@@ -1538,7 +1721,7 @@ function updateHud(): void {
       <p>残り時間: ${formatMs(snapshot.timeLeftMs)}</p>
       <p>ゴースト: ${ghosts} / フルーツ: ${fruits}</p>
       <p>ダウン: ${downCount}</p>
-      <p>ピン: ${snapshot.pings.length} (G:注目 / V:危険 / B:救助)</p>
+      <p>ピン: ${snapshot.pings.length} (G:注目 / V:危険 / B:救助 / N:スイッチ / M:ボス)</p>
       ${meLine}
       <h4>イベント</h4>
       <ul>${logs.slice(-8).map((line) => `<li>${escapeHtml(line)}</li>`).join('')}</ul>
@@ -2485,6 +2668,8 @@ function syncLocalPlayerPredictionFromSnapshot(nextSnapshot: Snapshot): void {
 
   const nowMs = performance.now();
   const desiredDir = currentDir !== 'none' ? currentDir : me.dir;
+  // The server has not applied our latest input yet, so keep predicting with it.
+  const inputPending = (me.lastInputSeq ?? 0) < inputSeq;
   if (!localPlayerPrediction) {
     localPlayerPrediction = {
       x: me.x,
@@ -2510,7 +2695,7 @@ function syncLocalPlayerPredictionFromSnapshot(nextSnapshot: Snapshot): void {
   if (shouldHardSnap) {
     localPlayerPrediction.x = me.x;
     localPlayerPrediction.y = me.y;
-    localPlayerPrediction.dir = me.dir;
+    localPlayerPrediction.dir = inputPending ? desiredDir : me.dir;
     localPlayerPrediction.moveBuffer = 0;
   }
 
@@ -2767,6 +2952,11 @@ function send(message: ClientMessage): void {
   ws.send(JSON.stringify(message));
 }
 
+function sendInput(input: { dir?: Direction; awaken?: boolean }): void {
+  inputSeq += 1;
+  send({ type: 'input', ...input, seq: inputSeq, clientTimeMs: Math.floor(performance.now()) });
+}
+
 function resize(): void {
   const hudWidth = window.innerWidth > 1080 ? 330 : 0;
   canvas.width = window.innerWidth - hudWidth;
@@ -2838,6 +3028,12 @@ function pingKindLabel(kind: PingType): string {
   if (kind === 'help') {
     return '救助';
   }
+  if (kind === 'gate') {
+    return 'スイッチ';
+  }
+  if (kind === 'boss') {
+    return 'ボス';
+  }
   return kind;
 }
 
@@ -2851,6 +3047,12 @@ function pingKindColor(kind: PingType): { r: number; g: number; b: number } {
   if (kind === 'help') {
     return { r: 146, g: 255, b: 170 };
   }
+  if (kind === 'gate') {
+    return { r: 255, g: 214, b: 102 };
+  }
+  if (kind === 'boss') {
+    return { r: 214, g: 128, b: 255 };
+  }
   return { r: 218, g: 222, b: 233 };
 }
 
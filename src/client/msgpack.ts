@@ -0,0 +1,120 @@
+// Minimal MessagePack decoder for the server's binary `state` / `game_init` frames.
+// Supports the subset produced by rmp-serde for JSON-shaped values (no ext types).
+
+const textDecoder = new TextDecoder();
+
+export function decodeMsgpack(bytes: Uint8Array): unknown {
+  const reader = new Reader(bytes);
+  const value = reader.read();
+  if (reader.offset !== bytes.length) {
+    throw new Error('msgpack: trailing bytes');
+  }
+  return value;
+}
+
+class Reader {
+  offset = 0;
+  private readonly view: DataView;
+
+  constructor(private readonly bytes: Uint8Array) {
+    this.view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  }
+
+  read(): unknown {
+    const byte = this.u8();
+    if (byte <= 0x7f) {
+      return byte;
+    }
+    if (byte >= 0xe0) {
+      return byte - 0x100;
+    }
+    if ((byte & 0xf0) === 0x80) {
+      return this.map(byte & 0x0f);
+    }
+    if ((byte & 0xf0) === 0x90) {
+      return this.array(byte & 0x0f);
+    }
+    if ((byte & 0xe0) === 0xa0) {
+      return this.str(byte & 0x1f);
+    }
+
+    switch (byte) {
+      case 0xc0:
+        return null;
+      case 0xc2:
+        return false;
+      case 0xc3:
+        return true;
+      case 0xca:
+        return this.take(4, (offset) => this.view.getFloat32(offset));
+      case 0xcb:
+        return this.take(8, (offset) => this.view.getFloat64(offset));
+      case 0xcc:
+        return this.u8();
+      case 0xcd:
+        return this.take(2, (offset) => this.view.getUint16(offset));
+      case 0xce:
+        return this.take(4, (offset) => this.view.getUint32(offset));
+      case 0xcf:
+        return this.take(8, (offset) => Number(this.view.getBigUint64(offset)));
+      case 0xd0:
+        return this.take(1, (offset) => this.view.getInt8(offset));
+      case 0xd1:
+        return this.take(2, (offset) => this.view.getInt16(offset));
+      case 0xd2:
+        return this.take(4, (offset) => this.view.getInt32(offset));
+      case 0xd3:
+        return this.take(8, (offset) => Number(this.view.getBigInt64(offset)));
+      case 0xd9:
+        return this.str(this.u8());
+      case 0xda:
+        return this.str(this.take(2, (offset) => this.view.getUint16(offset)));
+      case 0xdb:
+        return this.str(this.take(4, (offset) => this.view.getUint32(offset)));
+      case 0xdc:
+        return this.array(this.take(2, (offset) => this.view.getUint16(offset)));
+      case 0xdd:
+        return this.array(this.take(4, (offset) => this.view.getUint32(offset)));
+      case 0xde:
+        return this.map(this.take(2, (offset) => this.view.getUint16(offset)));
+      case 0xdf:
+        return this.map(this.take(4, (offset) => this.view.getUint32(offset)));
+      default:
+        throw new Error(`msgpack: unsupported type 0x${byte.toString(16)}`);
+    }
+  }
+
+  private u8(): number {
+    return this.take(1, (offset) => this.view.getUint8(offset));
+  }
+
+  private take<T>(size: number, read: (offset: number) => T): T {
+    if (this.offset + size > this.bytes.length) {
+      throw new Error('msgpack: unexpected end of data');
+    }
+    const value = read(this.offset);
+    this.offset += size;
+    return value;
+  }
+
+  private str(length: number): string {
+    return this.take(length, (offset) => textDecoder.decode(this.bytes.subarray(offset, offset + length)));
+  }
+
+  private array(length: number): unknown[] {
+    const items: unknown[] = [];
+    for (let idx = 0; idx < length; idx += 1) {
+      items.push(this.read());
+    }
+    return items;
+  }
+
+  private map(length: number): Record<string, unknown> {
+    const result: Record<string, unknown> = {};
+    for (let idx = 0; idx < length; idx += 1) {
+      const key = this.read();
+      result[String(key)] = this.read();
+    }
+    return result;
+  }
+}
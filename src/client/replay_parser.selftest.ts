@@ -193,6 +193,7 @@ function testReplayModelUtilities(): void {
         score: 100,
         connected: true,
         ai: false,
+        bot: false,
         speedBuffUntil: 0,
         powerUntil: 0,
         downSince: null,
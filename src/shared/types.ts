@@ -4,8 +4,11 @@ export type GhostType = 'random' | 'chaser' | 'patrol' | 'pincer' | 'invader' |
 export type SectorType = 'normal' | 'narrow' | 'plaza' | 'dark' | 'fast' | 'nest';
 export type FruitType = 'cherry' | 'strawberry' | 'orange' | 'apple' | 'key' | 'grape';
 export type Difficulty = 'casual' | 'normal' | 'hard' | 'nightmare';
-export type GameOverReason = 'victory' | 'timeout' | 'all_down' | 'collapse';
-export type PingType = 'focus' | 'danger' | 'help';
+export type AiSkill = 'beginner' | 'intermediate' | 'advanced' | 'expert';
+export type GameOverReason = 'victory' | 'timeout' | 'all_down' | 'collapse' | 'shutdown';
+export type ChatChannel = 'lobby' | 'team' | 'spectator';
+
+export type PingType = 'focus' | 'danger' | 'help' | 'gate' | 'boss';
 
 export interface Vec2 {
   x: number;
@@ -17,10 +20,31 @@ export interface LobbyPlayer {
   name: string;
   connected: boolean;
   ai: boolean;
+  bot: boolean;
   spectator: boolean;
+  muted: boolean;
+  ready: boolean;
   isHost: boolean;
 }
 
+export interface LobbySettings {
+  difficulty: Difficulty;
+  aiPlayerCount: number;
+  timeLimitMinutes: number | null;
+  aiSkill: AiSkill;
+  requireReady: boolean;
+}
+
+export interface QueueStatus {
+  queued: boolean;
+  position?: number;
+  queueSize?: number;
+  waitedMs?: number;
+  etaMs?: number;
+  difficulty?: Difficulty | null;
+  partySize?: number | null;
+}
+
 export interface GateState {
   id: string;
   a: Vec2;
@@ -81,9 +105,12 @@ export interface PlayerView {
   score: number;
   connected: boolean;
   ai: boolean;
+  bot: boolean;
   speedBuffUntil: number;
   powerUntil: number;
   downSince: number | null;
+  lastInputSeq?: number;
+  lastInputClientMs?: number;
 }
 
 export interface GhostView {
@@ -111,10 +138,20 @@ export interface PingView {
   x: number;
   y: number;
   kind: PingType;
+  targetId?: string;
   createdAtMs: number;
   expiresAtMs: number;
 }
 
+export interface ChatMessageView {
+  id: string;
+  senderId: string;
+  senderName: string;
+  channel: ChatChannel;
+  text: string;
+  sentAtMs: number;
+}
+
 export interface TimelineEvent {
   atMs: number;
   label: string;
@@ -137,6 +174,7 @@ export type RuntimeEvent =
 
 export interface Snapshot {
   tick: number;
+  frame?: number;
   nowMs: number;
   timeLeftMs: number;
   captureRatio: number;
@@ -158,6 +196,21 @@ export interface ScoreEntry {
   ghosts: number;
   rescues: number;
   captures: number;
+  bot: boolean;
+}
+
+export interface BotObservation {
+  tick: number;
+  nowMs: number;
+  timeLeftMs: number;
+  captureRatio: number;
+  self: PlayerView;
+  origin: Vec2;
+  radius: number;
+  grid: string[];
+  ghosts: GhostView[];
+  players: PlayerView[];
+  sectors: SectorState[];
 }
 
 export interface AwardWinner {
@@ -199,10 +252,42 @@ export interface GameSummary {
 }
 
 export type ClientMessage =
-  | { type: 'hello'; name: string; reconnectToken?: string; spectator?: boolean; roomId?: string }
-  | { type: 'lobby_start'; difficulty?: Difficulty; aiPlayerCount?: number; timeLimitMinutes?: number }
-  | { type: 'input'; dir?: Direction; awaken?: boolean }
-  | { type: 'place_ping'; kind: PingType }
+  | {
+      type: 'hello';
+      name: string;
+      reconnectToken?: string;
+      spectator?: boolean;
+      roomId?: string;
+      bot?: boolean;
+      botToken?: string;
+      protocolVersion?: number;
+      capabilities?: string[];
+    }
+  | {
+      type: 'lobby_start';
+      difficulty?: Difficulty;
+      aiPlayerCount?: number;
+      timeLimitMinutes?: number | null;
+      aiSkill?: AiSkill;
+      aiSkills?: AiSkill[];
+    }
+  | {
+      type: 'lobby_settings';
+      difficulty?: Difficulty;
+      aiPlayerCount?: number;
+      timeLimitMinutes?: number | null;
+      aiSkill?: AiSkill;
+      requireReady?: boolean;
+    }
+  | { type: 'ready'; ready?: boolean }
+  | { type: 'host_transfer'; playerId: string }
+  | { type: 'input'; dir?: Direction; awaken?: boolean; seq?: number; clientTimeMs?: number }
+  | { type: 'place_ping'; kind: PingType; x?: number; y?: number; targetId?: string }
+  | { type: 'chat'; channel?: ChatChannel; text: string }
+  | { type: 'chat_mute'; playerId: string; muted?: boolean }
+  | { type: 'kick'; playerId: string }
+  | { type: 'queue_join'; difficulty?: Difficulty; partySize?: number }
+  | { type: 'queue_leave' }
   | { type: 'ping'; t: number };
 
 export type ServerMessage =
@@ -212,6 +297,11 @@ export type ServerMessage =
       reconnectToken: string;
       isHost: boolean;
       isSpectator: boolean;
+      isBot: boolean;
+      chatHistory: ChatMessageView[];
+      protocolVersion: number;
+      serverProtocolVersion: number;
+      capabilities: string[];
     }
   | {
       type: 'lobby';
@@ -220,6 +310,7 @@ export type ServerMessage =
       canStart: boolean;
       running: boolean;
       spectatorCount: number;
+      settings: LobbySettings;
       note?: string;
     }
   | {
@@ -235,14 +326,29 @@ export type ServerMessage =
       type: 'state';
       snapshot: Snapshot;
     }
+  | {
+      type: 'bot_observation';
+      observation: BotObservation;
+      deadlineMs: number;
+    }
   | {
       type: 'game_over';
       summary: GameSummary;
     }
+  | {
+      type: 'chat';
+      message: ChatMessageView;
+    }
+  | ({ type: 'queue_status' } & QueueStatus)
   | {
       type: 'error';
       message: string;
     }
+  | {
+      type: 'server_shutdown';
+      message: string;
+      resumable: boolean;
+    }
   | {
       type: 'pong';
       t: number;